@@ -0,0 +1,474 @@
+use iced::Subscription;
+use iced::futures::channel::mpsc::{self, UnboundedSender};
+use iced::futures::{SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::fmt;
+use std::fs;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc::{SyncSender, sync_channel};
+use std::thread;
+use std::time::Duration;
+
+use super::http;
+use crate::export::front_matter;
+use crate::notes::folders;
+use crate::notes::notes::{Note, NoteId, Notes};
+use crate::storage::storage;
+
+/// How often the listener checks whether the API was switched off.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a client may take to send its request, and the app to answer it.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// A script asked for something; answer it with [`Call::answer`].
+    Called(Call),
+    /// The API could not be started.
+    Failed(String),
+}
+
+/// A request from a script, waiting for the app to answer it.
+#[derive(Clone)]
+pub struct Call {
+    request: Request,
+    reply: SyncSender<(u16, Value)>,
+}
+
+impl fmt::Debug for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Call")
+            .field("request", &self.request)
+            .finish()
+    }
+}
+
+impl Call {
+    /// Carries out the request on `notes` and sends the result back to the script.
+    /// Returns the existing page it rewrote, which editors showing it must pick up.
+    pub fn answer(self, notes: &mut Notes) -> Option<NoteId> {
+        let rewritten = match self.request {
+            Request::Update(id, _) => Some(id),
+            _ => None,
+        };
+        let response = self.request.carry_out(notes);
+        let rewritten = rewritten.filter(|_| response.0 == 200);
+        // The script may have given up waiting; the change stands either way.
+        let _ = self.reply.send(response);
+        rewritten
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Request {
+    List { archived: bool },
+    Search(String),
+    Get(NoteId),
+    Create(NewNote),
+    Update(NoteId, Changes),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NewNote {
+    title: String,
+    body: String,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Changes {
+    title: Option<String>,
+    body: Option<String>,
+    tags: Option<Vec<String>>,
+}
+
+impl Request {
+    fn carry_out(self, notes: &mut Notes) -> (u16, Value) {
+        match self {
+            Request::List { archived } => {
+                let list = notes
+                    .iter()
+                    .filter(|note| archived || !note.archived)
+                    .map(|note| summary(notes, note))
+                    .collect::<Vec<_>>();
+                (200, json!(list))
+            }
+            Request::Search(query) => {
+                let list = search(notes, &query)
+                    .into_iter()
+                    .map(|note| summary(notes, note))
+                    .collect::<Vec<_>>();
+                (200, json!(list))
+            }
+            Request::Get(id) => match notes.find(id) {
+                Some(note) => (200, full(notes, note)),
+                None => not_found(id),
+            },
+            Request::Create(new) => {
+                let index = notes.upsert(Note::new(new.title, new.body, new.tags), None);
+                (201, full(notes, notes.get(index).expect("just added")))
+            }
+            Request::Update(id, changes) => {
+                let Some(index) = notes.position(id) else {
+                    return not_found(id);
+                };
+                let mut note = notes.get(index).cloned().expect("found pages exist");
+                if let Some(title) = changes.title {
+                    note.title = title;
+                }
+                if let Some(body) = changes.body {
+                    note.body = body;
+                }
+                if let Some(tags) = changes.tags {
+                    note.tags = tags;
+                }
+                notes.upsert(note, Some(index));
+                (200, full(notes, notes.get(index).expect("just saved")))
+            }
+        }
+    }
+}
+
+/// Serves the scripting API on 127.0.0.1 for as long as the subscription is active.
+///
+/// The port and the token scripts must send as `Authorization: Bearer TOKEN` are
+/// written to `api.json` in the data directory, and change each time the API starts.
+pub fn subscription() -> Subscription<Message> {
+    Subscription::run(serve)
+}
+
+/// Where scripts find the API's address and token.
+fn info_path() -> PathBuf {
+    storage::data_dir().join("api.json")
+}
+
+fn serve() -> impl Stream<Item = Message> {
+    iced::stream::channel(16, |mut output| async move {
+        let (sender, mut calls) = mpsc::unbounded();
+        if let Err(error) = start(sender) {
+            let _ = output.send(Message::Failed(error)).await;
+            return;
+        }
+
+        while let Some(call) = calls.next().await {
+            if output.send(Message::Called(call)).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+fn start(calls: UnboundedSender<Call>) -> Result<(), String> {
+    let failed = |error: std::io::Error| format!("Could not start the scripting API: {error}");
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(failed)?;
+    listener.set_nonblocking(true).map_err(failed)?;
+    let port = listener.local_addr().map_err(failed)?.port();
+
+    let token = token()?;
+    let path = info_path();
+    let info = json!({ "url": format!("http://127.0.0.1:{port}"), "token": token });
+    storage::save_private_json(&path, &info).map_err(|error| error.to_string())?;
+
+    thread::spawn(move || {
+        // Once the app drops its end, the API was switched off or the app is closing.
+        while !calls.is_closed() {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let calls = calls.clone();
+                    let token = token.clone();
+                    thread::spawn(move || handle(stream, &token, &calls));
+                }
+                // Usually `WouldBlock`: nobody is connecting right now.
+                Err(_) => thread::sleep(POLL_INTERVAL),
+            }
+        }
+        let _ = fs::remove_file(&path);
+    });
+
+    Ok(())
+}
+
+fn handle(stream: TcpStream, token: &str, calls: &UnboundedSender<Call>) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(TIMEOUT));
+
+    let (status, body) = match http::read(&stream) {
+        Err(error) => (400, error_json(&error)),
+        Ok(request) if !authorized(&request, token) => {
+            (401, error_json("missing or wrong token; see api.json"))
+        }
+        Ok(request) => match route(&request) {
+            Ok(request) => dispatch(request, calls),
+            Err((status, error)) => (status, error_json(&error)),
+        },
+    };
+    http::respond(&stream, status, &body);
+}
+
+/// Hands `request` to the app and waits for its answer.
+fn dispatch(request: Request, calls: &UnboundedSender<Call>) -> (u16, Value) {
+    let (reply, answer) = sync_channel(1);
+    if calls.unbounded_send(Call { request, reply }).is_err() {
+        return (503, error_json("faitout is shutting down"));
+    }
+    answer
+        .recv_timeout(TIMEOUT)
+        .unwrap_or_else(|_| (503, error_json("faitout did not answer in time")))
+}
+
+fn route(request: &http::Request) -> Result<Request, (u16, String)> {
+    let segments = request
+        .path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let method = request.method.as_str();
+
+    match segments.as_slice() {
+        ["notes"] => match method {
+            "GET" => Ok(match request.query("q") {
+                Some(query) => Request::Search(query.to_string()),
+                None => Request::List {
+                    archived: request.query("archived") == Some("true"),
+                },
+            }),
+            "POST" => parse_body(request).map(Request::Create),
+            _ => Err((405, format!("{method} is not supported on /notes"))),
+        },
+        ["notes", id] => {
+            let id = id
+                .parse::<u64>()
+                .map(NoteId::from_raw)
+                .map_err(|_| (404, format!("no note with id {id}")))?;
+            match method {
+                "GET" => Ok(Request::Get(id)),
+                "PATCH" | "PUT" => parse_body(request).map(|changes| Request::Update(id, changes)),
+                _ => Err((405, format!("{method} is not supported on /notes/ID"))),
+            }
+        }
+        _ => Err((404, format!("no such endpoint: {}", request.path))),
+    }
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(request: &http::Request) -> Result<T, (u16, String)> {
+    serde_json::from_slice(&request.body)
+        .map_err(|error| (400, format!("invalid JSON body: {error}")))
+}
+
+fn authorized(request: &http::Request, token: &str) -> bool {
+    let given = request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default()
+        .trim();
+    // Compare every byte so the time taken does not reveal how much of it matched.
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// A fresh secret that clients must present, so only those able to read the app's files get in.
+pub fn token() -> Result<String, String> {
+    let mut bytes = [0; 16];
+    getrandom::fill(&mut bytes)
+        .map_err(|error| format!("Could not generate an access token: {error}"))?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+fn not_found(id: NoteId) -> (u16, Value) {
+    (404, error_json(&format!("no note with id {}", id.raw())))
+}
+
+fn error_json(error: &str) -> Value {
+    json!({ "error": error })
+}
+
+/// The pages whose title, text or tags contain every word of `query`, ignoring case.
+pub fn search<'a>(notes: &'a Notes, query: &str) -> Vec<&'a Note> {
+    let query = query.to_lowercase();
+    let words = query.split_whitespace().collect::<Vec<_>>();
+    notes
+        .iter()
+        .filter(|note| {
+            let text = format!("{}\n{}\n{}", note.title, note.body, note.tags.join(" "));
+            let text = text.to_lowercase();
+            words.iter().all(|word| text.contains(word))
+        })
+        .collect()
+}
+
+/// A page's details for scripts, without its text.
+pub fn summary(notes: &Notes, note: &Note) -> Value {
+    json!({
+        "id": note.id.raw(),
+        "title": note.title,
+        "tags": note.tags,
+        "color": note.color.name(),
+        "folder": note.folder.map(|folder| folders::path(notes.folders(), folder)),
+        "pinned": note.pinned,
+        "archived": note.archived,
+        "created": front_matter::format_timestamp(note.created),
+        "updated": front_matter::format_timestamp(note.updated),
+    })
+}
+
+/// A page's details for scripts, with its text and attached files.
+pub fn full(notes: &Notes, note: &Note) -> Value {
+    let mut value = summary(notes, note);
+    value["body"] = json!(note.body);
+    value["attachments"] = json!(
+        note.attachments
+            .iter()
+            .map(|attachment| json!({ "name": attachment.name, "file": attachment.file }))
+            .collect::<Vec<_>>()
+    );
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::storage::TestDataDir;
+    use std::io::{Read, Write};
+
+    /// Starts the API with a thread answering its calls, returning its port and token,
+    /// and the data directory to keep while it runs.
+    fn serve_for_test() -> (u16, String, TestDataDir) {
+        // Every server writes api.json; hold it until this one's is read back.
+        static STARTING: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let _starting = STARTING
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let data = storage::use_test_data_dir();
+        let (sender, mut calls) = mpsc::unbounded();
+        start(sender).expect("the API starts");
+
+        thread::spawn(move || {
+            let mut notes = Notes::open("api-tests");
+            loop {
+                match calls.try_next() {
+                    Ok(Some(call)) => {
+                        call.answer(&mut notes);
+                    }
+                    Ok(None) => break,
+                    Err(_) => thread::sleep(Duration::from_millis(5)),
+                }
+            }
+        });
+
+        let info = storage::load_json::<Value>(&info_path())
+            .expect("api.json is readable")
+            .expect("api.json is written");
+        let url = info["url"].as_str().expect("api.json has the url");
+        let port = url.rsplit(':').next().and_then(|port| port.parse().ok());
+        let token = info["token"].as_str().expect("api.json has the token");
+        (port.expect("the url has a port"), token.to_string(), data)
+    }
+
+    /// Sends `raw` as it is and returns the status and JSON body of the response.
+    fn exchange(port: u16, raw: &[u8]) -> (u16, Value) {
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).expect("connects");
+        stream.write_all(raw).expect("sends the request");
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .expect("reads the response");
+
+        let status = response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .expect("the response has a status");
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body)
+            .unwrap_or("");
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    fn call(
+        port: u16,
+        method: &str,
+        path: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (u16, Value) {
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let authorization = token
+            .map(|token| format!("Authorization: Bearer {token}\r\n"))
+            .unwrap_or_default();
+        let raw = format!(
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\n{authorization}\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        exchange(port, raw.as_bytes())
+    }
+
+    #[test]
+    fn scripts_read_and_write_pages_with_the_token() {
+        let (port, token, _data) = serve_for_test();
+        let token = Some(token.as_str());
+
+        assert_eq!(call(port, "GET", "/notes", None, None).0, 401);
+        assert_eq!(call(port, "GET", "/notes", Some("wrong"), None).0, 401);
+
+        let new = json!({ "title": "Groceries", "body": "Milk and eggs", "tags": ["home"] });
+        let (status, created) = call(port, "POST", "/notes", token, Some(new));
+        assert_eq!(status, 201);
+        assert_eq!(created["title"], "Groceries");
+        let id = created["id"].as_u64().expect("the page has an id");
+
+        let (status, list) = call(port, "GET", "/notes", token, None);
+        assert_eq!(status, 200);
+        assert!(
+            list.as_array()
+                .expect("a list")
+                .iter()
+                .any(|note| note["id"] == id)
+        );
+
+        let (status, found) = call(port, "GET", "/notes?q=EGGS+milk", token, None);
+        assert_eq!(status, 200);
+        assert_eq!(found.as_array().map(Vec::len), Some(1));
+        let (_, none) = call(port, "GET", "/notes?q=flour", token, None);
+        assert_eq!(none.as_array().map(Vec::len), Some(0));
+
+        let path = format!("/notes/{id}");
+        let changes = json!({ "body": "Bread" });
+        let (status, updated) = call(port, "PATCH", &path, token, Some(changes));
+        assert_eq!(status, 200);
+        assert_eq!(updated["body"], "Bread");
+        let (_, read) = call(port, "GET", &path, token, None);
+        assert_eq!(read["body"], "Bread");
+        assert_eq!(read["tags"], json!(["home"]));
+
+        let unknown = json!({ "colour": "red" });
+        assert_eq!(call(port, "PATCH", &path, token, Some(unknown)).0, 400);
+        assert_eq!(call(port, "GET", "/notes/1", token, None).0, 404);
+        assert_eq!(call(port, "DELETE", &path, token, None).0, 405);
+    }
+
+    #[test]
+    fn malformed_requests_are_refused() {
+        let (port, token, _data) = serve_for_test();
+        let head = format!("POST /notes HTTP/1.1\r\nAuthorization: Bearer {token}\r\n");
+
+        let bad_length = format!("{head}Content-Length: lots\r\n\r\n");
+        assert_eq!(exchange(port, bad_length.as_bytes()).0, 400);
+
+        let too_long = format!("{head}Content-Length: {}\r\n\r\n", http::MAX_BODY + 1);
+        assert_eq!(exchange(port, too_long.as_bytes()).0, 400);
+
+        let long_header = format!("{head}X-Padding: {}\r\n\r\n", "a".repeat(http::MAX_LINE));
+        assert_eq!(exchange(port, long_header.as_bytes()).0, 400);
+    }
+}
@@ -0,0 +1,166 @@
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+/// Largest request body the API reads, so a stray client cannot exhaust memory.
+pub(super) const MAX_BODY: usize = 4 * 1024 * 1024;
+/// Longest request line or header line the API reads.
+pub(super) const MAX_LINE: usize = 8 * 1024;
+/// Most header lines the API reads in one request.
+const MAX_HEADERS: usize = 100;
+
+/// An HTTP request, reduced to what the API looks at.
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// The value of the header `name`, which matches case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads one HTTP/1.1 request from `stream`.
+pub fn read(stream: &TcpStream) -> Result<Request, String> {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    read_line(&mut reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(String::from("malformed request line"));
+    };
+    let method = method.to_string();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = decode(path, false);
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(key, true), decode(value, true))
+        })
+        .collect();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if read_line(&mut reader, &mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(String::from("too many headers"));
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut request = Request {
+        method,
+        path,
+        query,
+        headers,
+        body: Vec::new(),
+    };
+    let length = request
+        .header("Content-Length")
+        .map(|length| length.parse::<usize>())
+        .transpose()
+        .map_err(|_| String::from("malformed Content-Length"))?
+        .unwrap_or(0);
+    if length > MAX_BODY {
+        return Err(String::from("request body too large"));
+    }
+    request.body = vec![0; length];
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|error| error.to_string())?;
+
+    Ok(request)
+}
+
+/// Reads one line into `line`, refusing lines longer than [`MAX_LINE`].
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<usize, String> {
+    let read = reader
+        .take(MAX_LINE as u64 + 1)
+        .read_line(line)
+        .map_err(|error| error.to_string())?;
+    if read > MAX_LINE {
+        return Err(String::from("request line or header too long"));
+    }
+    Ok(read)
+}
+
+/// Writes a JSON response with `status` and closes the exchange.
+pub fn respond(mut stream: &TcpStream, status: u16, body: &Value) {
+    let body = serde_json::to_string_pretty(body).unwrap_or_default();
+    let response = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        reason(status),
+        body.len()
+    );
+    // The client may already have gone away, and then there is nobody left to tell.
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.flush();
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "Error",
+    }
+}
+
+/// Undoes percent-encoding, and in query strings, `+` for spaces.
+fn decode(text: &str, plus_is_space: bool) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let hex = |offset: usize| {
+            bytes
+                .get(index + offset)
+                .and_then(|byte| (*byte as char).to_digit(16))
+        };
+        match (bytes[index], hex(1), hex(2)) {
+            (b'%', Some(high), Some(low)) => {
+                decoded.push((high * 16 + low) as u8);
+                index += 2;
+            }
+            (b'+', _, _) if plus_is_space => decoded.push(b' '),
+            (byte, _, _) => decoded.push(byte),
+        }
+        index += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
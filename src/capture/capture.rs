@@ -0,0 +1,114 @@
+use iced::widget::text_editor::{self as editor_widget, Content};
+use iced::widget::{button, column, horizontal_space, row, text, text_editor, text_input};
+use iced::{Element, Length, Size, alignment::Alignment, window};
+
+/// A small window for jotting down a page without leaving what you are doing.
+#[derive(Debug, Default)]
+pub struct Capture {
+    title: String,
+    body: Content,
+    tags_input: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    TitleChanged(String),
+    BodyEdited(editor_widget::Action),
+    TagsChanged(String),
+    SavePressed,
+    CancelPressed,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Save {
+        title: String,
+        body: String,
+        tags: Vec<String>,
+    },
+    Cancel,
+}
+
+impl Capture {
+    /// How the capture window opens: small, and above other programs' windows.
+    pub fn window_settings() -> window::Settings {
+        window::Settings {
+            size: Size::new(420.0, 320.0),
+            min_size: Some(Size::new(300.0, 220.0)),
+            level: window::Level::AlwaysOnTop,
+            ..window::Settings::default()
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Option<Event> {
+        match message {
+            Message::TitleChanged(value) => {
+                self.title = value;
+                None
+            }
+            Message::BodyEdited(action) => {
+                self.body.perform(action);
+                None
+            }
+            Message::TagsChanged(value) => {
+                self.tags_input = value;
+                None
+            }
+            Message::SavePressed => {
+                let title = self.title.trim().to_string();
+                let body = self.body.text().trim_end_matches('\n').to_string();
+                if title.is_empty() && body.trim().is_empty() {
+                    return None;
+                }
+
+                let tags = self
+                    .tags_input
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                *self = Self::default();
+                Some(Event::Save { title, body, tags })
+            }
+            Message::CancelPressed => {
+                *self = Self::default();
+                Some(Event::Cancel)
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let title_input = text_input("Title", &self.title)
+            .on_input(Message::TitleChanged)
+            .on_submit(Message::SavePressed)
+            .padding(8)
+            .size(18);
+
+        let body_editor = text_editor(&self.body)
+            .placeholder("What's on your mind?")
+            .height(Length::Fill)
+            .wrapping(text::Wrapping::WordOrGlyph)
+            .on_action(Message::BodyEdited);
+
+        let tags_input = text_input("Tags (comma separated)", &self.tags_input)
+            .on_input(Message::TagsChanged)
+            .on_submit(Message::SavePressed)
+            .padding(8)
+            .size(14);
+
+        let actions = row![
+            horizontal_space(),
+            button("Cancel").on_press(Message::CancelPressed),
+            button("Save").on_press(Message::SavePressed),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center);
+
+        column![title_input, body_editor, tags_input, actions]
+            .spacing(8)
+            .padding(12)
+            .height(Length::Fill)
+            .into()
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{self, Write};
+
+use super::commands;
+use crate::notes::notebook;
+use crate::notes::notes::Notes;
+use crate::platform::platform;
+
+pub const USAGE: &str = "\
+Usage: faitout [COMMAND]
+
+Without a command, the app window opens; if the app is running already, its window
+comes to the front. The other commands work on the default notebook, or with
+--notebook NAME, on another one. With --json, they print JSON for scripts.
+
+Commands:
+  open PAGE
+      Open a page in its own window, in the running app if there is one. PAGE is a
+      page's id or title.
+  capture
+      Open the quick-capture window, in the running app if there is one.
+  list [--tag TAG] [--archived] [--json]
+      List the pages with their ids. Archived pages are left out unless --archived
+      is given.
+  show [--json] PAGE
+      Print a page. PAGE is a page's id or title.
+  add [--tag TAG]... [--json] TITLE
+      Add a page. Its text is read from standard input unless that is a terminal.
+  edit [--title TITLE] [--stdin] [--json] PAGE
+      Rename a page, or with --stdin, replace its text with standard input.
+  rm [--json] PAGE
+      Delete a page.
+  search [--json] QUERY
+      List the pages whose title, text or tags contain every word of QUERY.
+  tag [--remove] [--json] PAGE [TAG]...
+      Add tags to a page, or with --remove, take them off. Without tags, print them.
+  export [--format markdown|zip|html] [--note PAGE] [--json] OUT
+      Export the notebook as Markdown files with front matter into the directory OUT,
+      into the zip archive OUT, or as a static site into the directory OUT. With
+      --format html and --note, export a single page into the file OUT.
+  import [--from markdown|evernote|keep] [--dry-run] [--json] PATH...
+      Import a folder or zip archive of Markdown files, Evernote .enex files or a
+      Google Keep Takeout folder. With --dry-run, only show what would be imported.
+  help
+      Show this message.";
+
+/// Runs the command given on the command line, if any. Returns the exit code, or `None`
+/// when the app should start normally.
+pub fn run(args: &[String]) -> Option<i32> {
+    let (command, rest) = args.split_first()?;
+    platform::attach_console();
+
+    let result = match command.as_str() {
+        "help" | "--help" | "-h" => print(USAGE),
+        // With exactly one PAGE, the app starts instead; see `Launch::parse`.
+        "open" => Err(format!("open takes exactly one PAGE\n\n{USAGE}")),
+        "list" => commands::list(rest),
+        "show" => commands::show(rest),
+        "add" => commands::add(rest),
+        "edit" => commands::edit(rest),
+        "rm" => commands::remove(rest),
+        "search" => commands::search(rest),
+        "tag" => commands::tag(rest),
+        "export" => commands::export(rest),
+        "import" => commands::import(rest),
+        other => Err(format!("unknown command `{other}`\n\n{USAGE}")),
+    };
+
+    Some(match result {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("faitout: {error}");
+            1
+        }
+    })
+}
+
+/// Writes `text` and a newline to standard output. A reader that went away, like
+/// `head` after enough lines, is not an error.
+pub fn print(text: &str) -> Result<(), String> {
+    match writeln!(io::stdout().lock(), "{text}") {
+        Err(error) if error.kind() != io::ErrorKind::BrokenPipe => {
+            Err(format!("could not write to standard output: {error}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Opens the notebook with the given key or name, or the default one. A `read_only`
+/// notebook never writes its file, not even to fill in missing ids.
+pub fn open_notebook(name: Option<String>, read_only: bool) -> Result<Notes, String> {
+    let key = match name {
+        Some(name) => notebook::discover()
+            .into_iter()
+            .find(|notebook| {
+                notebook.key == name || notebook.name.to_lowercase() == name.to_lowercase()
+            })
+            .map(|notebook| notebook.key)
+            .ok_or_else(|| format!("no notebook called \"{name}\""))?,
+        None => notebook::DEFAULT_KEY.to_string(),
+    };
+
+    let mut notes = if read_only {
+        Notes::open_read_only(&key)
+    } else {
+        Notes::open(&key)
+    };
+    check(&mut notes)?;
+    Ok(notes)
+}
+
+/// Fails with the first error `notes` ran into, e.g. while saving.
+pub fn check(notes: &mut Notes) -> Result<(), String> {
+    match notes.take_errors().into_iter().next() {
+        Some(error) => Err(error.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Command line arguments split into `--name value` options, `--name` flags and
+/// positional values. Every command takes `--notebook NAME` and `--json`.
+pub struct Options {
+    named: Vec<(String, String)>,
+    flags: Vec<String>,
+    positional: Vec<String>,
+}
+
+impl Options {
+    pub fn parse(args: &[String], known: &[&str], flags: &[&str]) -> Result<Self, String> {
+        let mut named = Vec::new();
+        let mut set = Vec::new();
+        let mut positional = Vec::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "--notebook" || known.contains(&arg.as_str()) {
+                let value = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+                named.push((arg.clone(), value.clone()));
+            } else if arg == "--json" || flags.contains(&arg.as_str()) {
+                set.push(arg.clone());
+            } else if arg == "--" {
+                positional.extend(args.by_ref().cloned());
+            } else if arg.starts_with("--") {
+                return Err(format!("unknown option `{arg}`"));
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+
+        Ok(Self {
+            named,
+            flags: set,
+            positional,
+        })
+    }
+
+    pub fn take(&mut self, name: &str) -> Option<String> {
+        let index = self.named.iter().position(|(key, _)| key == name)?;
+        Some(self.named.remove(index).1)
+    }
+
+    /// Every value given for an option that may be repeated.
+    pub fn take_all(&mut self, name: &str) -> Vec<String> {
+        std::iter::from_fn(|| self.take(name)).collect()
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    pub fn json(&self) -> bool {
+        self.flag("--json")
+    }
+
+    /// Opens the notebook named by `--notebook`, or the default one.
+    pub fn notebook(&mut self) -> Result<Notes, String> {
+        open_notebook(self.take("--notebook"), false)
+    }
+
+    /// Opens the notebook like [`Options::notebook`], for commands that only read it.
+    pub fn read_notebook(&mut self) -> Result<Notes, String> {
+        open_notebook(self.take("--notebook"), true)
+    }
+
+    pub fn positional(&self, index: usize, name: &str) -> Result<String, String> {
+        self.positional
+            .get(index)
+            .cloned()
+            .ok_or_else(|| format!("missing {name}\n\n{USAGE}"))
+    }
+
+    /// The positional values from `index` on.
+    pub fn rest(&self, index: usize) -> &[String] {
+        self.positional.get(index..).unwrap_or_default()
+    }
+}
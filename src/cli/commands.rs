@@ -0,0 +1,413 @@
+use serde_json::{Value, json};
+use std::fs;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+
+use super::cli::{self, Options};
+use crate::api::api;
+use crate::export::export::{self, ExportFormat};
+use crate::export::html::{self, Look};
+use crate::import::import::{self, ImportSource, Plan};
+use crate::import::{enex, keep, markdown};
+use crate::notes::links;
+use crate::notes::notes::{Note, NoteId, Notes};
+use crate::settings::settings::SettingsState;
+
+pub fn list(args: &[String]) -> Result<(), String> {
+    let mut args = Options::parse(args, &["--tag"], &["--archived"])?;
+    let notes = args.read_notebook()?;
+    let tag = args.take("--tag");
+
+    let shown = notes
+        .iter()
+        .filter(|note| args.flag("--archived") || !note.archived)
+        .filter(|note| {
+            tag.as_ref().is_none_or(|tag| {
+                note.tags
+                    .iter()
+                    .any(|other| other.eq_ignore_ascii_case(tag))
+            })
+        })
+        .collect::<Vec<_>>();
+    print_list(&notes, &shown, args.json())
+}
+
+pub fn show(args: &[String]) -> Result<(), String> {
+    let mut args = Options::parse(args, &[], &[])?;
+    let page = args.positional(0, "PAGE")?;
+    let notes = args.read_notebook()?;
+    let note = &notes.get(find(&notes, &page)?).expect("found pages exist");
+
+    if args.json() {
+        return print_json(&api::full(&notes, note));
+    }
+    let mut heading = note.display_title().to_string();
+    if !note.tags.is_empty() {
+        heading = format!("{heading}\n{}", hashtags(note));
+    }
+    cli::print(&format!("{heading}\n\n{}", note.body))
+}
+
+pub fn add(args: &[String]) -> Result<(), String> {
+    let mut args = Options::parse(args, &["--tag"], &[])?;
+    let title = args.positional(0, "TITLE")?;
+    let tags = args.take_all("--tag");
+    let mut notes = args.notebook()?;
+
+    let body = if std::io::stdin().is_terminal() {
+        String::new()
+    } else {
+        read_stdin()?
+    };
+    let index = notes.upsert(Note::new(title, body, tags), None);
+    cli::check(&mut notes)?;
+
+    print_changed(&notes, index, "Added", args.json())
+}
+
+pub fn edit(args: &[String]) -> Result<(), String> {
+    let mut args = Options::parse(args, &["--title"], &["--stdin"])?;
+    let page = args.positional(0, "PAGE")?;
+    let title = args.take("--title");
+    if title.is_none() && !args.flag("--stdin") {
+        return Err(String::from("nothing to change; give --title or --stdin"));
+    }
+    let mut notes = args.notebook()?;
+    let index = find(&notes, &page)?;
+
+    let mut note = notes.get(index).cloned().expect("found pages exist");
+    if let Some(title) = title {
+        note.title = title;
+    }
+    if args.flag("--stdin") {
+        note.body = read_stdin()?;
+    }
+    notes.upsert(note, Some(index));
+    cli::check(&mut notes)?;
+
+    print_changed(&notes, index, "Saved", args.json())
+}
+
+pub fn remove(args: &[String]) -> Result<(), String> {
+    let mut args = Options::parse(args, &[], &[])?;
+    let page = args.positional(0, "PAGE")?;
+    let mut notes = args.notebook()?;
+    let index = find(&notes, &page)?;
+
+    let summary = api::summary(&notes, notes.get(index).expect("found pages exist"));
+    let note = notes.remove(index).expect("found pages exist");
+    cli::check(&mut notes)?;
+
+    if args.json() {
+        print_json(&summary)
+    } else {
+        cli::print(&format!(
+            "Deleted {}\t{}",
+            note.id.raw(),
+            note.display_title()
+        ))
+    }
+}
+
+pub fn search(args: &[String]) -> Result<(), String> {
+    let mut args = Options::parse(args, &[], &[])?;
+    let query = args.rest(0).join(" ");
+    if query.trim().is_empty() {
+        return Err(format!("missing QUERY\n\n{}", cli::USAGE));
+    }
+    let notes = args.read_notebook()?;
+
+    let found = api::search(&notes, &query);
+    print_list(&notes, &found, args.json())
+}
+
+pub fn tag(args: &[String]) -> Result<(), String> {
+    let mut args = Options::parse(args, &[], &["--remove"])?;
+    let page = args.positional(0, "PAGE")?;
+    let mut notes = args.notebook()?;
+    let index = find(&notes, &page)?;
+    let mut note = notes.get(index).cloned().expect("found pages exist");
+
+    let tags = args
+        .rest(1)
+        .iter()
+        .map(|tag| tag.trim().trim_start_matches('#').to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+    if tags.is_empty() {
+        return if args.json() {
+            print_json(&json!(note.tags))
+        } else if note.tags.is_empty() {
+            Ok(())
+        } else {
+            cli::print(&hashtags(&note))
+        };
+    }
+
+    for tag in tags {
+        let existing = note
+            .tags
+            .iter()
+            .position(|other| other.eq_ignore_ascii_case(&tag));
+        match (args.flag("--remove"), existing) {
+            (true, Some(position)) => {
+                note.tags.remove(position);
+            }
+            (false, None) => note.tags.push(tag),
+            _ => {}
+        }
+    }
+    notes.upsert(note, Some(index));
+    cli::check(&mut notes)?;
+
+    print_changed(&notes, index, "Tagged", args.json())
+}
+
+pub fn export(args: &[String]) -> Result<(), String> {
+    let mut args = Options::parse(args, &["--format", "--note"], &[])?;
+    let out = PathBuf::from(args.positional(0, "OUT")?);
+    let format = match args.take("--format").as_deref() {
+        None | Some("markdown" | "md") => ExportFormat::MarkdownFolder,
+        Some("zip") => ExportFormat::MarkdownZip,
+        Some("html") => ExportFormat::Html,
+        Some(other) => return Err(format!("unknown format `{other}`")),
+    };
+    let page = args.take("--note");
+    let notes = args.notebook()?;
+
+    let settings = SettingsState::default();
+    let look = Look {
+        theme: settings.theme().palette(),
+        notes: settings.palette(),
+    };
+
+    let count = match page {
+        Some(page) if format == ExportFormat::Html => {
+            let note = notes.get(find(&notes, &page)?).expect("found pages exist");
+            html::export_note(note, look, &out).map_err(|error| error.to_string())?;
+            1
+        }
+        Some(_) => return Err(String::from("--note only works with --format html")),
+        None => export::export_notebook(format, &notes, look, &out)
+            .map_err(|error| error.to_string())?,
+    };
+
+    if args.json() {
+        print_json(&json!({ "exported": count, "path": out }))
+    } else {
+        cli::print(&format!("Exported {count} pages to {}", out.display()))
+    }
+}
+
+pub fn import(args: &[String]) -> Result<(), String> {
+    let mut args = Options::parse(args, &["--from"], &["--dry-run"])?;
+    let paths = args.rest(0).iter().map(PathBuf::from).collect::<Vec<_>>();
+    let Some(first) = paths.first() else {
+        return Err(format!("missing PATH\n\n{}", cli::USAGE));
+    };
+    let source = match args.take("--from").as_deref() {
+        None => detect(&paths),
+        Some("markdown" | "md") => ImportSource::MarkdownFolder,
+        Some("evernote" | "enex") => ImportSource::Evernote,
+        Some("keep") => ImportSource::GoogleKeep,
+        Some(other) => return Err(format!("unknown source `{other}`")),
+    };
+    if source != ImportSource::Evernote && paths.len() > 1 {
+        return Err(String::from(
+            "only Evernote imports take more than one PATH",
+        ));
+    }
+    let mut notes = args.notebook()?;
+
+    let plan = match source {
+        ImportSource::MarkdownFolder | ImportSource::MarkdownZip => markdown::scan(first)?,
+        ImportSource::Evernote => enex::scan(&paths)?,
+        ImportSource::GoogleKeep => keep::scan(first)?,
+    };
+
+    if args.flag("--dry-run") {
+        return print_plan(&plan, args.json());
+    }
+
+    let applied = import::apply(&plan, &mut notes);
+    cli::check(&mut notes)?;
+    let errors = applied
+        .errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    if args.json() {
+        print_json(&json!({
+            "imported": applied.pages,
+            "incomplete": applied.incomplete,
+            "left_out": plan.warnings,
+            "errors": errors,
+        }))?;
+    } else {
+        for problem in plan.warnings.iter().chain(&errors) {
+            eprintln!("faitout: {problem}");
+        }
+        cli::print(&applied.summary(&format!("into {}", notes.name())))?;
+    }
+
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(String::from("1 file could not be copied")),
+        count => Err(format!("{count} files could not be copied")),
+    }
+}
+
+/// Where `paths` most likely come from, when `--from` is not given.
+fn detect(paths: &[PathBuf]) -> ImportSource {
+    let is_enex = |path: &PathBuf| {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("enex"))
+    };
+    let has_json = |dir: &Path| {
+        fs::read_dir(dir).is_ok_and(|entries| {
+            entries.filter_map(|entry| entry.ok()).any(|entry| {
+                entry
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+            })
+        })
+    };
+
+    match paths {
+        paths if paths.iter().all(is_enex) => ImportSource::Evernote,
+        [dir] if dir.join("Keep").is_dir() || dir.join("Takeout").is_dir() || has_json(dir) => {
+            ImportSource::GoogleKeep
+        }
+        [archive]
+            if archive
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("zip")) =>
+        {
+            ImportSource::MarkdownZip
+        }
+        _ => ImportSource::MarkdownFolder,
+    }
+}
+
+/// The index of the page with the id or title `page`.
+fn find(notes: &Notes, page: &str) -> Result<usize, String> {
+    if let Ok(raw) = page.trim().parse::<u64>()
+        && let Some(index) = notes.position(NoteId::from_raw(raw))
+    {
+        return Ok(index);
+    }
+
+    let key = links::normalize(page);
+    let found = notes
+        .iter()
+        .enumerate()
+        .filter(|(_, note)| links::normalize(&note.title) == key)
+        .collect::<Vec<_>>();
+    match found.as_slice() {
+        [(index, _)] => Ok(*index),
+        [] => Err(format!("no page called \"{page}\"")),
+        several => {
+            let ids = several
+                .iter()
+                .map(|(_, note)| note.id.raw().to_string())
+                .collect::<Vec<_>>();
+            Err(format!(
+                "several pages are called \"{page}\"; use one of their ids: {}",
+                ids.join(", ")
+            ))
+        }
+    }
+}
+
+fn print_list(notes: &Notes, shown: &[&Note], json: bool) -> Result<(), String> {
+    if json {
+        let list = shown
+            .iter()
+            .map(|note| api::summary(notes, note))
+            .collect::<Vec<_>>();
+        return print_json(&json!(list));
+    }
+
+    for note in shown {
+        let mut line = format!("{}\t{}", note.id.raw(), note.display_title());
+        if !note.tags.is_empty() {
+            line.push('\t');
+            line.push_str(&hashtags(note));
+        }
+        cli::print(&line)?;
+    }
+    Ok(())
+}
+
+fn print_changed(notes: &Notes, index: usize, verb: &str, json: bool) -> Result<(), String> {
+    let Some(note) = notes.get(index) else {
+        return Ok(());
+    };
+    if json {
+        print_json(&api::summary(notes, note))
+    } else {
+        cli::print(&format!(
+            "{verb} {}\t{}",
+            note.id.raw(),
+            note.display_title()
+        ))
+    }
+}
+
+fn print_plan(plan: &Plan, json: bool) -> Result<(), String> {
+    if json {
+        let pages = plan
+            .drafts
+            .iter()
+            .map(|draft| {
+                json!({
+                    "title": draft.title,
+                    "source": draft.source,
+                    "folder": draft.folder,
+                    "tags": draft.tags,
+                    "images": draft.embeds.len(),
+                    "attachments": draft.attachments.len(),
+                })
+            })
+            .collect::<Vec<_>>();
+        return print_json(&json!({ "pages": pages, "left_out": plan.warnings }));
+    }
+
+    cli::print(&format!(
+        "Would import {} pages from {}:",
+        plan.drafts.len(),
+        plan.source
+    ))?;
+    for draft in &plan.drafts {
+        cli::print(&format!("  {}\t{}", draft.title, draft.source))?;
+    }
+    if !plan.warnings.is_empty() {
+        cli::print("Left out:")?;
+        for warning in &plan.warnings {
+            cli::print(&format!("  {warning}"))?;
+        }
+    }
+    Ok(())
+}
+
+fn print_json(value: &Value) -> Result<(), String> {
+    cli::print(&serde_json::to_string_pretty(value).unwrap_or_default())
+}
+
+fn read_stdin() -> Result<String, String> {
+    let mut text = String::new();
+    std::io::stdin()
+        .read_to_string(&mut text)
+        .map_err(|error| format!("could not read standard input: {error}"))?;
+    Ok(text.trim_end_matches('\n').to_string())
+}
+
+fn hashtags(note: &Note) -> String {
+    note.tags
+        .iter()
+        .map(|tag| format!("#{tag}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -1,14 +1,20 @@
-use iced::widget::markdown::{self, Item, Settings, Style};
+use iced::widget::markdown;
 use iced::widget::text_editor::{self as editor_widget, Content};
 use iced::{
-    Element, Length, Theme,
+    Element, Length,
     alignment::Alignment,
     widget::{
-        button, column, container, row, scrollable, text, text_editor, text_input, vertical_space,
+        button, column, container, horizontal_space, row, scrollable, text, text_editor,
+        text_input, vertical_space,
     },
 };
 
-use crate::notes::notes::Note;
+use std::sync::Arc;
+
+use crate::notes::attachments::{self, Attachment};
+use crate::notes::links::{self, Backlink, WikiLink};
+use crate::notes::notes::{self, Note, NoteId};
+use crate::notes::preview::{self, Block};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ViewMode {
@@ -21,9 +27,14 @@ pub struct Editor {
     title: String,
     tags_input: String,
     body: Content,
-    preview: Vec<Item>,
+    attachments: Vec<Attachment>,
+    preview: Vec<Block>,
     editing: Option<usize>,
+    source: Option<Note>,
+    conflict: Option<Note>,
     mode: ViewMode,
+    /// Whether this editor has a window of its own rather than the main window's screen.
+    detached: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -33,8 +44,17 @@ pub enum Message {
     BodyEdited(editor_widget::Action),
     SavePressed,
     BackPressed,
-    PreviewLinkClicked,
+    PreviewLinkClicked(markdown::Url),
     ToggleViewMode,
+    KeepLocalVersion,
+    LoadDiskVersion,
+    BacklinkClicked(WikiLink),
+    AttachImage,
+    PasteImage,
+    AddAttachment,
+    ExportHtml,
+    RemoveAttachment(usize),
+    OpenAttachment(usize),
 }
 
 #[derive(Debug, Clone)]
@@ -43,9 +63,20 @@ pub enum Event {
         title: String,
         body: String,
         tags: Vec<String>,
+        attachments: Vec<Attachment>,
         editing: Option<usize>,
     },
     Back,
+    FollowLink(WikiLink),
+    OpenExternal(String),
+    /// The user wants to pick image files to embed.
+    AttachImage,
+    /// The user wants to embed the image on the clipboard.
+    PasteImage,
+    /// The user wants to pick files to attach.
+    AddAttachment,
+    /// The user wants the saved version of this note as an HTML page.
+    ExportHtml(NoteId),
 }
 
 impl Default for Editor {
@@ -54,14 +85,26 @@ impl Default for Editor {
             title: String::new(),
             tags_input: String::new(),
             body: Content::new(),
+            attachments: Vec::new(),
             preview: Vec::new(),
             editing: None,
+            source: None,
+            conflict: None,
             mode: ViewMode::PreviewOnly,
+            detached: false,
         }
     }
 }
 
 impl Editor {
+    /// An editor for a note window, which closes instead of going back to the notebook.
+    pub fn detached() -> Self {
+        Self {
+            detached: true,
+            ..Self::default()
+        }
+    }
+
     pub fn update(&mut self, message: Message) -> Option<Event> {
         match message {
             Message::TitleChanged(value) => {
@@ -95,18 +138,20 @@ impl Editor {
                     })
                     .collect::<Vec<_>>();
 
+                self.conflict = None;
+
                 Some(Event::Save {
                     title,
                     body,
                     tags,
+                    attachments: self.attachments.clone(),
                     editing: self.editing,
                 })
             }
-            Message::BackPressed => {
-                self.load_new();
-                Some(Event::Back)
-            }
-            Message::PreviewLinkClicked => None,
+            Message::BackPressed => Some(Event::Back),
+            Message::PreviewLinkClicked(url) => links::parse(&url)
+                .map(Event::FollowLink)
+                .or_else(|| links::external(&url).map(Event::OpenExternal)),
             Message::ToggleViewMode => {
                 self.mode = match self.mode {
                     ViewMode::PreviewOnly => ViewMode::Split,
@@ -119,10 +164,36 @@ impl Editor {
 
                 None
             }
+            Message::BacklinkClicked(link) => Some(Event::FollowLink(link)),
+            Message::AttachImage => Some(Event::AttachImage),
+            Message::PasteImage => Some(Event::PasteImage),
+            Message::AddAttachment => Some(Event::AddAttachment),
+            Message::ExportHtml => self.editing_id().map(Event::ExportHtml),
+            Message::RemoveAttachment(index) => {
+                if index < self.attachments.len() {
+                    self.attachments.remove(index);
+                }
+                None
+            }
+            Message::OpenAttachment(index) => self
+                .attachments
+                .get(index)
+                .map(|attachment| Event::OpenExternal(attachment.file.clone())),
+            Message::KeepLocalVersion => {
+                // Saving will overwrite the version on disk.
+                self.source = self.conflict.take();
+                None
+            }
+            Message::LoadDiskVersion => {
+                if let (Some(index), Some(note)) = (self.editing, self.conflict.take()) {
+                    self.reload(index, &note);
+                }
+                None
+            }
         }
     }
 
-    pub fn view(&self) -> Element<'_, Message> {
+    pub fn view<'a>(&'a self, backlinks: &'a [Backlink]) -> Element<'a, Message> {
         let header = if self.editing.is_some() {
             "Edit page"
         } else {
@@ -141,8 +212,15 @@ impl Editor {
         };
 
         let layout = match self.mode {
-            ViewMode::Split => self.split_layout(header, toggle_label, save_label),
-            ViewMode::PreviewOnly => self.preview_layout(header, toggle_label, save_label),
+            ViewMode::Split => self.split_layout(header, toggle_label, save_label, backlinks),
+            ViewMode::PreviewOnly => {
+                self.preview_layout(header, toggle_label, save_label, backlinks)
+            }
+        };
+
+        let layout = match &self.conflict {
+            Some(_) => column![self.conflict_banner(), layout].spacing(12).into(),
+            None => layout,
         };
 
         container(layout)
@@ -157,7 +235,10 @@ impl Editor {
         self.title.clear();
         self.tags_input.clear();
         self.body = Content::new();
+        self.attachments.clear();
         self.preview.clear();
+        self.source = None;
+        self.conflict = None;
         self.mode = ViewMode::PreviewOnly;
     }
 
@@ -166,14 +247,124 @@ impl Editor {
         self.title = note.title.to_owned();
         self.tags_input = note.tags.join(", ");
         self.body = Content::with_text(note.body.as_str());
+        self.attachments = note.attachments.clone();
         self.preview = note.parsed().to_vec();
+        self.source = Some(note.clone());
+        self.conflict = None;
         self.mode = ViewMode::PreviewOnly;
     }
 
+    /// Loads another version of the page being edited, keeping how it is shown.
+    pub fn reload(&mut self, index: usize, note: &Note) {
+        let mode = self.mode;
+        self.load_existing(index, note);
+        self.mode = mode;
+    }
+
+    /// Starts a new page with the given title, e.g. the target of a dangling wiki link.
+    pub fn load_titled(&mut self, title: &str) {
+        self.load_new();
+        self.title = title.trim().to_string();
+    }
+
+    /// Inserts `markdown` on its own line at the cursor, or at the end of the page when
+    /// only the preview is shown.
+    pub fn insert_block(&mut self, markdown: &str) {
+        if self.mode == ViewMode::PreviewOnly {
+            self.body
+                .perform(editor_widget::Action::Move(editor_widget::Motion::DocumentEnd));
+        }
+
+        let (_, column) = self.body.cursor_position();
+        let text = if column == 0 {
+            format!("{markdown}\n")
+        } else {
+            format!("\n{markdown}\n")
+        };
+
+        self.body
+            .perform(editor_widget::Action::Edit(editor_widget::Edit::Paste(Arc::new(text))));
+        self.refresh_preview();
+    }
+
+    /// Attaches a stored file, unless a file with the same contents already is.
+    /// Returns whether it was added.
+    pub fn add_attachment(&mut self, attachment: Attachment) -> bool {
+        if self.attachments.iter().any(|existing| existing.hash == attachment.hash) {
+            return false;
+        }
+        self.attachments.push(attachment);
+        true
+    }
+
+    /// The text of the page being edited, saved or not.
+    pub fn body_text(&self) -> String {
+        self.body.text()
+    }
+
+    /// Files attached to the page being edited, saved or not.
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+
+    /// The title as typed, saved or not.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
     pub fn editing(&self) -> Option<usize> {
         self.editing
     }
 
+    pub fn editing_id(&self) -> Option<NoteId> {
+        self.source.as_ref().map(|note| note.id)
+    }
+
+    /// Whether the buffer differs from the note it was loaded from.
+    pub fn is_modified(&self) -> bool {
+        let (title, body, tags, attachments) = match &self.source {
+            Some(note) => (
+                note.title.as_str(),
+                note.body.as_str(),
+                note.tags.join(", "),
+                note.attachments.as_slice(),
+            ),
+            None => ("", "", String::new(), [].as_slice()),
+        };
+
+        self.title.trim() != title
+            || self.body.text().trim_end_matches('\n') != body
+            || self.tags_input != tags
+            || self.attachments != attachments
+    }
+
+    /// Called when the note being edited was rewritten by another program or saved in
+    /// another window. Untouched buffers follow the new version; otherwise the user is
+    /// asked which one to keep.
+    pub fn note_changed_elsewhere(&mut self, index: usize, note: &Note) {
+        self.editing = Some(index);
+
+        if self.is_modified() {
+            self.conflict = Some(note.clone());
+        } else {
+            self.reload(index, note);
+        }
+    }
+
+    /// Called when the note being edited was deleted by another program. The buffer
+    /// is kept and saving it creates the note again.
+    pub fn note_removed_on_disk(&mut self) {
+        self.editing = None;
+        self.source = None;
+        self.conflict = None;
+    }
+
+    pub fn set_editing_index(&mut self, index: usize) {
+        if self.editing.is_some() {
+            self.editing = Some(index);
+        }
+    }
+
     pub fn adjust_after_delete(&mut self, index: usize) {
         if let Some(current) = self.editing {
             if current == index {
@@ -189,6 +380,7 @@ impl Editor {
         header: &'a str,
         toggle_label: &'a str,
         save_label: &'a str,
+        backlinks: &'a [Backlink],
     ) -> Element<'a, Message> {
         let title_input = text_input("Page title", &self.title)
             .on_input(Message::TitleChanged)
@@ -223,7 +415,7 @@ impl Editor {
         let preview_panel = column![
             text("Preview").size(24),
             scrollable(
-                container(self.preview_element())
+                container(self.preview_element(backlinks))
                     .padding(12)
                     .width(Length::Fill)
                     .height(Length::Shrink),
@@ -236,8 +428,11 @@ impl Editor {
         .height(Length::Fill);
 
         let actions = row![
-            button("Back to notebook").on_press(Message::BackPressed),
+            button(self.back_label()).on_press(Message::BackPressed),
             button(toggle_label).on_press(Message::ToggleViewMode),
+            button("Attach image").on_press(Message::AttachImage),
+            button("Paste image").on_press(Message::PasteImage),
+            button("Attach file").on_press(Message::AddAttachment),
             button(save_label).on_press(Message::SavePressed),
         ]
         .spacing(12)
@@ -262,7 +457,7 @@ impl Editor {
             text("Give your page a title, tags, and as much text as you need.").size(16),
             vertical_space().height(Length::Fixed(16.0)),
             split,
-            vertical_space().height(Length::Fixed(16.0)),
+            self.attachments_view(),
             actions,
         ]
         .spacing(16)
@@ -276,12 +471,9 @@ impl Editor {
         header: &'a str,
         toggle_label: &'a str,
         save_label: &'a str,
+        backlinks: &'a [Backlink],
     ) -> Element<'a, Message> {
-        let title_display = if self.title.trim().is_empty() {
-            "Untitled page"
-        } else {
-            self.title.as_str()
-        };
+        let title_display = notes::display_title(&self.title);
 
         let tags_display = self
             .tags_input
@@ -302,15 +494,17 @@ impl Editor {
         }
 
         let actions = row![
-            button("Back to notebook").on_press(Message::BackPressed),
+            button(self.back_label()).on_press(Message::BackPressed),
             button(toggle_label).on_press(Message::ToggleViewMode),
+            button("Attach file").on_press(Message::AddAttachment),
+            button("Export HTML").on_press_maybe(self.editing.map(|_| Message::ExportHtml)),
             button(save_label).on_press(Message::SavePressed),
         ]
         .spacing(12)
         .align_y(Alignment::Center);
 
         let preview_panel = scrollable(
-            container(self.preview_element())
+            container(self.preview_element(backlinks))
                 .padding(12)
                 .width(Length::Fill)
                 .height(Length::Shrink),
@@ -324,7 +518,7 @@ impl Editor {
             metadata,
             vertical_space().height(Length::Fixed(16.0)),
             preview_panel,
-            vertical_space().height(Length::Fixed(16.0)),
+            self.attachments_view(),
             actions,
         ]
         .spacing(16)
@@ -333,18 +527,80 @@ impl Editor {
         .into()
     }
 
+    fn back_label(&self) -> &'static str {
+        if self.detached {
+            "Close window"
+        } else {
+            "Back to notebook"
+        }
+    }
+
+    fn attachments_view(&self) -> Element<'_, Message> {
+        if self.attachments.is_empty() {
+            return vertical_space().height(Length::Fixed(16.0)).into();
+        }
+
+        self.attachments
+            .iter()
+            .enumerate()
+            .fold(
+                column![text(format!("Attachments ({})", self.attachments.len())).size(18)]
+                    .spacing(6),
+                |list, (index, attachment)| {
+                    list.push(
+                        row![
+                            text(attachment.name.as_str()).size(14),
+                            text(attachments::format_size(attachment.size)).size(12),
+                            horizontal_space(),
+                            button(text("Open").size(14))
+                                .on_press(Message::OpenAttachment(index)),
+                            button(text("Remove").size(14))
+                                .style(button::danger)
+                                .on_press(Message::RemoveAttachment(index)),
+                        ]
+                        .spacing(12)
+                        .align_y(Alignment::Center),
+                    )
+                },
+            )
+            .into()
+    }
+
+    fn conflict_banner(&self) -> Element<'_, Message> {
+        column![
+            text("This page was saved in another window or program while you were editing it.")
+                .size(16),
+            row![
+                button("Keep my version").on_press(Message::KeepLocalVersion),
+                button("Load the version on disk").on_press(Message::LoadDiskVersion),
+            ]
+            .spacing(12)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(8)
+        .into()
+    }
+
     fn refresh_preview(&mut self) {
         let text = self.body.text();
-        self.preview = markdown::parse(text.as_str()).collect();
+        let resolved = self.source.as_ref().map(|note| note.links().clone()).unwrap_or_default();
+        let text = links::to_markdown(&text, &resolved);
+        self.preview = preview::parse(&text);
     }
 
-    fn preview_element(&self) -> Element<'_, Message> {
-        markdown::view(
-            &self.preview,
-            Settings::default(),
-            Style::from_palette(Theme::KanagawaDragon.palette()),
-        )
-        .map(|_| Message::PreviewLinkClicked)
+    fn preview_element<'a>(&'a self, backlinks: &'a [Backlink]) -> Element<'a, Message> {
+        let preview = preview::view(&self.preview, Message::PreviewLinkClicked);
+
+        if self.editing.is_none() {
+            return preview;
+        }
+
+        column![
+            preview,
+            vertical_space().height(Length::Fixed(24.0)),
+            links::backlinks_view(backlinks, Message::BacklinkClicked),
+        ]
+        .into()
     }
 }
 
@@ -0,0 +1,431 @@
+use iced::widget::button::Status as ButtonStatus;
+use iced::widget::{button, column, container, horizontal_space, row, scrollable, text};
+use iced::{Element, Length, Theme, alignment::Alignment, border};
+
+use super::editor::Editor;
+use crate::notes::notes::{self, Note, NoteId};
+
+/// The pages open in the main window, each in its own editor.
+#[derive(Debug, Default)]
+pub struct Tabs {
+    editors: Vec<Editor>,
+    active: usize,
+    /// A tab with unsaved changes the user asked to close, waiting for confirmation. Kept
+    /// pointing at that tab as others open and close, and dropped when tabs move.
+    closing: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Selected(usize),
+    NotebookSelected,
+    ClosePressed(usize),
+    DiscardConfirmed,
+    DiscardCancelled,
+    MovedLeft(usize),
+    MovedRight(usize),
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A tab was brought to the front.
+    Show,
+    ShowNotebook,
+    /// A tab was closed and its buffer dropped.
+    Closed,
+}
+
+impl Tabs {
+    pub fn update(&mut self, message: Message) -> Option<Event> {
+        match message {
+            Message::Selected(index) => {
+                if index >= self.editors.len() {
+                    return None;
+                }
+                self.active = index;
+                Some(Event::Show)
+            }
+            Message::NotebookSelected => Some(Event::ShowNotebook),
+            Message::ClosePressed(index) => {
+                let modified = self.editors.get(index)?.is_modified();
+                if modified {
+                    self.closing = Some(index);
+                    None
+                } else {
+                    self.close(index);
+                    Some(Event::Closed)
+                }
+            }
+            Message::DiscardConfirmed => {
+                let index = self.closing.take()?;
+                self.close(index);
+                Some(Event::Closed)
+            }
+            Message::DiscardCancelled => {
+                self.closing = None;
+                None
+            }
+            Message::MovedLeft(index) => {
+                if index > 0 {
+                    self.swap(index, index - 1);
+                }
+                None
+            }
+            Message::MovedRight(index) => {
+                if index + 1 < self.editors.len() {
+                    self.swap(index, index + 1);
+                }
+                None
+            }
+        }
+    }
+
+    /// The tab bar, with `notebook_shown` when the notebook rather than a tab is in front.
+    pub fn view(&self, notebook_shown: bool) -> Element<'_, Message> {
+        let notebook = button(text("Notebook").size(14))
+            .padding([6, 12])
+            .style(move |theme, status| tab_style(theme, status, notebook_shown))
+            .on_press(Message::NotebookSelected);
+
+        let tabs = self.editors.iter().enumerate().fold(
+            row![notebook].spacing(4).align_y(Alignment::Center),
+            |tabs, (index, editor)| {
+                let active = !notebook_shown && index == self.active;
+                let title = notes::display_title(editor.title());
+                let label = if editor.is_modified() {
+                    format!("{title} *")
+                } else {
+                    title.to_string()
+                };
+
+                let mut tab = row![
+                    button(text(label).size(14))
+                        .padding([6, 12])
+                        .style(move |theme, status| tab_style(theme, status, active))
+                        .on_press(Message::Selected(index)),
+                ]
+                .spacing(2)
+                .align_y(Alignment::Center);
+                if active {
+                    tab = tab
+                        .push(
+                            button(text("<").size(12))
+                                .padding([2, 6])
+                                .on_press_maybe((index > 0).then_some(Message::MovedLeft(index))),
+                        )
+                        .push(button(text(">").size(12)).padding([2, 6]).on_press_maybe(
+                            (index + 1 < self.editors.len()).then_some(Message::MovedRight(index)),
+                        ));
+                }
+                tabs.push(
+                    tab.push(
+                        button(text("x").size(12))
+                            .padding([2, 6])
+                            .style(button::text)
+                            .on_press(Message::ClosePressed(index)),
+                    ),
+                )
+            },
+        );
+
+        let bar = scrollable(tabs.padding([6, 16]))
+            .direction(scrollable::Direction::Horizontal(
+                scrollable::Scrollbar::new(),
+            ))
+            .width(Length::Fill);
+
+        match self.closing.and_then(|index| self.editors.get(index)) {
+            Some(editor) => {
+                let title = notes::display_title(editor.title());
+                let prompt = container(
+                    row![
+                        text(format!("\"{title}\" has unsaved changes.")).size(14),
+                        horizontal_space(),
+                        button(text("Discard them").size(14))
+                            .style(button::danger)
+                            .on_press(Message::DiscardConfirmed),
+                        button(text("Keep editing").size(14)).on_press(Message::DiscardCancelled),
+                    ]
+                    .spacing(12)
+                    .align_y(Alignment::Center),
+                )
+                .width(Length::Fill)
+                .padding([8, 16])
+                .style(container::rounded_box);
+                column![bar, prompt].into()
+            }
+            None => bar.into(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.editors.is_empty()
+    }
+
+    /// Every open editor, from left to right.
+    pub fn iter(&self) -> impl Iterator<Item = &Editor> {
+        self.editors.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Editor> {
+        self.editors.iter_mut()
+    }
+
+    /// The editor in front, if any tab is open.
+    pub fn current(&self) -> Option<&Editor> {
+        self.editors.get(self.active)
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut Editor> {
+        self.editors.get_mut(self.active)
+    }
+
+    /// Brings the tab of the page at `index` to the front, opening one if needed.
+    pub fn open(&mut self, index: usize, note: &Note) {
+        match self.find(note.id) {
+            Some(tab) => self.active = tab,
+            None => {
+                let mut editor = Editor::default();
+                editor.load_existing(index, note);
+                self.push(editor);
+            }
+        }
+    }
+
+    /// Opens a tab for a new page, titled `title` if given.
+    pub fn open_new(&mut self, title: Option<&str>) {
+        let mut editor = Editor::default();
+        if let Some(title) = title {
+            editor.load_titled(title);
+        }
+        self.push(editor);
+    }
+
+    /// The tab showing the page `id`.
+    pub fn find(&self, id: NoteId) -> Option<usize> {
+        self.editors
+            .iter()
+            .position(|editor| editor.editing_id() == Some(id))
+    }
+
+    /// Brings tab `index` to the front.
+    pub fn select(&mut self, index: usize) {
+        if index < self.editors.len() {
+            self.active = index;
+        }
+    }
+
+    /// Brings the next tab to the front, or the previous one if `backwards`, and
+    /// returns whether one could be: the notebook counts as the tab before the first.
+    pub fn cycle(&mut self, notebook_shown: bool, backwards: bool) -> bool {
+        let count = self.editors.len() + 1;
+        let position = if notebook_shown { 0 } else { self.active + 1 };
+        let next = if backwards {
+            (position + count - 1) % count
+        } else {
+            (position + 1) % count
+        };
+        if next > 0 {
+            self.active = next - 1;
+        }
+        next > 0
+    }
+
+    /// Closes the tab in front.
+    pub fn close_current(&mut self) {
+        if self.active < self.editors.len() {
+            self.close(self.active);
+        }
+    }
+
+    /// Closes the tabs for which `remove` holds.
+    pub fn close_where(&mut self, mut remove: impl FnMut(&Editor) -> bool) {
+        let mut index = 0;
+        while index < self.editors.len() {
+            if remove(&self.editors[index]) {
+                self.close(index);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Keeps the tabs' page indices valid after the page at `index` was deleted; the
+    /// tab of that page closes.
+    pub fn adjust_after_delete(&mut self, index: usize) {
+        self.close_where(|editor| editor.editing() == Some(index));
+        for editor in &mut self.editors {
+            editor.adjust_after_delete(index);
+        }
+    }
+
+    fn push(&mut self, editor: Editor) {
+        let at = if self.editors.is_empty() {
+            0
+        } else {
+            self.active + 1
+        };
+        self.editors.insert(at, editor);
+        self.active = at;
+        // A close waiting for confirmation still refers to the same tab.
+        if let Some(closing) = &mut self.closing
+            && *closing >= at
+        {
+            *closing += 1;
+        }
+    }
+
+    fn close(&mut self, index: usize) {
+        self.editors.remove(index);
+        if index < self.active || self.active >= self.editors.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        self.closing = match self.closing {
+            Some(closing) if closing == index => None,
+            Some(closing) if closing > index => Some(closing - 1),
+            closing => closing,
+        };
+    }
+
+    fn swap(&mut self, index: usize, other: usize) {
+        self.editors.swap(index, other);
+        if self.active == index {
+            self.active = other;
+        } else if self.active == other {
+            self.active = index;
+        }
+        self.closing = None;
+    }
+}
+
+fn tab_style(theme: &Theme, status: ButtonStatus, active: bool) -> button::Style {
+    let palette = theme.extended_palette();
+    let background = if active {
+        Some(palette.primary.weak.color.into())
+    } else if matches!(status, ButtonStatus::Hovered) {
+        Some(palette.background.weak.color.into())
+    } else {
+        None
+    };
+
+    button::Style {
+        background,
+        text_color: if active {
+            palette.primary.weak.text
+        } else {
+            palette.background.base.text
+        },
+        border: border::rounded(6),
+        ..button::Style::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tabs for saved pages with the given titles, the last one in front.
+    fn tabs_for(titles: &[&str]) -> Tabs {
+        let mut tabs = Tabs::default();
+        for (index, title) in titles.iter().enumerate() {
+            tabs.open(
+                index,
+                &Note::new(title.to_string(), String::new(), Vec::new()),
+            );
+        }
+        tabs
+    }
+
+    fn titles(tabs: &Tabs) -> Vec<&str> {
+        tabs.iter().map(Editor::title).collect()
+    }
+
+    fn front(tabs: &Tabs) -> &str {
+        tabs.current().map(Editor::title).unwrap_or_default()
+    }
+
+    #[test]
+    fn closing_a_tab_keeps_the_one_in_front() {
+        let mut tabs = tabs_for(&["A", "B", "C", "D"]);
+        tabs.select(2);
+
+        // Before the one in front.
+        assert!(matches!(
+            tabs.update(Message::ClosePressed(0)),
+            Some(Event::Closed)
+        ));
+        assert_eq!((titles(&tabs), front(&tabs)), (vec!["B", "C", "D"], "C"));
+
+        // After it.
+        tabs.update(Message::ClosePressed(2));
+        assert_eq!((titles(&tabs), front(&tabs)), (vec!["B", "C"], "C"));
+
+        // The one in front itself, at the end: its left neighbour comes forward.
+        tabs.update(Message::ClosePressed(1));
+        assert_eq!((titles(&tabs), front(&tabs)), (vec!["B"], "B"));
+
+        // In the middle, its right neighbour comes forward.
+        let mut tabs = tabs_for(&["A", "B", "C"]);
+        tabs.select(1);
+        tabs.close_current();
+        assert_eq!((titles(&tabs), front(&tabs)), (vec!["A", "C"], "C"));
+    }
+
+    #[test]
+    fn a_pending_close_follows_its_tab() {
+        let mut tabs = tabs_for(&["A", "B"]);
+        tabs.select(0);
+        tabs.open_new(Some("Draft"));
+        assert_eq!(titles(&tabs), ["A", "Draft", "B"]);
+
+        // Unsaved, so closing waits for confirmation.
+        assert!(tabs.update(Message::ClosePressed(1)).is_none());
+        assert_eq!(titles(&tabs), ["A", "Draft", "B"]);
+
+        // A tab opening before it and one closing before it do not change which
+        // tab the confirmation closes.
+        tabs.select(0);
+        tabs.open(2, &Note::new("C".into(), String::new(), Vec::new()));
+        assert_eq!(titles(&tabs), ["A", "C", "Draft", "B"]);
+        tabs.update(Message::ClosePressed(0));
+        assert_eq!(titles(&tabs), ["C", "Draft", "B"]);
+
+        assert!(matches!(
+            tabs.update(Message::DiscardConfirmed),
+            Some(Event::Closed)
+        ));
+        assert_eq!(titles(&tabs), ["C", "B"]);
+
+        // Moving tabs drops the pending close rather than close the wrong one.
+        tabs.open_new(Some("Another draft"));
+        let draft = titles(&tabs)
+            .iter()
+            .position(|title| *title == "Another draft")
+            .unwrap();
+        tabs.update(Message::ClosePressed(draft));
+        tabs.update(Message::MovedLeft(draft));
+        assert!(tabs.update(Message::DiscardConfirmed).is_none());
+        assert_eq!(titles(&tabs).len(), 3);
+    }
+
+    #[test]
+    fn cycling_passes_through_the_notebook() {
+        let mut tabs = tabs_for(&["A", "B", "C"]);
+        tabs.select(1);
+
+        assert!(tabs.cycle(false, false));
+        assert_eq!(front(&tabs), "C");
+        // After the last tab comes the notebook, and after it the first tab.
+        assert!(!tabs.cycle(false, false));
+        assert!(tabs.cycle(true, false));
+        assert_eq!(front(&tabs), "A");
+
+        // Backwards, the notebook comes before the first tab and after it the last.
+        assert!(!tabs.cycle(false, true));
+        assert!(tabs.cycle(true, true));
+        assert_eq!(front(&tabs), "C");
+
+        // With no tabs, only the notebook is left.
+        assert!(!Tabs::default().cycle(true, false));
+    }
+}
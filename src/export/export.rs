@@ -0,0 +1,53 @@
+use std::fmt;
+use std::path::Path;
+
+use super::html::{self, Look};
+use super::markdown;
+use crate::notes::notes::Notes;
+use crate::storage::storage::StorageError;
+
+/// The ways a whole notebook can be exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Html,
+    MarkdownFolder,
+    MarkdownZip,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Html,
+        ExportFormat::MarkdownFolder,
+        ExportFormat::MarkdownZip,
+    ];
+
+    /// Whether the export goes into a directory rather than a single file.
+    pub fn is_folder(self) -> bool {
+        !matches!(self, ExportFormat::MarkdownZip)
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExportFormat::Html => "Website (HTML)",
+            ExportFormat::MarkdownFolder => "Markdown files",
+            ExportFormat::MarkdownZip => "Markdown zip archive",
+        })
+    }
+}
+
+/// Exports every note of `notes` to `path` in `format`. Returns how many notes were
+/// written.
+pub fn export_notebook(
+    format: ExportFormat,
+    notes: &Notes,
+    look: Look<'_>,
+    path: &Path,
+) -> Result<usize, StorageError> {
+    match format {
+        ExportFormat::Html => html::export_site(notes, look, path),
+        ExportFormat::MarkdownFolder => markdown::export_folder(notes, path),
+        ExportFormat::MarkdownZip => markdown::export_zip(notes, path),
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::notes::attachments;
+use crate::notes::notes::{Note, NoteId};
+use crate::storage::storage::{self, StorageError};
+
+const MAX_SLUG_LENGTH: usize = 60;
+const MAX_FILE_NAME_LENGTH: usize = 100;
+
+/// Lowercase letters, digits and dashes, e.g. `meeting-notes-2024`.
+pub fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for ch in title.trim().chars() {
+        if ch.is_alphanumeric() {
+            slug.extend(ch.to_lowercase());
+        } else if !slug.ends_with('-') && !slug.is_empty() {
+            slug.push('-');
+        }
+        if slug.chars().count() >= MAX_SLUG_LENGTH {
+            break;
+        }
+    }
+
+    match slug.trim_end_matches('-') {
+        "" => String::from("untitled"),
+        slug => slug.to_string(),
+    }
+}
+
+/// `name` made safe to use as a file or directory name on every common system, keeping
+/// its case and spaces. Characters Windows forbids become dashes.
+pub fn sanitize(name: &str) -> String {
+    const RESERVED: [&str; 22] = [
+        "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+        "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+    ];
+
+    let cleaned = name
+        .chars()
+        .map(|ch| match ch {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '-',
+            ch if ch.is_control() => ' ',
+            ch => ch,
+        })
+        .take(MAX_FILE_NAME_LENGTH)
+        .collect::<String>();
+    let cleaned = cleaned.trim().trim_end_matches('.').trim_end();
+
+    if cleaned.is_empty() || cleaned.chars().all(|ch| ch == '.') {
+        String::from("Untitled")
+    } else if RESERVED.contains(&cleaned.to_lowercase().as_str()) {
+        format!("{cleaned}_")
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// `stem` itself, or `stem-2`, `stem-3`... whichever is not in `taken` yet, compared
+/// case-insensitively. The result is added to `taken`.
+pub fn unique(stem: &str, taken: &mut HashSet<String>) -> String {
+    let name = std::iter::once(stem.to_string())
+        .chain((2..).map(|suffix| format!("{stem}-{suffix}")))
+        .find(|name| !taken.contains(&name.to_lowercase()))
+        .expect("an unused suffix always exists");
+
+    taken.insert(name.to_lowercase());
+    name
+}
+
+/// A distinct file stem for every note, derived from its title.
+pub fn note_stems<'a>(
+    notes: impl IntoIterator<Item = &'a Note>,
+    taken: &mut HashSet<String>,
+) -> HashMap<NoteId, String> {
+    notes
+        .into_iter()
+        .map(|note| (note.id, unique(&slug(&note.title), taken)))
+        .collect()
+}
+
+/// Copies every stored file `notes` use into an `attachments` directory inside `dir`,
+/// so relative links in exported pages keep working.
+pub fn copy_attachments<'a>(
+    notes: impl IntoIterator<Item = &'a Note>,
+    dir: &Path,
+) -> Result<(), StorageError> {
+    let mut names = HashSet::new();
+    for note in notes {
+        names.extend(attachments::references(&note.body).map(str::to_string));
+        for attachment in &note.attachments {
+            names.extend(attachments::references(&attachment.file).map(str::to_string));
+        }
+    }
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let source_dir = storage::data_dir().join(attachments::DIR);
+    let target_dir = dir.join(attachments::DIR);
+    fs::create_dir_all(&target_dir).map_err(|error| StorageError::Write {
+        path: target_dir.clone(),
+        reason: error.to_string(),
+    })?;
+    // Exporting into the data directory would copy every file onto itself.
+    if target_dir.canonicalize().ok() == source_dir.canonicalize().ok() {
+        return Ok(());
+    }
+
+    for name in names {
+        let source = source_dir.join(&name);
+        if !source.is_file() {
+            continue;
+        }
+        let target = target_dir.join(&name);
+        fs::copy(&source, &target).map_err(|error| StorageError::Write {
+            path: target,
+            reason: error.to_string(),
+        })?;
+    }
+
+    Ok(())
+}
+
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), StorageError> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).map_err(|error| StorageError::Write {
+            path: parent.to_path_buf(),
+            reason: error.to_string(),
+        })?;
+    }
+
+    fs::write(path, contents).map_err(|error| StorageError::Write {
+        path: path.to_path_buf(),
+        reason: error.to_string(),
+    })
+}
@@ -0,0 +1,365 @@
+/// A value in YAML front matter. Only the parts of YAML that front matter commonly
+/// uses are understood: scalars, lists and lists of small maps.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The texts of a list, or a single text as a one-item list.
+    pub fn texts(&self) -> Vec<&str> {
+        match self {
+            Value::Text(text) => vec![text.as_str()],
+            Value::List(items) => items.iter().filter_map(Value::as_text).collect(),
+            Value::Map(_) => Vec::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => lookup(entries, key),
+            _ => None,
+        }
+    }
+}
+
+/// The value of `key` among front matter `entries`, compared case-insensitively.
+pub fn lookup<'a>(entries: &'a [(String, Value)], key: &str) -> Option<&'a Value> {
+    entries
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value)
+}
+
+/// Splits `text` into its front matter, if it starts with a `---` block, and the rest.
+pub fn split(text: &str) -> (Vec<(String, Value)>, &str) {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (Vec::new(), text);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            let body = &rest[offset + line.len()..];
+            return (parse(&rest[..offset]), body);
+        }
+        offset += line.len();
+    }
+
+    (Vec::new(), text)
+}
+
+/// Parses the lines between the `---` markers.
+pub fn parse(yaml: &str) -> Vec<(String, Value)> {
+    let lines = yaml
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>();
+    let mut entries = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+        if indent(line) > 0 {
+            continue;
+        }
+        let Some((key, value)) = key_value(line) else {
+            continue;
+        };
+
+        let nested = lines[index..]
+            .iter()
+            .take_while(|line| indent(line) > 0 || line.starts_with("- "))
+            .copied()
+            .collect::<Vec<_>>();
+        let value = match value {
+            "" => {
+                index += nested.len();
+                block(&nested)
+            }
+            "|" | "|-" | ">" | ">-" => {
+                index += nested.len();
+                let separator = if value.starts_with('|') { "\n" } else { " " };
+                let texts = nested.iter().map(|line| line.trim()).collect::<Vec<_>>();
+                Value::Text(texts.join(separator))
+            }
+            value => inline(value),
+        };
+        entries.push((key.to_string(), value));
+    }
+
+    entries
+}
+
+/// `secs` since the Unix epoch as an RFC 3339 UTC timestamp, e.g.
+/// `2024-05-01T09:30:00Z`.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Seconds since the Unix epoch of an RFC 3339 timestamp such as
+/// `2024-05-01T09:30:00Z` or `2024-05-01 09:30:00+02:00`, or of a plain date.
+pub fn parse_timestamp(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = text.get(range)?;
+        digits
+            .bytes()
+            .all(|byte| byte.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    if text.get(4..5) != Some("-") || text.get(7..8) != Some("-") {
+        return None;
+    }
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut seconds = 0;
+    let mut rest = &text[10..];
+    if let Some(time) = rest.strip_prefix(['T', 't', ' ']) {
+        let (hour, minute) = (number(11..13)?, number(14..16)?);
+        seconds = hour * 3600 + minute * 60;
+        rest = &time[5..];
+        if let Some(after) = rest.strip_prefix(':') {
+            seconds += number(17..19)?;
+            rest = after.get(2..)?;
+        }
+        if let Some(fraction) = rest.strip_prefix('.') {
+            rest = fraction.trim_start_matches(|ch: char| ch.is_ascii_digit());
+        }
+        match rest.trim() {
+            "" | "Z" | "z" => {}
+            offset => {
+                let sign = match offset.chars().next()? {
+                    '+' => 1,
+                    '-' => -1,
+                    _ => return None,
+                };
+                let digits = offset[1..].replace(':', "");
+                let hours = digits.get(0..2)?.parse::<i64>().ok()?;
+                let minutes = digits.get(2..4).map_or(Some(0), |m| m.parse().ok())?;
+                seconds -= sign * (hours * 3600 + minutes * 60);
+            }
+        }
+    } else if !rest.trim().is_empty() {
+        return None;
+    }
+
+    let total = days_from_civil(year, month as u32, day as u32) * 86_400 + seconds;
+    u64::try_from(total).ok()
+}
+
+/// Quotes `value` for a YAML front matter line when it needs it.
+pub fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value.trim() == value
+        && value
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, ' ' | '-' | '_' | '.' | '/'))
+        && !value.starts_with(['-', '.'])
+        && !matches!(
+            value.to_lowercase().as_str(),
+            "true" | "false" | "yes" | "no" | "null" | "~"
+        )
+        && value.parse::<f64>().is_err();
+
+    if plain {
+        value.to_string()
+    } else {
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\t', "\\t");
+        format!("\"{escaped}\"")
+    }
+}
+
+/// Year, month and day of the date `days` after 1970-01-01 in the proleptic
+/// Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Days from 1970-01-01 to the given date; the inverse of [`civil_from_days`].
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = i64::from((month + 9) % 12);
+    let day_of_year = (153 * shifted_month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The items of an indented block under a key: a list, or a map of `key: value` lines.
+fn block(lines: &[&str]) -> Value {
+    if !lines
+        .first()
+        .is_some_and(|line| line.trim_start().starts_with('-'))
+    {
+        let entries = lines
+            .iter()
+            .filter_map(|line| key_value(line.trim()))
+            .map(|(key, value)| (key.to_string(), inline(value)))
+            .collect();
+        return Value::Map(entries);
+    }
+
+    let mut items: Vec<Vec<&str>> = Vec::new();
+    let dash_indent = indent(lines[0]);
+    for line in lines {
+        let trimmed = line.trim_start();
+        if indent(line) == dash_indent && (trimmed == "-" || trimmed.starts_with("- ")) {
+            items.push(vec![trimmed[1..].trim()]);
+        } else if let Some(item) = items.last_mut() {
+            item.push(line.trim());
+        }
+    }
+
+    let values = items
+        .into_iter()
+        .map(|item| match key_value(item[0]) {
+            Some(_) if !item[0].starts_with(['"', '\'']) => Value::Map(
+                item.iter()
+                    .filter_map(|line| key_value(line))
+                    .map(|(key, value)| (key.to_string(), inline(value)))
+                    .collect(),
+            ),
+            _ => inline(item[0]),
+        })
+        .collect();
+    Value::List(values)
+}
+
+/// A value written on the same line as its key: a scalar or a `[flow, list]`.
+fn inline(value: &str) -> Value {
+    let value = value.trim();
+    let Some(inner) = value
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    else {
+        return Value::Text(scalar(value));
+    };
+
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for ch in inner.chars() {
+        match (quote, ch) {
+            (None, ',') => items.push(std::mem::take(&mut current)),
+            (None, '"' | '\'') => {
+                quote = Some(ch);
+                current.push(ch);
+            }
+            (Some(open), ch) if ch == open && !current.ends_with('\\') => {
+                quote = None;
+                current.push(ch);
+            }
+            (_, ch) => current.push(ch),
+        }
+    }
+    items.push(current);
+
+    Value::List(
+        items
+            .iter()
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(|item| Value::Text(scalar(item)))
+            .collect(),
+    )
+}
+
+/// A single scalar, unquoted and unescaped.
+fn scalar(value: &str) -> String {
+    let value = value.trim();
+    if let Some(inner) = value
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        let mut text = String::new();
+        let mut chars = inner.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '\\' {
+                text.push(ch);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some(other) => text.push(other),
+                None => {}
+            }
+        }
+        text
+    } else if let Some(inner) = value
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        inner.replace("''", "'")
+    } else {
+        match value.find(" #") {
+            Some(comment) => value[..comment].trim_end().to_string(),
+            None => value.to_string(),
+        }
+    }
+}
+
+/// Splits `key: value` at the first colon followed by a space or the line's end.
+fn key_value(line: &str) -> Option<(&str, &str)> {
+    let colon = line
+        .match_indices(':')
+        .map(|(index, _)| index)
+        .find(|&index| line[index + 1..].is_empty() || line[index + 1..].starts_with(' '))?;
+    let key = line[..colon].trim();
+    let key = key.trim_matches(|ch| ch == '"' || ch == '\'');
+    (!key.is_empty() && !key.starts_with('-')).then(|| (key, line[colon + 1..].trim()))
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
@@ -0,0 +1,261 @@
+use iced::Color;
+use iced::theme::Palette as ThemePalette;
+use pulldown_cmark::{Event, Options, Parser};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use super::files;
+use crate::notes::attachments;
+use crate::notes::colors::Palette;
+use crate::notes::links;
+use crate::notes::notes::{Note, NoteId, Notes};
+use crate::storage::storage::StorageError;
+
+const INDEX: &str = "index";
+const TAG_PREFIX: &str = "tag-";
+
+/// Colors exported pages are drawn with.
+#[derive(Debug, Clone, Copy)]
+pub struct Look<'a> {
+    pub theme: ThemePalette,
+    pub notes: &'a Palette,
+}
+
+/// Writes `note` as a single HTML page at `path`. Links to other notes become plain
+/// text, since their pages are not exported along with it.
+pub fn export_note(note: &Note, look: Look<'_>, path: &Path) -> Result<(), StorageError> {
+    let body = note_body(note, look, |_| None, |_| None);
+    files::write(path, page(note.display_title(), look, false, &body))?;
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    files::copy_attachments([note], dir)
+}
+
+/// Writes every note of `notes` into `dir` as a small static site: an index, a page
+/// per note and a page per tag, all linked together. Returns how many notes were
+/// written.
+pub fn export_site(notes: &Notes, look: Look<'_>, dir: &Path) -> Result<usize, StorageError> {
+    let mut sorted = notes.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|note| note.display_title().to_lowercase());
+
+    let mut taken = HashSet::from([INDEX.to_string()]);
+    let stems = files::note_stems(sorted.iter().copied(), &mut taken);
+
+    let mut tags: BTreeMap<String, (String, Vec<&Note>)> = BTreeMap::new();
+    for note in &sorted {
+        for tag in &note.tags {
+            tags.entry(tag.to_lowercase())
+                .or_insert_with(|| (tag.clone(), Vec::new()))
+                .1
+                .push(note);
+        }
+    }
+    let tag_stems = tags
+        .keys()
+        .map(|key| {
+            let stem = files::unique(&format!("{TAG_PREFIX}{}", files::slug(key)), &mut taken);
+            (key.clone(), stem)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let note_href = |id: Option<NoteId>| {
+        id.and_then(|id| stems.get(&id))
+            .map(|stem| format!("{stem}.html"))
+    };
+    let tag_href = |tag: &str| {
+        tag_stems
+            .get(&tag.to_lowercase())
+            .map(|stem| format!("{stem}.html"))
+    };
+
+    for note in &sorted {
+        let body = note_body(note, look, note_href, tag_href);
+        let path = dir.join(format!("{}.html", stems[&note.id]));
+        files::write(&path, page(note.display_title(), look, true, &body))?;
+    }
+
+    for (key, (tag, tagged)) in &tags {
+        let body = format!(
+            "<h1>#{}</h1>\n{}",
+            escape(tag),
+            note_list(tagged, |id| note_href(Some(id)))
+        );
+        let path = dir.join(format!("{}.html", tag_stems[key]));
+        files::write(&path, page(&format!("#{tag}"), look, true, &body))?;
+    }
+
+    let mut index = format!(
+        "<h1>{}</h1>\n{}",
+        escape(&notes.name()),
+        note_list(&sorted, |id| note_href(Some(id)))
+    );
+    if !tags.is_empty() {
+        index.push_str("<h2>Tags</h2>\n<p class=\"tags\">");
+        for (key, (tag, tagged)) in &tags {
+            index.push_str(&format!(
+                "<a href=\"{}.html\">#{}</a> ({}) ",
+                tag_stems[key],
+                escape(tag),
+                tagged.len()
+            ));
+        }
+        index.push_str("</p>\n");
+    }
+    files::write(
+        &dir.join(format!("{INDEX}.html")),
+        page(&notes.name(), look, false, &index),
+    )?;
+
+    files::copy_attachments(sorted.iter().copied(), dir)?;
+    Ok(sorted.len())
+}
+
+/// Renders markdown the way exported pages show it. Raw HTML in notes is shown as
+/// text, as it is in the app.
+pub fn render(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events);
+    html
+}
+
+fn note_body(
+    note: &Note,
+    look: Look<'_>,
+    note_href: impl Fn(Option<NoteId>) -> Option<String>,
+    tag_href: impl Fn(&str) -> Option<String>,
+) -> String {
+    let accent = look
+        .notes
+        .swatch(&note.color)
+        .map(|color| format!(" style=\"border-left: 6px solid {}\"", css(color)))
+        .unwrap_or_default();
+    let mut html = format!("<h1{accent}>{}</h1>\n", escape(note.display_title()));
+
+    if !note.tags.is_empty() {
+        html.push_str("<p class=\"tags\">");
+        for tag in &note.tags {
+            let label = format!("#{}", escape(tag));
+            match tag_href(tag) {
+                Some(href) => html.push_str(&format!("<a href=\"{href}\">{label}</a> ")),
+                None => html.push_str(&format!("<span>{label}</span> ")),
+            }
+        }
+        html.push_str("</p>\n");
+    }
+
+    html.push_str(&render(&links::to_markdown_with(
+        &note.body,
+        note.links(),
+        note_href,
+    )));
+
+    if !note.attachments.is_empty() {
+        html.push_str("<h2>Attachments</h2>\n<ul>\n");
+        for attachment in &note.attachments {
+            html.push_str(&format!(
+                "<li><a href=\"{}\" download=\"{name}\">{name}</a> ({})</li>\n",
+                escape(&attachment.file),
+                attachments::format_size(attachment.size),
+                name = escape(&attachment.name),
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    html
+}
+
+fn note_list(notes: &[&Note], href: impl Fn(NoteId) -> Option<String>) -> String {
+    let mut html = String::from("<ul class=\"notes\">\n");
+    for note in notes {
+        let title = escape(note.display_title());
+        match href(note.id) {
+            Some(href) => html.push_str(&format!("<li><a href=\"{href}\">{title}</a></li>\n")),
+            None => html.push_str(&format!("<li>{title}</li>\n")),
+        }
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+fn page(title: &str, look: Look<'_>, nav: bool, body: &str) -> String {
+    let theme = look.theme;
+    let faint = Color {
+        a: 0.08,
+        ..theme.text
+    };
+    let nav = if nav {
+        format!("<nav><a href=\"{INDEX}.html\">All pages</a></nav>\n")
+    } else {
+        String::new()
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{
+  background: {background}; color: {text};
+  font-family: system-ui, sans-serif; line-height: 1.6;
+  max-width: 46rem; margin: 2rem auto; padding: 0 1rem;
+}}
+a {{ color: {primary}; }}
+h1 {{ padding-left: 0.5rem; }}
+code, pre {{ background: {faint}; border-radius: 4px; }}
+code {{ padding: 0.1em 0.3em; }}
+pre {{ padding: 0.75rem; overflow: auto; }}
+pre code {{ padding: 0; background: none; }}
+blockquote {{ margin-left: 0; padding-left: 1rem; border-left: 3px solid {primary}; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid {faint}; padding: 0.25rem 0.5rem; }}
+img {{ max-width: 100%; }}
+.tags a, .tags span {{ margin-right: 0.5rem; }}
+nav {{ margin-bottom: 1.5rem; }}
+</style>
+</head>
+<body>
+{nav}<main>
+{body}</main>
+</body>
+</html>
+"#,
+        title = escape(title),
+        background = css(theme.background),
+        text = css(theme.text),
+        primary = css(theme.primary),
+        faint = css(faint),
+    )
+}
+
+fn css(color: Color) -> String {
+    let [r, g, b, a] = color.into_rgba8();
+    format!("rgba({r}, {g}, {b}, {:.2})", a as f32 / 255.0)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
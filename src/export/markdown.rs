@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use super::files;
+use super::front_matter;
+use super::zip;
+use crate::notes::attachments;
+use crate::notes::colors::NoteColor;
+use crate::notes::folders::{self, FolderId};
+use crate::notes::notes::{Note, Notes};
+use crate::storage::storage::{self, StorageError};
+
+/// Writes every note of `notes` as a `.md` file with front matter into the directory
+/// `dir`. Folders become subdirectories and attachments are copied along. Returns how
+/// many notes were written.
+pub fn export_folder(notes: &Notes, dir: &Path) -> Result<usize, StorageError> {
+    let entries = entries(notes)?;
+    for (path, contents) in &entries {
+        files::write(&dir.join(path), contents)?;
+    }
+    Ok(notes.iter().count())
+}
+
+/// Like [`export_folder`], but into a single zip archive at `path`.
+pub fn export_zip(notes: &Notes, path: &Path) -> Result<usize, StorageError> {
+    let entries = entries(notes)?;
+    zip::write(
+        path,
+        entries
+            .iter()
+            .map(|(name, contents)| (name.as_str(), contents.as_slice())),
+    )
+    .map_err(|error| StorageError::Write {
+        path: path.to_path_buf(),
+        reason: error.to_string(),
+    })?;
+    Ok(notes.iter().count())
+}
+
+/// The text of the `.md` file for `note`, which sits `depth` directories below the
+/// root of the export.
+pub fn note_markdown(note: &Note, depth: usize) -> String {
+    let up = "../".repeat(depth);
+    let relocate = |text: &str| {
+        let prefix = format!("{}/", attachments::DIR);
+        text.replace(&format!("({prefix}"), &format!("({up}{prefix}"))
+            .replace(&format!("(<{prefix}"), &format!("(<{up}{prefix}"))
+    };
+
+    let mut out = String::from("---\n");
+    out.push_str(&format!("title: {}\n", front_matter::quote(&note.title)));
+    if !note.tags.is_empty() {
+        let tags = note
+            .tags
+            .iter()
+            .map(|tag| front_matter::quote(tag))
+            .collect::<Vec<_>>();
+        out.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+    }
+    if note.color != NoteColor::default() {
+        out.push_str(&format!(
+            "color: {}\n",
+            front_matter::quote(note.color.name())
+        ));
+    }
+    out.push_str(&format!(
+        "created: {}\n",
+        front_matter::format_timestamp(note.created)
+    ));
+    out.push_str(&format!(
+        "updated: {}\n",
+        front_matter::format_timestamp(note.updated)
+    ));
+    if note.pinned {
+        out.push_str("pinned: true\n");
+    }
+    if note.archived {
+        out.push_str("archived: true\n");
+    }
+    if !note.attachments.is_empty() {
+        out.push_str("attachments:\n");
+        for attachment in &note.attachments {
+            out.push_str(&format!(
+                "  - name: {}\n    file: {}\n",
+                front_matter::quote(&attachment.name),
+                front_matter::quote(&format!("{up}{}", attachment.file)),
+            ));
+        }
+    }
+    out.push_str("---\n\n");
+    out.push_str(&relocate(&note.body));
+    out.push('\n');
+    out
+}
+
+/// Every file of the export as a slash-separated path and its contents.
+fn entries(notes: &Notes) -> Result<Vec<(String, Vec<u8>)>, StorageError> {
+    let mut taken_dirs: HashMap<String, HashSet<String>> = HashMap::new();
+    taken_dirs
+        .entry(String::new())
+        .or_default()
+        .insert(attachments::DIR.to_string());
+
+    let mut folder_dirs: HashMap<FolderId, String> = HashMap::new();
+    for (_, folder) in folders::flatten(notes.folders(), |_| true) {
+        let parent = folder
+            .parent
+            .and_then(|parent| folder_dirs.get(&parent))
+            .cloned()
+            .unwrap_or_default();
+        let name = files::unique(
+            &files::sanitize(&folder.name),
+            taken_dirs.entry(parent.clone()).or_default(),
+        );
+        let dir = if parent.is_empty() {
+            name
+        } else {
+            format!("{parent}/{name}")
+        };
+        folder_dirs.insert(folder.id, dir);
+    }
+
+    let mut taken_files: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut entries = Vec::new();
+    let mut referenced = HashSet::new();
+
+    for note in notes.iter() {
+        let dir = note
+            .folder
+            .and_then(|folder| folder_dirs.get(&folder))
+            .cloned()
+            .unwrap_or_default();
+        let stem = files::unique(
+            &files::sanitize(&note.title),
+            taken_files.entry(dir.clone()).or_default(),
+        );
+        let depth = if dir.is_empty() {
+            0
+        } else {
+            dir.matches('/').count() + 1
+        };
+        let path = if dir.is_empty() {
+            format!("{stem}.md")
+        } else {
+            format!("{dir}/{stem}.md")
+        };
+
+        entries.push((path, note_markdown(note, depth).into_bytes()));
+        referenced.extend(attachments::references(&note.body).map(str::to_string));
+        for attachment in &note.attachments {
+            referenced.extend(attachments::references(&attachment.file).map(str::to_string));
+        }
+    }
+
+    let source_dir = storage::data_dir().join(attachments::DIR);
+    let mut referenced = referenced.into_iter().collect::<Vec<_>>();
+    referenced.sort();
+    for name in referenced {
+        let source = source_dir.join(&name);
+        if !source.is_file() {
+            continue;
+        }
+        let contents = fs::read(&source).map_err(|error| StorageError::Read {
+            path: source.clone(),
+            reason: error.to_string(),
+        })?;
+        entries.push((format!("{}/{name}", attachments::DIR), contents));
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::import::{self as import, Source};
+    use crate::import::markdown::scan;
+
+    /// Title, text, tags, folder path, pinned and creation time of a page.
+    type Page = (String, String, Vec<String>, Option<String>, bool, u64);
+
+    /// What an export should carry over for every page, in a stable order.
+    fn pages(notes: &Notes) -> Vec<Page> {
+        let mut pages = notes
+            .iter()
+            .map(|note| {
+                let folder = note.folder.map(|id| folders::path(notes.folders(), id));
+                let (title, body) = (note.title.clone(), note.body.clone());
+                (
+                    title,
+                    body,
+                    note.tags.clone(),
+                    folder,
+                    note.pinned,
+                    note.created,
+                )
+            })
+            .collect::<Vec<_>>();
+        pages.sort();
+        pages
+    }
+
+    #[test]
+    fn exported_notebooks_import_back() {
+        let _data = storage::use_test_data_dir();
+        let mut notes = Notes::open("round-trip-export");
+        let chart = attachments::store_bytes(b"not really a png", "chart.png").unwrap();
+        let body = format!(
+            "See [[Recipes]].\n\n{}",
+            attachments::image_markdown(&chart.file, "chart")
+        );
+        let mut plans = Note::new("Plans: Q3".into(), body, vec!["work".into(), "q3".into()]);
+        plans.folder = notes.ensure_folder(&["Work".into(), "2025".into()]);
+        plans.pinned = true;
+        plans.created = 1_700_000_000;
+        notes.upsert(plans, None);
+        notes.upsert(
+            Note::new("Recipes".into(), "- Soup".into(), Vec::new()),
+            None,
+        );
+        assert!(notes.take_errors().is_empty());
+
+        let dir = storage::data_dir().join("round-trip-export");
+        let archive = storage::data_dir().join("round-trip-export.zip");
+        export_folder(&notes, &dir).unwrap();
+        export_zip(&notes, &archive).unwrap();
+        let from_dir = scan(&dir).unwrap();
+        let from_zip = scan(&archive).unwrap();
+        assert!(from_zip.warnings.is_empty(), "{:?}", from_zip.warnings);
+
+        // The image comes out of the archive itself, not out of a file left on disk.
+        let embeds = from_zip.drafts.iter().flat_map(|draft| &draft.embeds);
+        let images = embeds
+            .map(|embed| match &embed.source {
+                Source::Bytes { bytes, .. } => bytes.as_slice(),
+                Source::Path(path) => panic!("expected the image contents, got {path:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(images, [b"not really a png"]);
+
+        for (plan, notebook) in [(from_dir, "from-folder"), (from_zip, "from-zip")] {
+            let mut imported = Notes::open(notebook);
+            assert!(import::apply(&plan, &mut imported).errors.is_empty());
+            assert_eq!(pages(&imported), pages(&notes));
+        }
+    }
+}
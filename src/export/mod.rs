@@ -0,0 +1,6 @@
+pub mod export;
+pub mod files;
+pub mod front_matter;
+pub mod html;
+pub mod markdown;
+pub mod zip;
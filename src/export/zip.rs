@@ -0,0 +1,233 @@
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Modification time and date of every entry, in MS-DOS format: 1980-01-01 00:00.
+const MODIFIED: [u8; 4] = [0x00, 0x00, 0x21, 0x00];
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+/// Size of the end of central directory record without its comment.
+const END_LENGTH: usize = 22;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// Writes a zip archive holding `entries`, each a slash-separated path inside the
+/// archive and the file's contents. Contents are deflated.
+pub fn write<'a>(
+    path: &Path,
+    entries: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+) -> io::Result<()> {
+    let mut out = Counting {
+        inner: BufWriter::new(File::create(path)?),
+        written: 0,
+    };
+    let mut central = Vec::new();
+    let mut count: u16 = 0;
+
+    for (name, contents) in entries {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents)?;
+        let compressed = encoder.finish()?;
+        let crc = crc32fast::hash(contents);
+        let size = checked_u32(contents.len())?;
+        let compressed_size = checked_u32(compressed.len())?;
+        let offset = checked_u32(out.written)?;
+        let name = name.as_bytes();
+        let name_length = u16::try_from(name.len()).map_err(|_| too_large())?;
+
+        // Local file header. Bit 11 marks the name as UTF-8.
+        out.write_all(&LOCAL_HEADER.to_le_bytes())?;
+        out.write_all(&20_u16.to_le_bytes())?;
+        out.write_all(&0x0800_u16.to_le_bytes())?;
+        out.write_all(&DEFLATED.to_le_bytes())?;
+        out.write_all(&MODIFIED)?;
+        out.write_all(&crc.to_le_bytes())?;
+        out.write_all(&compressed_size.to_le_bytes())?;
+        out.write_all(&size.to_le_bytes())?;
+        out.write_all(&name_length.to_le_bytes())?;
+        out.write_all(&0_u16.to_le_bytes())?;
+        out.write_all(name)?;
+        out.write_all(&compressed)?;
+
+        central.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+        central.extend_from_slice(&20_u16.to_le_bytes());
+        central.extend_from_slice(&20_u16.to_le_bytes());
+        central.extend_from_slice(&0x0800_u16.to_le_bytes());
+        central.extend_from_slice(&DEFLATED.to_le_bytes());
+        central.extend_from_slice(&MODIFIED);
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&compressed_size.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&name_length.to_le_bytes());
+        // Extra field, comment, disk number, internal and external attributes.
+        central.extend_from_slice(&[0; 12]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name);
+
+        count = count.checked_add(1).ok_or_else(too_large)?;
+    }
+
+    let central_offset = checked_u32(out.written)?;
+    let central_size = checked_u32(central.len())?;
+    out.write_all(&central)?;
+
+    out.write_all(&END_OF_CENTRAL_DIRECTORY.to_le_bytes())?;
+    out.write_all(&[0; 4])?;
+    out.write_all(&count.to_le_bytes())?;
+    out.write_all(&count.to_le_bytes())?;
+    out.write_all(&central_size.to_le_bytes())?;
+    out.write_all(&central_offset.to_le_bytes())?;
+    out.write_all(&0_u16.to_le_bytes())?;
+    out.inner.flush()
+}
+
+/// Reads the zip archive at `path`: every file in it, with its slash-separated path
+/// inside the archive. Entries must be stored or deflated, as [`write`] and most zip
+/// tools write them; directories are left out.
+pub fn read(path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let data = fs::read(path)?;
+    let end = (0..=data.len().saturating_sub(END_LENGTH))
+        .rev()
+        .find(|&at| data[at..].starts_with(&END_OF_CENTRAL_DIRECTORY.to_le_bytes()))
+        .ok_or_else(|| invalid("it is not a zip archive"))?;
+
+    let count = u16_at(&data, end + 10)?;
+    let mut at = u32_at(&data, end + 16)? as usize;
+    let mut entries = Vec::new();
+
+    for _ in 0..count {
+        if u32_at(&data, at)? != CENTRAL_HEADER {
+            return Err(invalid("its list of files is broken"));
+        }
+        let flags = u16_at(&data, at + 8)?;
+        let method = u16_at(&data, at + 10)?;
+        let crc = u32_at(&data, at + 16)?;
+        let compressed_size = u32_at(&data, at + 20)? as usize;
+        let size = u32_at(&data, at + 24)? as usize;
+        let name_length = usize::from(u16_at(&data, at + 28)?);
+        let extra_length = usize::from(u16_at(&data, at + 30)?);
+        let comment_length = usize::from(u16_at(&data, at + 32)?);
+        let offset = u32_at(&data, at + 42)? as usize;
+        let name = String::from_utf8_lossy(bytes_at(&data, at + 46, name_length)?).into_owned();
+        at += 46 + name_length + extra_length + comment_length;
+
+        if name.ends_with('/') {
+            continue;
+        }
+        if flags & 1 != 0 {
+            return Err(invalid(&format!("{name} is encrypted")));
+        }
+
+        if u32_at(&data, offset)? != LOCAL_HEADER {
+            return Err(invalid(&format!("{name} is broken")));
+        }
+        let start = offset
+            + 30
+            + usize::from(u16_at(&data, offset + 26)?)
+            + usize::from(u16_at(&data, offset + 28)?);
+        let compressed = bytes_at(&data, start, compressed_size)?;
+        let contents = match method {
+            STORED => compressed.to_vec(),
+            DEFLATED => {
+                let mut contents = Vec::with_capacity(size);
+                DeflateDecoder::new(compressed).read_to_end(&mut contents)?;
+                contents
+            }
+            _ => {
+                return Err(invalid(&format!(
+                    "{name} is compressed in a way not supported"
+                )));
+            }
+        };
+        if contents.len() != size || crc32fast::hash(&contents) != crc {
+            return Err(invalid(&format!("{name} is damaged")));
+        }
+
+        entries.push((name, contents));
+    }
+
+    Ok(entries)
+}
+
+fn bytes_at(data: &[u8], at: usize, length: usize) -> io::Result<&[u8]> {
+    at.checked_add(length)
+        .and_then(|end| data.get(at..end))
+        .ok_or_else(|| invalid("it ends too early"))
+}
+
+fn u16_at(data: &[u8], at: usize) -> io::Result<u16> {
+    let bytes = bytes_at(data, at, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], at: usize) -> io::Result<u32> {
+    let bytes = bytes_at(data, at, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+/// Tracks how far into the archive the next write lands.
+struct Counting<W> {
+    inner: W,
+    written: usize,
+}
+
+impl<W: Write> Write for Counting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn checked_u32(value: usize) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| too_large())
+}
+
+fn too_large() -> io::Error {
+    io::Error::other("the archive is too large for the zip format")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::storage;
+
+    #[test]
+    fn written_archives_read_back() {
+        let _data = storage::use_test_data_dir();
+        let path = storage::data_dir().join("read-back.zip");
+        let big = "All work and no play. ".repeat(500);
+        let entries = [
+            ("Notes/Ünïcode page.md", big.as_bytes()),
+            ("empty.txt", b"".as_slice()),
+            ("attachments/image.png", &[0x89, b'P', b'N', b'G', 0, 255]),
+        ];
+
+        write(&path, entries).unwrap();
+        let read = read(&path).unwrap();
+        let read = read
+            .iter()
+            .map(|(name, contents)| (name.as_str(), contents.as_slice()))
+            .collect::<Vec<_>>();
+        assert_eq!(read, entries);
+
+        // A damaged archive is reported rather than read wrongly.
+        let mut data = fs::read(&path).unwrap();
+        data[30 + entries[0].0.len() + 5] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+        assert!(super::read(&path).is_err());
+    }
+}
@@ -0,0 +1,486 @@
+use iced::mouse::{self, ScrollDelta};
+use iced::widget::canvas::{self, Cache, Frame, Geometry, LineDash, Path, Stroke, Text};
+use iced::widget::{
+    button, canvas as canvas_widget, checkbox, column, container, horizontal_space, row, text,
+};
+use iced::{
+    Alignment, Color, Element, Length, Point, Rectangle, Renderer, Task, Theme, Vector, alignment,
+};
+use std::collections::{HashMap, HashSet};
+
+use super::layout;
+use crate::notes::colors::Palette;
+use crate::notes::notes::{NoteId, Notes};
+
+/// How far from the center the layout reaches at zoom 1.
+const VIEW_RADIUS: f32 = 250.0;
+const NODE_RADIUS: f32 = 7.0;
+const MIN_ZOOM: f32 = 0.2;
+const MAX_ZOOM: f32 = 5.0;
+/// Titles are drawn for every node from this zoom on, and only on hover below it.
+const LABEL_ZOOM: f32 = 0.75;
+/// A press and release closer than this is a click rather than a drag.
+const CLICK_SLOP: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EdgeKind {
+    Link,
+    Tag,
+}
+
+#[derive(Debug)]
+struct Node {
+    id: NoteId,
+    title: String,
+    color: Option<Color>,
+    position: Point,
+}
+
+/// Notes as a network: pages are nodes, wiki links (and optionally shared tags) are
+/// the edges between them.
+#[derive(Debug)]
+pub struct Graph {
+    nodes: Vec<Node>,
+    edges: Vec<(usize, usize, EdgeKind)>,
+    show_tags: bool,
+    /// Counts the layouts started, so a layout that finishes after a newer one started is
+    /// thrown away.
+    generation: u64,
+    /// Whether the nodes have not been placed yet, while the first layout runs.
+    laying_out: bool,
+    zoom: f32,
+    offset: Vector,
+    cache: Cache,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    TagsToggled(bool),
+    Zoomed {
+        factor: f32,
+        anchor: Vector,
+    },
+    Panned(Vector),
+    NodeClicked(NoteId),
+    ResetView,
+    Back,
+    LaidOut {
+        generation: u64,
+        positions: Vec<Point>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Open(NoteId),
+    Back,
+    /// The shared-tag edges were switched on or off, so the graph needs a
+    /// [`Graph::rebuild`].
+    TagsToggled,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            show_tags: false,
+            generation: 0,
+            laying_out: false,
+            zoom: 1.0,
+            offset: Vector::new(0.0, 0.0),
+            cache: Cache::new(),
+        }
+    }
+}
+
+impl Graph {
+    /// Lays out `notes` from scratch and centers the view on them.
+    pub fn open(&mut self, notes: &Notes, palette: &Palette) -> Task<Message> {
+        self.zoom = 1.0;
+        self.offset = Vector::new(0.0, 0.0);
+        self.nodes.clear();
+        self.edges.clear();
+        self.laying_out = true;
+        self.rebuild(notes, palette)
+    }
+
+    /// Lays out `notes` again, keeping the current zoom and pan. Archived pages are
+    /// left out. When the same pages are linked the same way, only titles and colors
+    /// change and the nodes stay where they are; otherwise the returned task lays them
+    /// out in the background, and they keep their old places until it is done.
+    pub fn rebuild(&mut self, notes: &Notes, palette: &Palette) -> Task<Message> {
+        let shown = notes
+            .iter()
+            .filter(|note| !note.archived)
+            .collect::<Vec<_>>();
+        let index_of = |id: NoteId| shown.iter().position(|note| note.id == id);
+
+        let mut linked = HashSet::new();
+        let mut edges = Vec::new();
+        for (from, note) in shown.iter().enumerate() {
+            for to in note.links().values().filter_map(|id| index_of(*id)) {
+                let pair = (from.min(to), from.max(to));
+                if from != to && linked.insert(pair) {
+                    edges.push((pair.0, pair.1, EdgeKind::Link));
+                }
+            }
+        }
+
+        // Comparing every pair of pages is slow on large notebooks, so it is only done
+        // when the tag edges are shown.
+        for (a, first) in shown.iter().enumerate().filter(|_| self.show_tags) {
+            let tags = first
+                .tags
+                .iter()
+                .map(|tag| tag.to_lowercase())
+                .collect::<HashSet<_>>();
+
+            for (b, second) in shown.iter().enumerate().skip(a + 1) {
+                let shares_tag = second
+                    .tags
+                    .iter()
+                    .any(|tag| tags.contains(&tag.to_lowercase()));
+                if shares_tag && !linked.contains(&(a, b)) {
+                    edges.push((a, b, EdgeKind::Tag));
+                }
+            }
+        }
+
+        let unchanged = edges == self.edges
+            && shown.len() == self.nodes.len()
+            && shown
+                .iter()
+                .zip(&self.nodes)
+                .all(|(note, node)| note.id == node.id);
+
+        let positions = self
+            .nodes
+            .iter()
+            .map(|node| (node.id, node.position))
+            .collect::<HashMap<_, _>>();
+        self.nodes = shown
+            .iter()
+            .map(|note| Node {
+                id: note.id,
+                title: note.display_title().to_string(),
+                color: palette.swatch(&note.color),
+                position: positions.get(&note.id).copied().unwrap_or(Point::ORIGIN),
+            })
+            .collect();
+        self.edges = edges;
+        self.cache.clear();
+
+        if unchanged && !self.laying_out {
+            Task::none()
+        } else {
+            self.layout()
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Option<Event> {
+        match message {
+            Message::TagsToggled(show) => {
+                self.show_tags = show;
+                Some(Event::TagsToggled)
+            }
+            Message::Zoomed { factor, anchor } => {
+                let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+                // Keep the point under the cursor where it is.
+                let world = (anchor - self.offset) * (1.0 / self.zoom);
+                self.offset = anchor - world * zoom;
+                self.zoom = zoom;
+                self.cache.clear();
+                None
+            }
+            Message::Panned(delta) => {
+                self.offset = self.offset + delta;
+                self.cache.clear();
+                None
+            }
+            Message::NodeClicked(id) => Some(Event::Open(id)),
+            Message::ResetView => {
+                self.zoom = 1.0;
+                self.offset = Vector::new(0.0, 0.0);
+                self.cache.clear();
+                None
+            }
+            Message::Back => Some(Event::Back),
+            Message::LaidOut {
+                generation,
+                positions,
+            } => {
+                if generation == self.generation && positions.len() == self.nodes.len() {
+                    for (node, position) in self.nodes.iter_mut().zip(positions) {
+                        node.position = position;
+                    }
+                    self.laying_out = false;
+                    self.cache.clear();
+                }
+                None
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let header = row![
+            text("Graph").size(28),
+            horizontal_space(),
+            checkbox("Connect pages that share a tag", self.show_tags)
+                .on_toggle(Message::TagsToggled),
+            button("Reset view").on_press(Message::ResetView),
+            button("Back to notebook").on_press(Message::Back),
+        ]
+        .spacing(12)
+        .align_y(Alignment::Center);
+
+        let body: Element<'_, Message> = if self.nodes.is_empty() {
+            container(text("No pages to show yet.").size(16))
+                .center(Length::Fill)
+                .into()
+        } else if self.laying_out {
+            container(text("Laying out the pages…").size(16))
+                .center(Length::Fill)
+                .into()
+        } else {
+            canvas_widget(self)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into()
+        };
+
+        container(
+            column![
+                header,
+                text("Scroll to zoom, drag to move around, click a page to open it.").size(14),
+                body,
+            ]
+            .spacing(12),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .padding([24, 32])
+        .into()
+    }
+
+    /// Starts laying out the current nodes and edges off the UI thread.
+    fn layout(&mut self) -> Task<Message> {
+        self.generation += 1;
+        let generation = self.generation;
+        let count = self.nodes.len();
+        let edges = self
+            .edges
+            .iter()
+            .map(|&(a, b, _)| (a, b))
+            .collect::<Vec<_>>();
+
+        Task::perform(
+            async move {
+                let positions = layout::force_directed(count, &edges);
+                let radius = layout::radius(&positions);
+                let scale = if radius > VIEW_RADIUS {
+                    VIEW_RADIUS / radius
+                } else {
+                    1.0
+                };
+                positions
+                    .into_iter()
+                    .map(|position| Point::new(position.x * scale, position.y * scale))
+                    .collect()
+            },
+            move |positions| Message::LaidOut {
+                generation,
+                positions,
+            },
+        )
+    }
+
+    /// Where `node` is drawn inside a canvas of the given size.
+    fn screen_position(&self, node: &Node, bounds: Rectangle) -> Point {
+        let center = Point::new(bounds.width / 2.0, bounds.height / 2.0);
+        center + self.offset + Vector::new(node.position.x, node.position.y) * self.zoom
+    }
+
+    fn node_at(&self, bounds: Rectangle, cursor: mouse::Cursor) -> Option<&Node> {
+        let cursor = cursor.position_in(bounds)?;
+
+        // Later nodes are drawn on top, so they win when nodes overlap.
+        self.nodes
+            .iter()
+            .rev()
+            .find(|node| cursor.distance(self.screen_position(node, bounds)) <= NODE_RADIUS + 3.0)
+    }
+
+    fn label(&self, node: &Node, bounds: Rectangle, color: Color) -> Text {
+        Text {
+            content: node.title.clone(),
+            position: self.screen_position(node, bounds) + Vector::new(0.0, NODE_RADIUS + 4.0),
+            color,
+            size: 13.0.into(),
+            horizontal_alignment: alignment::Horizontal::Center,
+            vertical_alignment: alignment::Vertical::Top,
+            ..Text::default()
+        }
+    }
+}
+
+/// A drag in progress on the graph canvas.
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    origin: Point,
+    last: Point,
+}
+
+#[derive(Debug, Default)]
+pub struct Interaction {
+    drag: Option<Drag>,
+}
+
+impl canvas::Program<Message> for Graph {
+    type State = Interaction;
+
+    fn update(
+        &self,
+        state: &mut Interaction,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (canvas::event::Status, Option<Message>) {
+        let canvas::Event::Mouse(event) = event else {
+            return (canvas::event::Status::Ignored, None);
+        };
+
+        match event {
+            mouse::Event::WheelScrolled { delta } => {
+                let Some(position) = cursor.position_in(bounds) else {
+                    return (canvas::event::Status::Ignored, None);
+                };
+                let factor = match delta {
+                    ScrollDelta::Lines { y, .. } => 1.15_f32.powf(y),
+                    ScrollDelta::Pixels { y, .. } => 1.15_f32.powf(y / 50.0),
+                };
+                let anchor = position - Point::new(bounds.width / 2.0, bounds.height / 2.0);
+                (
+                    canvas::event::Status::Captured,
+                    Some(Message::Zoomed { factor, anchor }),
+                )
+            }
+            mouse::Event::ButtonPressed(mouse::Button::Left) => {
+                let Some(position) = cursor.position_over(bounds) else {
+                    return (canvas::event::Status::Ignored, None);
+                };
+                state.drag = Some(Drag {
+                    origin: position,
+                    last: position,
+                });
+                (canvas::event::Status::Captured, None)
+            }
+            mouse::Event::CursorMoved { position } => match &mut state.drag {
+                Some(drag) => {
+                    let delta = position - drag.last;
+                    drag.last = position;
+                    (
+                        canvas::event::Status::Captured,
+                        Some(Message::Panned(delta)),
+                    )
+                }
+                None => (canvas::event::Status::Ignored, None),
+            },
+            mouse::Event::ButtonReleased(mouse::Button::Left) => {
+                let Some(drag) = state.drag.take() else {
+                    return (canvas::event::Status::Ignored, None);
+                };
+                let clicked = drag.origin.distance(drag.last) < CLICK_SLOP;
+                let message = clicked
+                    .then(|| self.node_at(bounds, cursor))
+                    .flatten()
+                    .map(|node| Message::NodeClicked(node.id));
+                (canvas::event::Status::Captured, message)
+            }
+            _ => (canvas::event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Interaction,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let palette = theme.extended_palette();
+        let text_color = palette.background.base.text;
+        let default_node = palette.primary.strong.color;
+
+        let graph = self.cache.draw(renderer, bounds.size(), |frame| {
+            for &(a, b, kind) in &self.edges {
+                let line = Path::line(
+                    self.screen_position(&self.nodes[a], bounds),
+                    self.screen_position(&self.nodes[b], bounds),
+                );
+                let stroke = match kind {
+                    EdgeKind::Link => Stroke::default()
+                        .with_color(Color {
+                            a: 0.5,
+                            ..text_color
+                        })
+                        .with_width(1.5),
+                    EdgeKind::Tag => Stroke {
+                        line_dash: LineDash {
+                            segments: &[4.0, 4.0],
+                            offset: 0,
+                        },
+                        ..Stroke::default()
+                            .with_color(Color {
+                                a: 0.3,
+                                ..text_color
+                            })
+                            .with_width(1.0)
+                    },
+                };
+                frame.stroke(&line, stroke);
+            }
+
+            for node in &self.nodes {
+                let dot = Path::circle(self.screen_position(node, bounds), NODE_RADIUS);
+                frame.fill(&dot, node.color.unwrap_or(default_node));
+
+                if self.zoom >= LABEL_ZOOM {
+                    frame.fill_text(self.label(node, bounds, text_color));
+                }
+            }
+        });
+
+        let mut hover = Frame::new(renderer, bounds.size());
+        if let Some(node) = self.node_at(bounds, cursor) {
+            let ring = Path::circle(self.screen_position(node, bounds), NODE_RADIUS + 3.0);
+            hover.stroke(
+                &ring,
+                Stroke::default().with_color(text_color).with_width(2.0),
+            );
+            if self.zoom < LABEL_ZOOM {
+                hover.fill_text(self.label(node, bounds, text_color));
+            }
+        }
+
+        vec![graph, hover.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        state: &Interaction,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        if state.drag.is_some() {
+            mouse::Interaction::Grabbing
+        } else if self.node_at(bounds, cursor).is_some() {
+            mouse::Interaction::Pointer
+        } else if cursor.is_over(bounds) {
+            mouse::Interaction::Grab
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use iced::{Point, Vector};
+
+/// Distance connected nodes settle at.
+const SPACING: f32 = 60.0;
+const ITERATIONS: usize = 300;
+/// Pull of every node toward the center, so unconnected groups stay in view.
+const GRAVITY: f32 = 0.5;
+
+/// Places `count` nodes with a force-directed layout: every pair of nodes repels and
+/// every edge pulls its ends together (Fruchterman-Reingold).
+///
+/// The result is deterministic for a given input, centered on the origin, and does not
+/// depend on anything but its arguments, so it can be computed without a window.
+pub fn force_directed(count: usize, edges: &[(usize, usize)]) -> Vec<Point> {
+    let mut positions = initial_positions(count);
+    if count < 2 {
+        return positions;
+    }
+
+    let edges = edges
+        .iter()
+        .copied()
+        .filter(|&(a, b)| a != b && a < count && b < count)
+        .collect::<Vec<_>>();
+
+    let k = SPACING;
+    let mut temperature = SPACING * (count as f32).sqrt();
+    let cooling = temperature / ITERATIONS as f32;
+
+    for _ in 0..ITERATIONS {
+        let mut moves = vec![Vector::new(0.0, 0.0); count];
+
+        for a in 0..count {
+            for b in a + 1..count {
+                let delta = separation(positions[a], positions[b], a, b);
+                let distance = length(delta);
+                let push = delta * (k * k / (distance * distance));
+                moves[a] = moves[a] + push;
+                moves[b] = moves[b] - push;
+            }
+        }
+
+        for &(a, b) in &edges {
+            let delta = separation(positions[a], positions[b], a, b);
+            let distance = length(delta);
+            let pull = delta * (distance / k);
+            moves[a] = moves[a] - pull;
+            moves[b] = moves[b] + pull;
+        }
+
+        for (position, movement) in positions.iter().zip(moves.iter_mut()) {
+            *movement = *movement - Vector::new(position.x, position.y) * GRAVITY;
+        }
+
+        for (position, movement) in positions.iter_mut().zip(&moves) {
+            let distance = length(*movement);
+            if distance > 0.0 {
+                *position = *position + *movement * (distance.min(temperature) / distance);
+            }
+        }
+
+        temperature = (temperature - cooling).max(0.01);
+    }
+
+    center(&mut positions);
+    positions
+}
+
+/// The distance from the origin to the node furthest from it.
+pub fn radius(positions: &[Point]) -> f32 {
+    positions
+        .iter()
+        .map(|position| length(Vector::new(position.x, position.y)))
+        .fold(0.0, f32::max)
+}
+
+/// Nodes on a sunflower spiral, so no two start at the same spot.
+fn initial_positions(count: usize) -> Vec<Point> {
+    const GOLDEN_ANGLE: f32 = 2.399_963;
+
+    (0..count)
+        .map(|index| {
+            let radius = SPACING * (index as f32).sqrt();
+            let angle = index as f32 * GOLDEN_ANGLE;
+            Point::new(radius * angle.cos(), radius * angle.sin())
+        })
+        .collect()
+}
+
+/// The vector from `b` to `a`, nudged apart when they overlap.
+fn separation(a: Point, b: Point, a_index: usize, b_index: usize) -> Vector {
+    let delta = a - b;
+    if length(delta) > 0.01 {
+        return delta;
+    }
+
+    let angle = (a_index * 31 + b_index * 17) as f32;
+    Vector::new(angle.cos(), angle.sin()) * 0.01
+}
+
+fn center(positions: &mut [Point]) {
+    let count = positions.len() as f32;
+    let sum = positions
+        .iter()
+        .fold(Vector::new(0.0, 0.0), |sum, position| {
+            sum + Vector::new(position.x, position.y)
+        });
+    let middle = sum * (1.0 / count);
+
+    for position in positions {
+        *position = *position - middle;
+    }
+}
+
+fn length(vector: Vector) -> f32 {
+    (vector.x * vector.x + vector.y * vector.y).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: Point, b: Point) -> f32 {
+        length(a - b)
+    }
+
+    #[test]
+    fn empty_and_single_graphs() {
+        assert!(force_directed(0, &[]).is_empty());
+        assert_eq!(force_directed(1, &[(0, 0), (0, 3)]), [Point::ORIGIN]);
+        assert_eq!(radius(&[]), 0.0);
+    }
+
+    #[test]
+    fn same_input_same_layout() {
+        let edges = [(0, 1), (1, 2), (2, 3), (3, 0), (4, 5)];
+        let first = force_directed(8, &edges);
+        assert_eq!(first, force_directed(8, &edges));
+        assert!(
+            first
+                .iter()
+                .all(|point| point.x.is_finite() && point.y.is_finite())
+        );
+
+        // Centered on the origin.
+        let sum = first.iter().fold(Vector::new(0.0, 0.0), |sum, point| {
+            sum + Vector::new(point.x, point.y)
+        });
+        assert!(length(sum) / 8.0 < 0.01);
+    }
+
+    #[test]
+    fn connected_nodes_end_up_closer() {
+        // Two linked pairs and two loose nodes; edges to missing nodes are ignored.
+        let positions = force_directed(6, &[(0, 1), (2, 3), (1, 9)]);
+        let linked = distance(positions[0], positions[1]).max(distance(positions[2], positions[3]));
+        let unlinked = [(0, 2), (0, 4), (1, 5), (3, 4), (4, 5)]
+            .iter()
+            .map(|&(a, b)| distance(positions[a], positions[b]))
+            .fold(f32::MAX, f32::min);
+        assert!(linked < unlinked, "linked {linked} vs unlinked {unlinked}");
+    }
+}
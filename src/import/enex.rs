@@ -0,0 +1,313 @@
+use base64::Engine;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use md5::{Digest, Md5};
+use roxmltree::{Document, Node, ParsingOptions};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::html::{self, MEDIA_MARK};
+use super::import::{Draft, Embed, Plan, Source};
+use crate::export::front_matter;
+use crate::notes::attachments;
+use crate::notes::notes;
+
+/// A file embedded in an Evernote note.
+struct Resource {
+    name: String,
+    mime: String,
+    bytes: Vec<u8>,
+}
+
+/// Works out how the notes in Evernote's `.enex` exports would be imported. Each file
+/// holds one notebook, which becomes a folder named after the file.
+pub fn scan(files: &[PathBuf]) -> Result<Plan, String> {
+    let mut plan = Plan {
+        source: match files {
+            [file] => file.display().to_string(),
+            files => format!("{} Evernote exports", files.len()),
+        },
+        ..Plan::default()
+    };
+
+    for file in files {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match fs::read_to_string(file) {
+            Ok(text) => scan_file(&text, file, &mut plan),
+            Err(error) => plan
+                .warnings
+                .push(format!("{name}: could not be read ({error})")),
+        }
+    }
+
+    Ok(plan)
+}
+
+fn scan_file(text: &str, file: &Path, plan: &mut Plan) {
+    let file_name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let notebook = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let document = match Document::parse_with_options(text, options) {
+        Ok(document) => document,
+        Err(error) => {
+            plan.warnings
+                .push(format!("{file_name}: not an Evernote export ({error})"));
+            return;
+        }
+    };
+
+    for note in document
+        .root_element()
+        .children()
+        .filter(|node| node.has_tag_name("note"))
+    {
+        let draft = draft(note, &file_name, &mut plan.warnings);
+        plan.drafts.push(Draft {
+            folder: vec![notebook.clone()],
+            ..draft
+        });
+    }
+}
+
+fn draft(note: Node, file_name: &str, warnings: &mut Vec<String>) -> Draft {
+    let title = child_text(note, "title").trim().to_string();
+    let source = format!("{file_name}: {title}");
+    let tags = note
+        .children()
+        .filter(|node| node.has_tag_name("tag"))
+        .map(|node| node.text().unwrap_or_default().trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    let updated = timestamp(&child_text(note, "updated")).unwrap_or_else(notes::now);
+    let created = timestamp(&child_text(note, "created")).unwrap_or(updated);
+
+    let mut resources = HashMap::new();
+    let mut order = Vec::new();
+    for node in note.children().filter(|node| node.has_tag_name("resource")) {
+        match resource(node) {
+            Some(resource) => {
+                let hash = md5_hex(&resource.bytes);
+                order.push(hash.clone());
+                resources.insert(hash, resource);
+            }
+            None => warnings.push(format!("{source}: an attached file could not be decoded")),
+        }
+    }
+
+    let content = child_text(note, "content");
+    let markdown = html::to_markdown(&content).unwrap_or_else(|error| {
+        warnings.push(format!("{source}: formatting was lost ({error})"));
+        html::strip_tags(&content)
+    });
+
+    // Put images where the note shows them; everything else becomes an attachment.
+    let mut body = String::new();
+    let mut embeds = Vec::new();
+    let mut shown = Vec::new();
+    for (index, part) in markdown.split(MEDIA_MARK).enumerate() {
+        if index % 2 == 0 {
+            body.push_str(part);
+            continue;
+        }
+        match resources.get(part) {
+            Some(resource) if resource.mime.starts_with("image/") => {
+                let start = body.len();
+                body.push_str(&attachments::image_markdown(&resource.name, &resource.name));
+                embeds.push(Embed {
+                    range: start..body.len(),
+                    alt: resource.name.clone(),
+                    source: Source::Bytes {
+                        name: resource.name.clone(),
+                        bytes: resource.bytes.clone(),
+                    },
+                });
+                shown.push(part.to_string());
+            }
+            Some(_) => {}
+            None => warnings.push(format!("{source}: a missing attached file was left out")),
+        }
+    }
+
+    let attachments = order
+        .iter()
+        .filter(|hash| !shown.contains(hash))
+        .filter_map(|hash| resources.remove(hash))
+        .map(|resource| {
+            let source = Source::Bytes {
+                name: resource.name.clone(),
+                bytes: resource.bytes,
+            };
+            (resource.name, source)
+        })
+        .collect();
+
+    Draft {
+        source,
+        title,
+        body: body.trim().to_string(),
+        tags,
+        created,
+        updated,
+        embeds,
+        attachments,
+        ..Draft::default()
+    }
+}
+
+fn resource(node: Node) -> Option<Resource> {
+    let data = node.children().find(|child| child.has_tag_name("data"))?;
+    let bytes = base64_decode(data.text().unwrap_or_default())?;
+    let mime = child_text(node, "mime").trim().to_string();
+    let name = node
+        .children()
+        .find(|child| child.has_tag_name("resource-attributes"))
+        .map(|attributes| child_text(attributes, "file-name").trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| match mime.split_once('/') {
+            Some((_, subtype)) if !subtype.is_empty() => format!("attachment.{subtype}"),
+            _ => String::from("attachment"),
+        });
+
+    Some(Resource { name, mime, bytes })
+}
+
+fn child_text(node: Node, name: &str) -> String {
+    node.children()
+        .find(|child| child.has_tag_name(name))
+        .map(|child| {
+            child
+                .children()
+                .filter_map(|node| node.text())
+                .collect::<String>()
+        })
+        .unwrap_or_default()
+}
+
+/// Seconds since the Unix epoch of Evernote's `20240501T093000Z` timestamps.
+fn timestamp(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.len() < 15 || !text.is_ascii() {
+        return None;
+    }
+    front_matter::parse_timestamp(&format!(
+        "{}-{}-{}T{}:{}:{}Z",
+        &text[0..4],
+        &text[4..6],
+        &text[6..8],
+        &text[9..11],
+        &text[11..13],
+        &text[13..15],
+    ))
+}
+
+/// Decodes Evernote's base64, which wraps its lines and may leave out the padding.
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    const ENGINE: GeneralPurpose = GeneralPurpose::new(
+        &alphabet::STANDARD,
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+
+    let text = text.split_ascii_whitespace().collect::<String>();
+    ENGINE.decode(text).ok()
+}
+
+/// MD5 of `bytes` in lowercase hex, which is how notes refer to their resources.
+fn md5_hex(bytes: &[u8]) -> String {
+    Md5::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/import/Recipes.enex")
+    }
+
+    #[test]
+    fn notes_come_across_with_their_resources() {
+        let plan = scan(&[fixture()]).unwrap();
+        assert_eq!(plan.drafts.len(), 2);
+
+        let pancakes = &plan.drafts[0];
+        assert_eq!(pancakes.title, "Pancakes");
+        assert_eq!(pancakes.source, "Recipes.enex: Pancakes");
+        assert_eq!(pancakes.folder, ["Recipes"]);
+        assert_eq!(pancakes.tags, ["breakfast", "sweet"]);
+        assert_eq!(pancakes.created, 1_714_555_800);
+        assert_eq!(pancakes.updated, 1_714_644_900);
+        assert_eq!(
+            pancakes.body,
+            "# Shopping\n\n- [x] Eggs\n- [ ] Milk\n\n![stack.png](stack.png)"
+        );
+
+        // The image the note shows by hash is embedded where it stands.
+        let [embed] = pancakes.embeds.as_slice() else {
+            panic!("expected one embed, got {:?}", pancakes.embeds);
+        };
+        assert_eq!(
+            &pancakes.body[embed.range.clone()],
+            "![stack.png](stack.png)"
+        );
+        let Source::Bytes { name, bytes } = &embed.source else {
+            panic!("expected decoded bytes");
+        };
+        assert_eq!(name, "stack.png");
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(md5_hex(bytes), "856e67be4f0c8d4b5144acf9bf655b1d");
+
+        // The PDF it does not show is attached, named after its type.
+        let [(name, Source::Bytes { bytes, .. })] = pancakes.attachments.as_slice() else {
+            panic!("expected one attachment, got {:?}", pancakes.attachments);
+        };
+        assert_eq!(name, "attachment.pdf");
+        assert!(bytes.starts_with(b"%PDF-1.4"));
+
+        let broken = &plan.drafts[1];
+        assert_eq!(broken.body, "Only text survives.");
+        assert!(broken.embeds.is_empty() && broken.attachments.is_empty());
+
+        assert_eq!(
+            plan.warnings,
+            [
+                "Recipes.enex: Pancakes: a missing attached file was left out",
+                "Recipes.enex: Broken scan: an attached file could not be decoded",
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_files_are_warnings() {
+        let missing = fixture().with_file_name("Missing.enex");
+        let plan = scan(&[missing]).unwrap();
+        assert!(plan.drafts.is_empty());
+        assert_eq!(plan.warnings.len(), 1);
+        assert!(plan.warnings[0].starts_with("Missing.enex: could not be read"));
+    }
+
+    #[test]
+    fn base64_and_md5_match_evernote() {
+        assert_eq!(base64_decode("aGVs\nbG8=").unwrap(), b"hello");
+        assert_eq!(base64_decode("aGVs bG8").unwrap(), b"hello");
+        assert_eq!(base64_decode("not*base64"), None);
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(md5_hex(b"hello"), "5d41402abc4b2a76b9719d911017c592");
+    }
+}
@@ -0,0 +1,430 @@
+use roxmltree::{Document, Node, ParsingOptions};
+
+/// Surrounds the hash of the resource an `<en-media>` element shows, so importers can
+/// put the image or file in its place.
+pub const MEDIA_MARK: char = '\u{fffc}';
+
+/// Named entities HTML notes use that XML does not know, as numeric references.
+const ENTITIES: [(&str, &str); 19] = [
+    ("nbsp", "#160"),
+    ("copy", "#169"),
+    ("reg", "#174"),
+    ("trade", "#8482"),
+    ("mdash", "#8212"),
+    ("ndash", "#8211"),
+    ("hellip", "#8230"),
+    ("lsquo", "#8216"),
+    ("rsquo", "#8217"),
+    ("ldquo", "#8220"),
+    ("rdquo", "#8221"),
+    ("laquo", "#171"),
+    ("raquo", "#187"),
+    ("bull", "#8226"),
+    ("middot", "#183"),
+    ("times", "#215"),
+    ("deg", "#176"),
+    ("euro", "#8364"),
+    ("pound", "#163"),
+];
+
+const BLOCKS: [&str; 26] = [
+    "p",
+    "div",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "main",
+    "aside",
+    "center",
+    "body",
+    "html",
+    "en-note",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "blockquote",
+    "pre",
+    "hr",
+    "table",
+    "head",
+    "dl",
+];
+
+/// Converts well-formed HTML, such as the XHTML of an Evernote note, to markdown.
+/// Evernote's `<en-media>` elements become the resource hash between [`MEDIA_MARK`]s
+/// and its `<en-todo>` checkboxes become task list items.
+pub fn to_markdown(html: &str) -> Result<String, String> {
+    let xml = replace_entities(html);
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let document =
+        Document::parse_with_options(&xml, options).map_err(|error| error.to_string())?;
+    Ok(blocks(document.root_element()))
+}
+
+/// The text of `html` with every tag left out, for notes that cannot be converted.
+pub fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            ch if !in_tag => text.push(ch),
+            _ => {}
+        }
+    }
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The children of `node` as markdown blocks separated by blank lines.
+fn blocks(node: Node) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut paragraph = String::new();
+
+    for child in node.children() {
+        if is_block(child) {
+            flush(&mut paragraph, &mut out);
+            let block = block(child);
+            if !block.trim().is_empty() {
+                out.push(block);
+            }
+        } else {
+            inline(child, &mut paragraph);
+        }
+    }
+    flush(&mut paragraph, &mut out);
+
+    // Checkboxes on consecutive lines make one task list rather than several.
+    let is_task = |block: &str| {
+        (block.starts_with("- [ ] ") || block.starts_with("- [x] ")) && !block.contains('\n')
+    };
+    let mut markdown = String::new();
+    for (index, block) in out.iter().enumerate() {
+        if index > 0 {
+            let tight = is_task(&out[index - 1]) && is_task(block);
+            markdown.push_str(if tight { "\n" } else { "\n\n" });
+        }
+        markdown.push_str(block);
+    }
+    markdown
+}
+
+fn block(node: Node) -> String {
+    let name = node.tag_name().name().to_lowercase();
+    match name.as_str() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = usize::from(name.as_bytes()[1] - b'0');
+            format!("{} {}", "#".repeat(level), inline_text(node))
+        }
+        "ul" | "ol" => list(node),
+        "blockquote" => blocks(node)
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    String::from(">")
+                } else {
+                    format!("> {line}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "pre" => {
+            let code = node
+                .descendants()
+                .filter_map(|node| node.text().filter(|_| node.is_text()))
+                .collect::<String>();
+            format!("```\n{}\n```", code.trim_end_matches('\n'))
+        }
+        "hr" => String::from("---"),
+        "table" => table(node),
+        "head" => String::new(),
+        _ => blocks(node),
+    }
+}
+
+fn list(node: Node) -> String {
+    let ordered = node.tag_name().name().eq_ignore_ascii_case("ol");
+    let todo = style(node).contains("--en-todo:true");
+    let mut number = node
+        .attribute("start")
+        .and_then(|start| start.parse::<usize>().ok())
+        .unwrap_or(1);
+
+    let mut items = Vec::new();
+    for item in node.children().filter(|child| is_element(*child, "li")) {
+        let content = blocks(item);
+        let (marker, content) = if ordered {
+            number += 1;
+            (format!("{}. ", number - 1), content.as_str())
+        } else if todo {
+            let checked = style(item).contains("--en-checked:true");
+            let marker = if checked { "- [x] " } else { "- [ ] " };
+            (marker.to_string(), content.as_str())
+        } else if content.starts_with("- [ ] ") || content.starts_with("- [x] ") {
+            // Already a task item, from an `<en-todo>` at the start of the item.
+            (String::from("- "), &content[2..])
+        } else {
+            (String::from("- "), content.as_str())
+        };
+
+        let indent = " ".repeat(marker.len());
+        let lines = content
+            .lines()
+            .enumerate()
+            .map(|(index, line)| match (index, line.is_empty()) {
+                (0, _) => format!("{marker}{line}"),
+                (_, true) => String::new(),
+                (_, false) => format!("{indent}{line}"),
+            })
+            .collect::<Vec<_>>();
+        items.push(if lines.is_empty() {
+            marker.trim_end().to_string()
+        } else {
+            lines.join("\n")
+        });
+    }
+
+    items.join("\n")
+}
+
+fn table(node: Node) -> String {
+    let rows = node
+        .descendants()
+        .filter(|node| is_element(*node, "tr"))
+        .map(|row| {
+            row.children()
+                .filter(|cell| is_element(*cell, "td") || is_element(*cell, "th"))
+                .map(|cell| inline_text(cell).replace('|', "\\|"))
+                .collect::<Vec<_>>()
+        })
+        .filter(|cells| !cells.is_empty())
+        .collect::<Vec<_>>();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let mut cells = row.clone();
+        cells.resize(columns, String::new());
+        lines.push(format!("| {} |", cells.join(" | ")));
+        if index == 0 {
+            lines.push(format!("|{}", " --- |".repeat(columns)));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Appends the markdown for an inline `node` to `out`. Line breaks are kept as `\n`
+/// until the paragraph is finished.
+fn inline(node: Node, out: &mut String) {
+    if node.is_text() {
+        let text = node.text().unwrap_or_default();
+        if text.starts_with(char::is_whitespace) && !ends_in_space(out) {
+            out.push(' ');
+        }
+        for (index, word) in text.split_whitespace().enumerate() {
+            if index > 0 {
+                out.push(' ');
+            }
+            out.push_str(&escape(word));
+        }
+        if text.ends_with(char::is_whitespace) && !text.trim().is_empty() {
+            out.push(' ');
+        }
+        return;
+    }
+    if !node.is_element() {
+        return;
+    }
+
+    match node.tag_name().name().to_lowercase().as_str() {
+        "br" => out.push('\n'),
+        "b" | "strong" => wrap(node, "**", out),
+        "i" | "em" => wrap(node, "*", out),
+        "s" | "strike" | "del" => wrap(node, "~~", out),
+        "code" | "tt" | "kbd" => {
+            let code = node
+                .descendants()
+                .filter_map(|node| node.text().filter(|_| node.is_text()))
+                .collect::<String>();
+            if !code.trim().is_empty() {
+                out.push_str(&format!("`{}`", code.trim()));
+            }
+        }
+        "a" => {
+            let mut label = String::new();
+            children(node, &mut label);
+            let label = label.trim();
+            match node
+                .attribute("href")
+                .filter(|href| !href.trim().is_empty())
+            {
+                Some(href) if !label.is_empty() => {
+                    out.push_str(&format!("[{label}](<{}>)", href.trim()));
+                }
+                Some(href) => out.push_str(&format!("<{}>", href.trim())),
+                None => out.push_str(label),
+            }
+        }
+        "img" => {
+            if let Some(src) = node.attribute("src").filter(|src| src.contains("://")) {
+                let alt = node
+                    .attribute("alt")
+                    .unwrap_or_default()
+                    .replace(['[', ']'], "");
+                out.push_str(&format!("![{alt}](<{src}>)"));
+            }
+        }
+        "en-media" => {
+            if let Some(hash) = node.attribute("hash") {
+                out.push(MEDIA_MARK);
+                out.push_str(hash);
+                out.push(MEDIA_MARK);
+            }
+        }
+        "en-todo" => {
+            let checked = node
+                .attribute("checked")
+                .is_some_and(|checked| checked == "true");
+            out.push_str(if checked { "[x] " } else { "[ ] " });
+        }
+        "en-crypt" => out.push_str("(encrypted text)"),
+        "script" | "style" | "title" => {}
+        _ => children(node, out),
+    }
+}
+
+fn children(node: Node, out: &mut String) {
+    for child in node.children() {
+        if is_block(child) {
+            out.push('\n');
+            out.push_str(&blocks(child));
+            out.push('\n');
+        } else {
+            inline(child, out);
+        }
+    }
+}
+
+fn wrap(node: Node, marker: &str, out: &mut String) {
+    let mut inner = String::new();
+    children(node, &mut inner);
+    let trimmed = inner.trim();
+    if trimmed.is_empty() || trimmed.contains('\n') {
+        out.push_str(&inner);
+        return;
+    }
+
+    if inner.starts_with(' ') && !ends_in_space(out) {
+        out.push(' ');
+    }
+    out.push_str(&format!("{marker}{trimmed}{marker}"));
+    if inner.ends_with(' ') {
+        out.push(' ');
+    }
+}
+
+/// The inline content of `node` on a single line, e.g. for headings and table cells.
+fn inline_text(node: Node) -> String {
+    let mut text = String::new();
+    children(node, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Ends the paragraph collected so far, turning its line breaks into hard breaks.
+fn flush(paragraph: &mut String, out: &mut Vec<String>) {
+    let text = std::mem::take(paragraph);
+    let lines = text.lines().map(str::trim).collect::<Vec<_>>();
+    let start = lines.iter().position(|line| !line.is_empty());
+    let end = lines.iter().rposition(|line| !line.is_empty());
+    let (Some(start), Some(end)) = (start, end) else {
+        return;
+    };
+
+    let text = lines[start..=end].join("  \n");
+    if text.starts_with("[ ] ") || text.starts_with("[x] ") {
+        out.push(format!("- {text}"));
+    } else {
+        out.push(text);
+    }
+}
+
+fn is_block(node: Node) -> bool {
+    node.is_element() && BLOCKS.contains(&node.tag_name().name().to_lowercase().as_str())
+}
+
+fn is_element(node: Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().name().eq_ignore_ascii_case(name)
+}
+
+fn style(node: Node) -> String {
+    node.attribute("style").unwrap_or_default().replace(' ', "")
+}
+
+fn ends_in_space(text: &str) -> bool {
+    text.is_empty() || text.ends_with([' ', '\n'])
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '*' | '_' | '`' | '[' | ']' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+/// Rewrites entities XML does not define into ones it does, and escapes stray `&`s.
+fn replace_entities(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp + 1..];
+
+        let name = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[..end])
+            .filter(|name| {
+                !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|ch| ch.is_ascii_alphanumeric() || ch == '#')
+            });
+        match name {
+            Some(name)
+                if name.starts_with('#')
+                    || matches!(name, "amp" | "lt" | "gt" | "quot" | "apos") =>
+            {
+                out.push('&');
+            }
+            Some(name) => {
+                match ENTITIES.iter().find(|(entity, _)| *entity == name) {
+                    Some((_, number)) => out.push_str(&format!("&{number};")),
+                    None => out.push_str(&format!("&amp;{name};")),
+                }
+                rest = &rest[name.len() + 1..];
+            }
+            None => out.push_str("&amp;"),
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,409 @@
+use iced::widget::{button, column, container, horizontal_space, row, scrollable, text};
+use iced::{Alignment, Element, Length};
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use super::{enex, keep, markdown};
+use crate::notes::attachments::{self, Attachment};
+use crate::notes::colors::NoteColor;
+use crate::notes::links;
+use crate::notes::notes::{Note, Notes};
+use crate::platform::platform::{self, FileFilter};
+use crate::storage::storage::StorageError;
+
+/// The places notes can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    MarkdownFolder,
+    MarkdownZip,
+    Evernote,
+    GoogleKeep,
+}
+
+impl ImportSource {
+    pub const ALL: [ImportSource; 4] = [
+        ImportSource::MarkdownFolder,
+        ImportSource::MarkdownZip,
+        ImportSource::Evernote,
+        ImportSource::GoogleKeep,
+    ];
+}
+
+impl fmt::Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImportSource::MarkdownFolder => "Markdown folder",
+            ImportSource::MarkdownZip => "Markdown zip archive",
+            ImportSource::Evernote => "Evernote (.enex)",
+            ImportSource::GoogleKeep => "Google Keep Takeout",
+        })
+    }
+}
+
+/// Asks where to import from and works out what the import would do. Returns `None`
+/// when the dialog is cancelled.
+pub fn pick_and_scan(source: ImportSource) -> Result<Option<Plan>, String> {
+    let dialog_error = |error: std::io::Error| format!("Could not show the file dialog: {error}");
+
+    match source {
+        ImportSource::MarkdownFolder => platform::pick_folder("Import Markdown folder")
+            .map_err(dialog_error)?
+            .map(|dir| markdown::scan(&dir))
+            .transpose(),
+        ImportSource::MarkdownZip => {
+            let filter = FileFilter {
+                name: "Zip archives",
+                extensions: &["zip"],
+            };
+            platform::pick_files("Import Markdown zip archive", Some(filter))
+                .map_err(dialog_error)?
+                .first()
+                .map(|archive| markdown::scan(archive))
+                .transpose()
+        }
+        ImportSource::Evernote => {
+            let filter = FileFilter {
+                name: "Evernote exports",
+                extensions: &["enex"],
+            };
+            let files = platform::pick_files("Import Evernote notes", Some(filter))
+                .map_err(dialog_error)?;
+            if files.is_empty() {
+                Ok(None)
+            } else {
+                enex::scan(&files).map(Some)
+            }
+        }
+        ImportSource::GoogleKeep => platform::pick_folder("Import Google Keep Takeout folder")
+            .map_err(dialog_error)?
+            .map(|dir| keep::scan(&dir))
+            .transpose(),
+    }
+}
+
+/// A page an importer found, not added to any notebook yet.
+#[derive(Debug, Clone, Default)]
+pub struct Draft {
+    /// The file it was read from, relative to the imported directory.
+    pub source: String,
+    pub title: String,
+    pub body: String,
+    pub tags: Vec<String>,
+    pub color: NoteColor,
+    pub pinned: bool,
+    pub archived: bool,
+    /// Seconds since the Unix epoch.
+    pub created: u64,
+    pub updated: u64,
+    /// Names of the folders it goes into, outermost first.
+    pub folder: Vec<String>,
+    /// Images in the body to copy into the attachments directory.
+    pub embeds: Vec<Embed>,
+    /// Files to attach, each with the name it is attached under.
+    pub attachments: Vec<(String, Source)>,
+}
+
+/// Where the contents of an imported image or attachment come from.
+#[derive(Debug, Clone)]
+pub enum Source {
+    Path(PathBuf),
+    /// Contents carried inside the imported file, with the file name they had.
+    Bytes {
+        name: String,
+        bytes: Vec<u8>,
+    },
+}
+
+impl Source {
+    fn store(&self) -> Result<Attachment, StorageError> {
+        match self {
+            Source::Path(path) => attachments::store(path),
+            Source::Bytes { name, bytes } => attachments::store_bytes(bytes, name),
+        }
+    }
+}
+
+/// An image reference in a draft's body, replaced by one to the stored copy on import.
+#[derive(Debug, Clone)]
+pub struct Embed {
+    /// Where the reference is written in the body.
+    pub range: Range<usize>,
+    pub alt: String,
+    pub source: Source,
+}
+
+/// What an import would do, worked out without changing anything.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    /// Where the pages come from, as shown to the user.
+    pub source: String,
+    pub drafts: Vec<Draft>,
+    /// Things that will not come across, e.g. unreadable files or missing images.
+    pub warnings: Vec<String>,
+}
+
+impl Plan {
+    fn image_count(&self) -> usize {
+        self.drafts.iter().map(|draft| draft.embeds.len()).sum()
+    }
+
+    fn attachment_count(&self) -> usize {
+        self.drafts
+            .iter()
+            .map(|draft| draft.attachments.len())
+            .sum()
+    }
+}
+
+/// What [`apply`] did.
+#[derive(Debug, Default)]
+pub struct Applied {
+    /// Pages added to the notebook.
+    pub pages: usize,
+    /// How many of those pages are missing files that could not be copied.
+    pub incomplete: usize,
+    pub errors: Vec<StorageError>,
+}
+
+impl Applied {
+    /// A sentence saying how many pages came across, with where from or to in `place`,
+    /// e.g. `from Recipes.enex`.
+    pub fn summary(&self, place: &str) -> String {
+        let pages = match self.pages {
+            1 => String::from("1 page"),
+            pages => format!("{pages} pages"),
+        };
+        match self.incomplete {
+            0 => format!("Imported {pages} {place}"),
+            incomplete => format!(
+                "Imported {pages} {place}, {incomplete} of them without files that could \
+                 not be copied"
+            ),
+        }
+    }
+}
+
+/// Adds the pages of `plan` to `notes`, copying their images and attachments along.
+/// Files that cannot be copied are reported and left referenced as they were.
+pub fn apply(plan: &Plan, notes: &mut Notes) -> Applied {
+    let mut applied = Applied::default();
+    let mut imported = Vec::new();
+
+    for draft in &plan.drafts {
+        let mut errors = Vec::new();
+        let mut body = draft.body.clone();
+        let mut embeds = draft.embeds.iter().collect::<Vec<_>>();
+        embeds.sort_by_key(|embed| std::cmp::Reverse(embed.range.start));
+        for embed in embeds {
+            match embed.source.store() {
+                Ok(stored) => body.replace_range(
+                    embed.range.clone(),
+                    &attachments::image_markdown(&stored.file, &embed.alt),
+                ),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        let mut stored: Vec<Attachment> = Vec::new();
+        for (name, source) in &draft.attachments {
+            match source.store() {
+                Ok(attachment) if !stored.iter().any(|other| other.hash == attachment.hash) => {
+                    stored.push(Attachment {
+                        name: name.clone(),
+                        ..attachment
+                    });
+                }
+                Ok(_) => {}
+                Err(error) => errors.push(error),
+            }
+        }
+
+        let mut note = Note::new(draft.title.clone(), body, draft.tags.clone());
+        note.color = draft.color.clone();
+        note.pinned = draft.pinned;
+        note.archived = draft.archived;
+        note.attachments = stored;
+        note.created = draft.created;
+        note.updated = draft.updated;
+        note.folder = notes.ensure_folder(&draft.folder);
+        imported.push(note);
+
+        if !errors.is_empty() {
+            applied.incomplete += 1;
+            applied.errors.append(&mut errors);
+        }
+    }
+
+    applied.pages = imported.len();
+    notes.insert_all(imported);
+    applied
+}
+
+/// Screen that shows what an import would add, before anything is changed.
+#[derive(Debug, Default)]
+pub struct ImportPreview {
+    plan: Plan,
+    /// Normalized titles of the pages already in the notebook.
+    existing: HashSet<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Confirm,
+    Cancel,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Confirm,
+    Cancel,
+}
+
+impl ImportPreview {
+    pub fn open(&mut self, plan: Plan, notes: &Notes) {
+        self.existing = notes
+            .iter()
+            .map(|note| links::normalize(&note.title))
+            .collect();
+        self.plan = plan;
+    }
+
+    /// Hands over the plan to carry out, leaving the preview empty.
+    pub fn take_plan(&mut self) -> Plan {
+        self.existing.clear();
+        std::mem::take(&mut self.plan)
+    }
+
+    pub fn update(&mut self, message: Message) -> Option<Event> {
+        match message {
+            Message::Confirm => Some(Event::Confirm),
+            Message::Cancel => {
+                self.take_plan();
+                Some(Event::Cancel)
+            }
+        }
+    }
+
+    pub fn view<'a>(&'a self, notebook: &str) -> Element<'a, Message> {
+        let plan = &self.plan;
+        let count = plan.drafts.len();
+        let mut confirm = button(text(match count {
+            1 => String::from("Import 1 page"),
+            count => format!("Import {count} pages"),
+        }));
+        if count > 0 {
+            confirm = confirm.on_press(Message::Confirm);
+        }
+
+        let header = row![
+            text("Import").size(28),
+            horizontal_space(),
+            button(text("Cancel")).on_press(Message::Cancel),
+            confirm,
+        ]
+        .spacing(12)
+        .align_y(Alignment::Center);
+
+        let summary = text(format!(
+            "{count} pages from {} into {notebook}, with {} images and {} attached files. \
+             Nothing has been changed yet.",
+            plan.source,
+            plan.image_count(),
+            plan.attachment_count(),
+        ))
+        .size(14);
+
+        let mut list = column![].spacing(6);
+        for draft in &plan.drafts {
+            let mut details = vec![draft.source.clone()];
+            if !draft.folder.is_empty() {
+                details.push(format!("in {}", draft.folder.join(" / ")));
+            }
+            if !draft.tags.is_empty() {
+                let tags = draft
+                    .tags
+                    .iter()
+                    .map(|tag| format!("#{tag}"))
+                    .collect::<Vec<_>>();
+                details.push(tags.join(" "));
+            }
+            if !draft.embeds.is_empty() {
+                details.push(format!("{} images", draft.embeds.len()));
+            }
+            if !draft.attachments.is_empty() {
+                details.push(format!("{} attached files", draft.attachments.len()));
+            }
+            if self.existing.contains(&links::normalize(&draft.title)) {
+                details.push(String::from("a page with this title already exists"));
+            }
+
+            list = list.push(
+                column![
+                    text(draft.title.as_str()).size(16),
+                    text(details.join(" · ")).size(12),
+                ]
+                .spacing(2),
+            );
+        }
+
+        if !plan.warnings.is_empty() {
+            list = list.push(text("Left out").size(20));
+            for warning in &plan.warnings {
+                list = list.push(text(warning.as_str()).size(12));
+            }
+        }
+
+        container(column![header, summary, scrollable(list).height(Length::Fill)].spacing(12))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding([24, 32])
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::storage;
+
+    #[test]
+    fn pages_whose_files_cannot_be_copied_still_come_across() {
+        let _data = storage::use_test_data_dir();
+        let mut notes = Notes::open("apply-tests");
+        let body = String::from("Before ![chart](chart.png) after");
+        let plan = Plan {
+            drafts: vec![
+                Draft {
+                    title: "Report".into(),
+                    embeds: vec![Embed {
+                        range: 7..26,
+                        alt: "chart".into(),
+                        source: Source::Path(PathBuf::from("no-such-dir/chart.png")),
+                    }],
+                    body,
+                    ..Draft::default()
+                },
+                Draft {
+                    title: "Plain".into(),
+                    ..Draft::default()
+                },
+            ],
+            ..Plan::default()
+        };
+
+        let applied = apply(&plan, &mut notes);
+        assert_eq!(
+            (applied.pages, applied.incomplete, applied.errors.len()),
+            (2, 1, 1)
+        );
+        assert_eq!(
+            applied.summary("from test"),
+            "Imported 2 pages from test, 1 of them without files that could not be copied"
+        );
+        // The reference is left as it was.
+        let report = notes.iter().find(|note| note.title == "Report").unwrap();
+        assert_eq!(report.body, "Before ![chart](chart.png) after");
+    }
+}
@@ -0,0 +1,272 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::import::{Draft, Embed, Plan, Source};
+use crate::notes::attachments;
+use crate::notes::colors::NoteColor;
+use crate::notes::notes;
+
+/// A note as Google Takeout writes it, one JSON file per note.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepNote {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    text_content: String,
+    #[serde(default)]
+    list_content: Vec<ListItem>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    #[serde(default)]
+    attachments: Vec<KeepAttachment>,
+    #[serde(default)]
+    color: String,
+    #[serde(default)]
+    is_pinned: bool,
+    #[serde(default)]
+    is_archived: bool,
+    #[serde(default)]
+    is_trashed: bool,
+    #[serde(default)]
+    created_timestamp_usec: u64,
+    #[serde(default)]
+    user_edited_timestamp_usec: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListItem {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    is_checked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Label {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepAttachment {
+    file_path: String,
+    #[serde(default)]
+    mimetype: String,
+}
+
+/// Works out how the notes in a Google Keep Takeout folder would be imported. `dir`
+/// may be the `Keep` folder itself or the `Takeout` folder around it.
+pub fn scan(dir: &Path) -> Result<Plan, String> {
+    let keep = [
+        dir.to_path_buf(),
+        dir.join("Keep"),
+        dir.join("Takeout").join("Keep"),
+    ]
+    .into_iter()
+    .find(|dir| has_notes(dir))
+    .ok_or_else(|| format!("No Google Keep notes found in {}", dir.display()))?;
+
+    let mut files = fs::read_dir(&keep)
+        .map_err(|error| format!("Could not read {}: {error}", keep.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_json(path))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut plan = Plan {
+        source: keep.display().to_string(),
+        ..Plan::default()
+    };
+    for file in files {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let note = fs::read_to_string(&file)
+            .map_err(|error| error.to_string())
+            .and_then(|text| {
+                serde_json::from_str::<KeepNote>(&text).map_err(|error| error.to_string())
+            });
+
+        match note {
+            Ok(note) if note.is_trashed => plan.warnings.push(format!("{name}: in the trash")),
+            Ok(note) => {
+                let draft = draft(note, &keep, &name, &mut plan.warnings);
+                plan.drafts.push(draft);
+            }
+            Err(error) => plan
+                .warnings
+                .push(format!("{name}: not a Keep note ({error})")),
+        }
+    }
+
+    Ok(plan)
+}
+
+fn draft(note: KeepNote, dir: &Path, name: &str, warnings: &mut Vec<String>) -> Draft {
+    let mut body = note.text_content.trim_end().to_string();
+    if !note.list_content.is_empty() {
+        if !body.is_empty() {
+            body.push_str("\n\n");
+        }
+        let items = note
+            .list_content
+            .iter()
+            .map(|item| {
+                let check = if item.is_checked { 'x' } else { ' ' };
+                format!("- [{check}] {}", item.text.trim())
+            })
+            .collect::<Vec<_>>();
+        body.push_str(&items.join("\n"));
+    }
+
+    let mut embeds = Vec::new();
+    let mut files = Vec::new();
+    for attachment in &note.attachments {
+        let Some(path) = locate(dir, &attachment.file_path) else {
+            warnings.push(format!(
+                "{name}: attached file {} not found",
+                attachment.file_path
+            ));
+            continue;
+        };
+
+        if attachment.mimetype.starts_with("image/") || attachments::is_image(&path) {
+            if !body.is_empty() {
+                body.push_str("\n\n");
+            }
+            let start = body.len();
+            body.push_str(&attachments::image_markdown(&attachment.file_path, ""));
+            embeds.push(Embed {
+                range: start..body.len(),
+                alt: String::new(),
+                source: Source::Path(path),
+            });
+        } else {
+            files.push((attachment.file_path.clone(), Source::Path(path)));
+        }
+    }
+
+    let micros = |usec: u64| (usec > 0).then_some(usec / 1_000_000);
+    let updated = micros(note.user_edited_timestamp_usec).unwrap_or_else(notes::now);
+
+    Draft {
+        source: name.to_string(),
+        title: note.title.trim().to_string(),
+        body,
+        tags: note.labels.into_iter().map(|label| label.name).collect(),
+        color: color(&note.color),
+        pinned: note.is_pinned,
+        archived: note.is_archived,
+        created: micros(note.created_timestamp_usec).unwrap_or(updated),
+        updated,
+        folder: Vec::new(),
+        embeds,
+        attachments: files,
+    }
+}
+
+/// The closest built-in color to one of Keep's.
+fn color(keep: &str) -> NoteColor {
+    let name = match keep.to_uppercase().as_str() {
+        "RED" | "PINK" => "Cherry",
+        "ORANGE" | "YELLOW" | "BROWN" => "Amber",
+        "GREEN" | "TEAL" => "Emerald",
+        "BLUE" | "CERULEAN" | "DARK_BLUE" => "Ocean",
+        "PURPLE" => "Violet",
+        _ => return NoteColor::default(),
+    };
+    NoteColor::named(name)
+}
+
+/// The attached file Takeout wrote next to the note. Its extension sometimes differs
+/// from the one the note names, e.g. `.jpg` for `.jpeg`.
+fn locate(dir: &Path, file: &str) -> Option<PathBuf> {
+    let path = dir.join(Path::new(file).file_name()?);
+    if path.is_file() {
+        return Some(path);
+    }
+
+    let stem = path.file_stem()?.to_string_lossy().into_owned();
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|candidate| {
+            candidate
+                .file_stem()
+                .is_some_and(|other| other.to_string_lossy() == stem)
+                && !is_json(candidate)
+        })
+}
+
+fn has_notes(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries
+            .filter_map(|entry| entry.ok())
+            .any(|entry| is_json(&entry.path()))
+    })
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/import")
+    }
+
+    #[test]
+    fn notes_come_across_with_labels_colors_and_state() {
+        // The folder around `Takeout` works as well as `Keep` itself.
+        let plan = scan(&fixture()).unwrap();
+        assert!(plan.source.ends_with("Keep"));
+
+        let titles = plan
+            .drafts
+            .iter()
+            .map(|draft| draft.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["Groceries", "Old idea"]);
+        assert_eq!(plan.warnings, ["Gone.json: in the trash"]);
+
+        let groceries = &plan.drafts[0];
+        assert_eq!(groceries.source, "Groceries.json");
+        assert_eq!(groceries.tags, ["home", "errands"]);
+        assert_eq!(groceries.color, NoteColor::named("Emerald"));
+        assert!(groceries.pinned && !groceries.archived);
+        assert_eq!(groceries.created, 1_714_555_800);
+        assert_eq!(groceries.updated, 1_714_642_200);
+        assert!(groceries.body.starts_with("- [x] Apples\n- [ ] Bread\n\n"));
+
+        // Takeout wrote the image as `.jpg` though the note names `.jpeg`.
+        let [embed] = groceries.embeds.as_slice() else {
+            panic!("expected one embed, got {:?}", groceries.embeds);
+        };
+        assert_eq!(&groceries.body[embed.range.clone()], "![](groceries.jpeg)");
+        let Source::Path(path) = &embed.source else {
+            panic!("expected a path");
+        };
+        assert_eq!(path.file_name().unwrap(), "groceries.jpg");
+        assert!(groceries.attachments.is_empty());
+
+        let old = &plan.drafts[1];
+        assert_eq!(old.body, "Paint the shed.");
+        assert_eq!(old.color, NoteColor::named("Violet"));
+        assert!(old.archived && !old.pinned);
+        assert!(old.tags.is_empty());
+    }
+
+    #[test]
+    fn folders_without_notes_are_refused() {
+        let empty = fixture().join("Takeout");
+        assert!(scan(&empty.join("Nothing here")).is_err());
+    }
+}
@@ -0,0 +1,448 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::import::{Draft, Embed, Plan, Source};
+use crate::export::front_matter::{self, Value};
+use crate::export::zip;
+use crate::notes::attachments;
+use crate::notes::colors::NoteColor;
+use crate::notes::links;
+use crate::notes::notes;
+use crate::storage::storage;
+
+const EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+/// Works out how the `.md` files in `path` and its subdirectories would be imported,
+/// e.g. from an Obsidian vault or a Markdown export. `path` may also be a `.zip` of
+/// them, like the app's own zipped export. Subdirectories become folders.
+pub fn scan(path: &Path) -> Result<Plan, String> {
+    if is_zip(path) {
+        scan_zip(path)
+    } else {
+        scan_dir(path)
+    }
+}
+
+fn scan_dir(dir: &Path) -> Result<Plan, String> {
+    let mut files = Vec::new();
+    walk(dir, &mut files).map_err(|error| format!("Could not read {}: {error}", dir.display()))?;
+    files.sort();
+
+    let mut by_name: HashMap<String, PathBuf> = HashMap::new();
+    for file in &files {
+        if let Some(name) = file.file_name() {
+            by_name
+                .entry(name.to_string_lossy().to_lowercase())
+                .or_insert_with(|| file.clone());
+        }
+    }
+
+    let mut plan = Plan {
+        source: dir.display().to_string(),
+        ..Plan::default()
+    };
+    for file in files.iter().filter(|file| is_markdown(file)) {
+        let relative = file.strip_prefix(dir).unwrap_or(file);
+        match fs::read_to_string(file) {
+            Ok(text) => {
+                let draft = draft(&text, file, relative, dir, &by_name, &mut plan.warnings);
+                plan.drafts.push(draft);
+            }
+            Err(error) => plan.warnings.push(format!(
+                "{}: could not be read ({error})",
+                relative.display()
+            )),
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Unpacks the archive at `path` into a directory of its own and scans that. The
+/// images and files the pages use are read into the plan, so the directory can be
+/// removed straight away.
+fn scan_zip(path: &Path) -> Result<Plan, String> {
+    let read_error = |error: std::io::Error| format!("Could not read {}: {error}", path.display());
+    let entries = zip::read(path).map_err(read_error)?;
+
+    let dir = storage::unique_temp_path("import");
+    let unpacked = unpack(&entries, &dir)
+        .map_err(|error| format!("Could not unpack {}: {error}", path.display()))
+        .and_then(|()| scan_dir(&dir))
+        .and_then(|mut plan| {
+            for draft in &mut plan.drafts {
+                let sources = draft
+                    .embeds
+                    .iter_mut()
+                    .map(|embed| &mut embed.source)
+                    .chain(draft.attachments.iter_mut().map(|(_, source)| source));
+                for source in sources {
+                    load(source).map_err(read_error)?;
+                }
+            }
+            Ok(plan)
+        });
+    let _ = fs::remove_dir_all(&dir);
+
+    let mut plan = unpacked?;
+    plan.source = path.display().to_string();
+    Ok(plan)
+}
+
+/// Writes `entries` below `dir`, which must not exist yet. Entries whose path would
+/// lead out of `dir` are left out.
+fn unpack(entries: &[(String, Vec<u8>)], dir: &Path) -> std::io::Result<()> {
+    fs::create_dir(dir)?;
+    for (name, contents) in entries {
+        let relative = Path::new(name);
+        if !relative
+            .components()
+            .all(|part| matches!(part, Component::Normal(_)))
+        {
+            continue;
+        }
+        let target = dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, contents)?;
+    }
+    Ok(())
+}
+
+/// Replaces a file `source` points to by its contents.
+fn load(source: &mut Source) -> std::io::Result<()> {
+    if let Source::Path(path) = source {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let bytes = fs::read(&path)?;
+        *source = Source::Bytes { name, bytes };
+    }
+    Ok(())
+}
+
+fn draft(
+    text: &str,
+    file: &Path,
+    relative: &Path,
+    root: &Path,
+    by_name: &HashMap<String, PathBuf>,
+    warnings: &mut Vec<String>,
+) -> Draft {
+    let (front, body) = front_matter::split(text);
+    let body = body.strip_prefix('\n').unwrap_or(body);
+    let body = body.strip_suffix('\n').unwrap_or(body).to_string();
+    let source = relative.display().to_string();
+    let dir = file.parent().unwrap_or(root);
+    let field = |key: &str| front_matter::lookup(&front, key);
+    let flag = |key: &str| {
+        field(key)
+            .and_then(Value::as_text)
+            .is_some_and(|value| matches!(value.to_lowercase().as_str(), "true" | "yes"))
+    };
+
+    let title = field("title")
+        .and_then(Value::as_text)
+        .map(str::to_string)
+        .or_else(|| first_heading(&body))
+        .unwrap_or_else(|| {
+            file.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+
+    let mut tags: Vec<String> = Vec::new();
+    let listed = field("tags").or_else(|| field("tag"));
+    let listed = listed.map(|value| match value {
+        Value::Text(text) => text
+            .split(|ch: char| ch == ',' || ch.is_whitespace())
+            .map(str::to_string)
+            .collect(),
+        value => value
+            .texts()
+            .into_iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>(),
+    });
+    for tag in listed.into_iter().flatten().chain(inline_tags(&body)) {
+        let tag = tag.trim().trim_start_matches('#').trim().to_string();
+        if !tag.is_empty() && !tags.iter().any(|other| other.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+
+    let modified = fs::metadata(file).ok();
+    let file_time = |time: Option<std::time::SystemTime>| {
+        time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|elapsed| elapsed.as_secs())
+    };
+    let timestamp = |keys: &[&str]| {
+        keys.iter()
+            .filter_map(|key| field(key).and_then(Value::as_text))
+            .find_map(front_matter::parse_timestamp)
+    };
+    let updated = timestamp(&["updated", "modified"])
+        .or_else(|| file_time(modified.as_ref().and_then(|meta| meta.modified().ok())))
+        .unwrap_or_else(notes::now);
+    let created = timestamp(&["created", "date"])
+        .or_else(|| file_time(modified.as_ref().and_then(|meta| meta.created().ok())))
+        .unwrap_or(updated);
+
+    let mut attachments = Vec::new();
+    if let Some(Value::List(items)) = field("attachments") {
+        for item in items {
+            let Some(target) = item.get("file").and_then(Value::as_text) else {
+                continue;
+            };
+            let name = item.get("name").and_then(Value::as_text).unwrap_or(target);
+            match resolve(target, dir, root, by_name) {
+                Some(path) => attachments.push((name.to_string(), Source::Path(path))),
+                None => warnings.push(format!("{source}: attached file {target} not found")),
+            }
+        }
+    }
+
+    let embeds = embeds(&body, dir, root, by_name, |target| {
+        warnings.push(format!("{source}: image {target} not found"));
+    });
+
+    let folder = relative
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+
+    Draft {
+        source,
+        title,
+        body,
+        tags,
+        color: field("color")
+            .and_then(Value::as_text)
+            .map(NoteColor::named)
+            .unwrap_or_default(),
+        pinned: flag("pinned"),
+        archived: flag("archived"),
+        created,
+        updated,
+        folder,
+        embeds,
+        attachments,
+    }
+}
+
+/// Every file below `dir`, leaving out hidden ones such as `.obsidian` and `.trash`.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            walk(&entry.path(), files)?;
+        } else if kind.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+fn is_zip(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+fn first_heading(body: &str) -> Option<String> {
+    let mut heading: Option<String> = None;
+    for event in Parser::new(body) {
+        match event {
+            Event::Start(Tag::Heading { .. }) => heading = Some(String::new()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = heading.as_mut() {
+                    heading.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let text = heading.take().unwrap_or_default();
+                if !text.trim().is_empty() {
+                    return Some(text.trim().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `#tags` written in the text, outside code.
+fn inline_tags(body: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut in_code = false;
+    for event in Parser::new(body) {
+        let text = match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code = true;
+                continue;
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code = false;
+                continue;
+            }
+            Event::Text(text) if !in_code => text,
+            _ => continue,
+        };
+        let mut previous = ' ';
+        for (index, ch) in text.char_indices() {
+            if ch == '#' && (previous.is_whitespace() || previous == '(') {
+                let rest = &text[index + 1..];
+                let end = rest
+                    .find(|ch: char| !(ch.is_alphanumeric() || matches!(ch, '-' | '_' | '/')))
+                    .unwrap_or(rest.len());
+                let tag = rest[..end].trim_end_matches('/');
+                if tag.chars().any(|ch| !ch.is_ascii_digit()) {
+                    tags.push(tag.to_string());
+                }
+            }
+            previous = ch;
+        }
+    }
+    tags
+}
+
+/// Local images in `body`: `![alt](path)` and Obsidian's `![[file.png]]`. Calls
+/// `missing` with each local image that cannot be found.
+fn embeds(
+    body: &str,
+    dir: &Path,
+    root: &Path,
+    by_name: &HashMap<String, PathBuf>,
+    mut missing: impl FnMut(&str),
+) -> Vec<Embed> {
+    let mut embeds = Vec::new();
+    let mut code: Vec<Range<usize>> = Vec::new();
+    let mut image: Option<(Range<usize>, String, String)> = None;
+
+    for (event, range) in Parser::new(body).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => code.push(range),
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                image = Some((range, dest_url.to_string(), String::new()));
+            }
+            Event::Text(text) => {
+                if let Some((_, _, alt)) = image.as_mut() {
+                    alt.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Image) => {
+                let Some((range, target, alt)) = image.take() else {
+                    continue;
+                };
+                if is_remote(&target) {
+                    continue;
+                }
+                match resolve(&target, dir, root, by_name) {
+                    Some(path) => embeds.push(Embed {
+                        range,
+                        alt,
+                        source: Source::Path(path),
+                    }),
+                    None => missing(&target),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut start = 0;
+    while let Some(found) = body[start..].find("![[") {
+        let open = start + found;
+        let Some(length) = body[open..].find("]]") else {
+            break;
+        };
+        let range = open..open + length + 2;
+        start = range.end;
+        if code.iter().any(|code| code.contains(&open)) {
+            continue;
+        }
+
+        let inner = &body[open + 3..range.end - 2];
+        let (target, alias) = inner.split_once('|').unwrap_or((inner, ""));
+        let target = target.split('#').next().unwrap_or_default().trim();
+        if !attachments::is_image(Path::new(target)) {
+            continue;
+        }
+        let alt = if alias.is_empty() || alias.chars().all(|ch| ch.is_ascii_digit() || ch == 'x') {
+            Path::new(target)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        } else {
+            alias.to_string()
+        };
+        match resolve(target, dir, root, by_name) {
+            Some(path) => embeds.push(Embed {
+                range,
+                alt,
+                source: Source::Path(path),
+            }),
+            None => missing(target),
+        }
+    }
+
+    embeds
+}
+
+fn is_remote(target: &str) -> bool {
+    target.contains("://") || target.starts_with("data:") || target.starts_with("mailto:")
+}
+
+/// The file `target` refers to: relative to the note, to the imported directory, or
+/// (as Obsidian resolves them) anywhere in it with that file name. Files outside the
+/// imported directory are not picked up.
+fn resolve(
+    target: &str,
+    dir: &Path,
+    root: &Path,
+    by_name: &HashMap<String, PathBuf>,
+) -> Option<PathBuf> {
+    let target = links::percent_decode(target.trim());
+    let relative = Path::new(&target);
+    if relative.is_absolute() {
+        return None;
+    }
+
+    let root = root.canonicalize().ok()?;
+    let inside = |path: &PathBuf| {
+        path.is_file()
+            && path
+                .canonicalize()
+                .is_ok_and(|path| path.starts_with(&root))
+    };
+    [dir.join(relative), root.join(relative)]
+        .into_iter()
+        .find(inside)
+        .or_else(|| {
+            let name = relative.file_name()?.to_string_lossy().to_lowercase();
+            by_name.get(&name).cloned()
+        })
+}
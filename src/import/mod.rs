@@ -0,0 +1,5 @@
+pub mod enex;
+pub mod html;
+pub mod import;
+pub mod keep;
+pub mod markdown;
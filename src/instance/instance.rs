@@ -0,0 +1,223 @@
+use iced::Subscription;
+use iced::futures::channel::mpsc::{self, UnboundedSender};
+use iced::futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::api::api;
+use crate::storage::storage;
+
+/// How long a second launch waits for the running app to take its request.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a second launch waits for the running app to say where it listens.
+const STARTUP_WAIT: Duration = Duration::from_secs(3);
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What a launch of the app asks for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Launch {
+    /// The main window.
+    Main,
+    /// A page, by id or title, in its own window.
+    Open(String),
+    /// The quick-capture window.
+    Capture,
+}
+
+impl Launch {
+    /// The request made by command line `args`, or `None` when they name a command
+    /// that runs without the app.
+    pub fn parse(args: &[String]) -> Option<Self> {
+        match args {
+            [] => Some(Launch::Main),
+            [command, page] if command == "open" => Some(Launch::Open(page.clone())),
+            [command] if command == "capture" => Some(Launch::Capture),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Another launch of the app handed over its request.
+    Launched(Launch),
+}
+
+/// Where the running app listens for later launches, and the token they must send.
+#[derive(Debug, Serialize, Deserialize)]
+struct Address {
+    port: u16,
+    token: String,
+}
+
+/// What one launch sends to the running app.
+#[derive(Debug, Serialize, Deserialize)]
+struct Forward {
+    token: String,
+    launch: Launch,
+}
+
+/// This process's claim to be the only running app for the data directory.
+#[derive(Debug)]
+pub struct Instance {
+    /// Held for as long as the app runs; the OS lets go of it if the app crashes.
+    _lock: File,
+    listener: Arc<TcpListener>,
+    token: String,
+}
+
+impl Instance {
+    /// Makes this process the running app, or if one is running already, hands it
+    /// `launch` and returns `Ok(None)`.
+    pub fn claim(launch: &Launch) -> Result<Option<Self>, String> {
+        let path = storage::data_dir().join("instance.lock");
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|error| format!("Could not open {}: {error}", path.display()))?;
+
+        match lock.try_lock() {
+            Ok(()) => Self::listen(lock).map(Some),
+            Err(TryLockError::WouldBlock) => forward(launch).map(|()| None),
+            Err(TryLockError::Error(error)) => {
+                Err(format!("Could not lock {}: {error}", path.display()))
+            }
+        }
+    }
+
+    fn listen(lock: File) -> Result<Self, String> {
+        let failed = |error: std::io::Error| format!("Could not listen for launches: {error}");
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(failed)?;
+        let port = listener.local_addr().map_err(failed)?.port();
+        let token = api::token()?;
+
+        let path = address_path();
+        let address = Address {
+            port,
+            token: token.clone(),
+        };
+        storage::save_private_json(&path, &address).map_err(|error| error.to_string())?;
+
+        Ok(Self {
+            _lock: lock,
+            listener: Arc::new(listener),
+            token,
+        })
+    }
+
+    /// Launches handed over by later starts of the app.
+    pub fn subscription(&self) -> Subscription<Message> {
+        let listener = Arc::clone(&self.listener);
+        let token = self.token.clone();
+
+        Subscription::run_with_id(
+            "instance",
+            iced::stream::channel(8, move |mut output| async move {
+                let (sender, mut launches) = mpsc::unbounded();
+                thread::spawn(move || accept(&listener, &token, &sender));
+
+                while let Some(launch) = launches.next().await {
+                    if output.send(Message::Launched(launch)).await.is_err() {
+                        break;
+                    }
+                }
+            }),
+        )
+    }
+}
+
+fn address_path() -> PathBuf {
+    storage::data_dir().join("instance.json")
+}
+
+fn accept(listener: &TcpListener, token: &str, launches: &UnboundedSender<Launch>) {
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        let _ = stream.set_read_timeout(Some(TIMEOUT));
+
+        let mut line = String::new();
+        let _ = BufReader::new(&stream).read_line(&mut line);
+        let reply = match serde_json::from_str::<Forward>(&line) {
+            Ok(forward) if forward.token == token => {
+                if launches.unbounded_send(forward.launch).is_err() {
+                    break;
+                }
+                "ok"
+            }
+            Ok(_) => "wrong token",
+            Err(_) => "malformed request",
+        };
+        let _ = writeln!(stream, "{reply}");
+    }
+}
+
+/// Hands `launch` to the app that holds the lock.
+fn forward(launch: &Launch) -> Result<(), String> {
+    let (address, mut stream) = connect()?;
+    let forward = Forward {
+        token: address.token,
+        launch: launch.clone(),
+    };
+    let not_responding =
+        |error: std::io::Error| format!("faitout is already running but does not respond: {error}");
+
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(not_responding)?;
+    let request = serde_json::to_string(&forward).map_err(|error| error.to_string())?;
+    writeln!(stream, "{request}").map_err(not_responding)?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut reply)
+        .map_err(not_responding)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        "" => Err(String::from(
+            "faitout is already running but closed the connection",
+        )),
+        error => Err(format!(
+            "faitout is already running but refused the request: {error}"
+        )),
+    }
+}
+
+/// Connects to the running app. Right after it starts, it may not have written its
+/// address yet, or the file may still hold the one from its last run.
+fn connect() -> Result<(Address, TcpStream), String> {
+    let mut waited = Duration::ZERO;
+    loop {
+        let attempt = storage::load_json::<Address>(&address_path())
+            .map_err(|error| error.to_string())
+            .and_then(|address| address.ok_or_else(|| String::from("no address yet")))
+            .and_then(|address| {
+                let socket = SocketAddr::from((Ipv4Addr::LOCALHOST, address.port));
+                TcpStream::connect_timeout(&socket, TIMEOUT)
+                    .map(|stream| (address, stream))
+                    .map_err(|error| error.to_string())
+            });
+
+        match attempt {
+            Ok(connected) => return Ok(connected),
+            Err(error) if waited >= STARTUP_WAIT => {
+                return Err(format!(
+                    "faitout is already running but cannot be reached: {error}"
+                ));
+            }
+            Err(_) => {
+                thread::sleep(RETRY_INTERVAL);
+                waited += RETRY_INTERVAL;
+            }
+        }
+    }
+}
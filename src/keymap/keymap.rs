@@ -0,0 +1,399 @@
+use iced::keyboard::key::Physical;
+use iced::keyboard::{Key, Modifiers};
+use iced::widget::{button, column, container, horizontal_space, row, scrollable, text};
+use iced::{Alignment, Element, Length, Theme};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Keys that can be bound besides letters, digits and punctuation, as written in
+/// settings.json.
+const NAMED_KEYS: [&str; 27] = [
+    "Escape",
+    "Delete",
+    "Backspace",
+    "Tab",
+    "Enter",
+    "Space",
+    "Insert",
+    "Home",
+    "End",
+    "PageUp",
+    "PageDown",
+    "ArrowUp",
+    "ArrowDown",
+    "ArrowLeft",
+    "ArrowRight",
+    "F1",
+    "F2",
+    "F3",
+    "F4",
+    "F5",
+    "F6",
+    "F7",
+    "F8",
+    "F9",
+    "F10",
+    "F11",
+    "F12",
+];
+
+/// Ctrl with these keys copies, cuts, pastes, selects and undoes in text fields.
+const TEXT_EDITING_KEYS: [&str; 6] = ["c", "x", "v", "a", "z", "y"];
+
+/// What a keyboard shortcut can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    NewNote,
+    Save,
+    FocusSearch,
+    Back,
+    TogglePreview,
+    OpenInWindow,
+    Delete,
+    NextTab,
+    PreviousTab,
+    ShowShortcuts,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::NewNote,
+        Action::Save,
+        Action::FocusSearch,
+        Action::Back,
+        Action::TogglePreview,
+        Action::OpenInWindow,
+        Action::Delete,
+        Action::NextTab,
+        Action::PreviousTab,
+        Action::ShowShortcuts,
+    ];
+
+    /// The action's key in the `shortcuts` table of settings.json.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::NewNote => "new_note",
+            Action::Save => "save",
+            Action::FocusSearch => "search",
+            Action::Back => "back",
+            Action::TogglePreview => "toggle_preview",
+            Action::OpenInWindow => "open_in_window",
+            Action::Delete => "delete",
+            Action::NextTab => "next_tab",
+            Action::PreviousTab => "previous_tab",
+            Action::ShowShortcuts => "shortcuts",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Action::NewNote => "New page",
+            Action::Save => "Save the page",
+            Action::FocusSearch => "Search the notebook",
+            Action::Back => "Go back, close a prompt or window",
+            Action::TogglePreview => "Switch between preview and editing",
+            Action::OpenInWindow => "Open the page in its own window",
+            Action::Delete => "Delete the selected page, after asking",
+            Action::NextTab => "Next tab",
+            Action::PreviousTab => "Previous tab",
+            Action::ShowShortcuts => "Show keyboard shortcuts",
+        }
+    }
+
+    fn default_shortcut(self) -> &'static str {
+        match self {
+            Action::NewNote => "Ctrl+N",
+            Action::Save => "Ctrl+S",
+            Action::FocusSearch => "Ctrl+F",
+            Action::Back => "Escape",
+            Action::TogglePreview => "Ctrl+E",
+            Action::OpenInWindow => "Ctrl+Shift+O",
+            Action::Delete => "Delete",
+            Action::NextTab => "Ctrl+Tab",
+            Action::PreviousTab => "Ctrl+Shift+Tab",
+            Action::ShowShortcuts => "F1",
+        }
+    }
+}
+
+/// A key together with the modifiers held down with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcut {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    logo: bool,
+    /// One of [`NAMED_KEYS`], or a single lowercase character.
+    key: String,
+}
+
+impl Shortcut {
+    /// Reads a shortcut written like `Ctrl+Shift+N`, `Alt+1` or `Escape`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut shortcut = Shortcut {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            logo: false,
+            key: String::new(),
+        };
+        let parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+        let (key, modifiers) = parts.split_last().unwrap_or((&"", &[]));
+
+        for modifier in modifiers {
+            let held = match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => &mut shortcut.ctrl,
+                "alt" | "option" => &mut shortcut.alt,
+                "shift" => &mut shortcut.shift,
+                "cmd" | "command" | "super" | "logo" | "win" => &mut shortcut.logo,
+                _ => return Err(format!("\"{text}\": unknown modifier \"{modifier}\"")),
+            };
+            *held = true;
+        }
+
+        shortcut.key = match key.to_lowercase().as_str() {
+            "esc" => String::from("Escape"),
+            "del" => String::from("Delete"),
+            "return" => String::from("Enter"),
+            lower => match NAMED_KEYS.iter().find(|name| name.to_lowercase() == lower) {
+                Some(name) => name.to_string(),
+                None if lower.chars().count() == 1 => lower.to_string(),
+                None => return Err(format!("\"{text}\": unknown key \"{key}\"")),
+            },
+        };
+        Ok(shortcut)
+    }
+
+    /// The shortcut for a key press, from the key as it reads without modifiers.
+    pub fn from_key(key: &Key, modifiers: Modifiers) -> Option<Self> {
+        let key = match key {
+            Key::Named(named) => format!("{named:?}"),
+            Key::Character(character) => character.to_lowercase(),
+            Key::Unidentified => return None,
+        };
+        Some(Shortcut {
+            ctrl: modifiers.control(),
+            alt: modifiers.alt(),
+            shift: modifiers.shift(),
+            logo: modifiers.logo(),
+            key,
+        })
+    }
+
+    /// The shortcut for a press of a digit key, whatever character the layout puts on
+    /// it: on AZERTY keyboards the digits need Shift, and without it the key reads `&`.
+    fn from_digit_key(physical: Physical, modifiers: Modifiers) -> Option<Self> {
+        let Physical::Code(code) = physical else {
+            return None;
+        };
+        let name = format!("{code:?}");
+        let digit = name
+            .strip_prefix("Digit")
+            .filter(|digit| digit.len() == 1)?;
+        Some(Shortcut {
+            ctrl: modifiers.control(),
+            alt: modifiers.alt(),
+            shift: modifiers.shift(),
+            logo: modifiers.logo(),
+            key: digit.to_string(),
+        })
+    }
+
+    /// Whether text fields already use the shortcut for editing.
+    fn edits_text(&self) -> bool {
+        self.ctrl && !self.alt && !self.logo && TEXT_EDITING_KEYS.contains(&self.key.as_str())
+    }
+
+    /// Whether the shortcut is a key on its own, which text fields may want for
+    /// themselves.
+    fn is_plain(&self) -> bool {
+        !self.ctrl && !self.alt && !self.logo
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.ctrl, "Ctrl+"),
+            (self.alt, "Alt+"),
+            (self.shift, "Shift+"),
+            (self.logo, "Cmd+"),
+        ];
+        for (held, name) in modifiers {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        // Named keys are spelled out already; characters read better in capitals.
+        if self.key.chars().count() == 1 {
+            f.write_str(&self.key.to_uppercase())
+        } else {
+            f.write_str(&self.key)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Back,
+}
+
+/// Which shortcut runs which action: the defaults, changed by the `shortcuts` table
+/// of settings.json.
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: Vec<(Action, Option<Shortcut>)>,
+    /// Entries of the table that could not be used, explained for the user.
+    problems: Vec<String>,
+}
+
+impl Keymap {
+    /// The keymap for the `shortcuts` table of settings.json, where an empty string
+    /// unbinds an action.
+    pub fn new(overrides: &BTreeMap<String, String>) -> Self {
+        let mut problems = overrides
+            .keys()
+            .filter(|name| {
+                !Action::ALL
+                    .iter()
+                    .any(|action| action.name() == name.as_str())
+            })
+            .map(|name| format!("\"{name}\" is not an action that can have a shortcut."))
+            .collect::<Vec<_>>();
+
+        let mut bindings: Vec<(Action, Option<Shortcut>)> = Vec::new();
+        for action in Action::ALL {
+            let text = overrides
+                .get(action.name())
+                .map(String::as_str)
+                .unwrap_or(action.default_shortcut());
+            if text.trim().is_empty() {
+                bindings.push((action, None));
+                continue;
+            }
+
+            let shortcut = match Shortcut::parse(text) {
+                Ok(shortcut) => shortcut,
+                Err(error) => {
+                    problems.push(format!("{}: {error}.", action.label()));
+                    bindings.push((action, None));
+                    continue;
+                }
+            };
+            if shortcut.edits_text() {
+                problems.push(format!(
+                    "{}: {shortcut} is kept for editing text.",
+                    action.label()
+                ));
+                bindings.push((action, None));
+                continue;
+            }
+            let taken = bindings
+                .iter()
+                .find(|(_, bound)| bound.as_ref() == Some(&shortcut))
+                .map(|(other, _)| *other);
+            match taken {
+                Some(other) => {
+                    problems.push(format!(
+                        "{shortcut} is set for both \"{}\" and \"{}\"; only the first has it.",
+                        other.label(),
+                        action.label(),
+                    ));
+                    bindings.push((action, None));
+                }
+                None => bindings.push((action, Some(shortcut))),
+            }
+        }
+
+        Self { bindings, problems }
+    }
+
+    /// The action for a key press, by the character on the key or else, for digit
+    /// keys, by the key's place on the keyboard. Keys pressed on their own are left to
+    /// the text field or other widget that `captured` them.
+    pub fn action(
+        &self,
+        key: &Key,
+        physical: Physical,
+        modifiers: Modifiers,
+        captured: bool,
+    ) -> Option<Action> {
+        let pressed = Shortcut::from_key(key, modifiers)?;
+        if captured && pressed.is_plain() {
+            return None;
+        }
+        let bound = |pressed: &Shortcut| {
+            self.bindings
+                .iter()
+                .find(|(_, shortcut)| shortcut.as_ref() == Some(pressed))
+                .map(|(action, _)| *action)
+        };
+        bound(&pressed).or_else(|| bound(&Shortcut::from_digit_key(physical, modifiers)?))
+    }
+
+    /// Entries of the `shortcuts` table that are ignored, and why.
+    pub fn problems(&self) -> &[String] {
+        &self.problems
+    }
+
+    /// The reference screen listing every shortcut.
+    pub fn view(&self) -> Element<'_, Message> {
+        let list = self
+            .bindings
+            .iter()
+            .fold(column![].spacing(8), |list, (action, shortcut)| {
+                let shortcut = match shortcut {
+                    Some(shortcut) => text(shortcut.to_string()).size(16),
+                    None => text("None").size(16),
+                };
+                list.push(
+                    row![
+                        text(action.label()).size(16).width(Length::Fill),
+                        shortcut.width(Length::Fixed(200.0)),
+                    ]
+                    .spacing(12)
+                    .align_y(Alignment::Center),
+                )
+            });
+
+        let mut content = column![
+            row![
+                text("Keyboard shortcuts").size(24),
+                horizontal_space().width(Length::Fill),
+                button(text("Back")).on_press(Message::Back),
+            ]
+            .align_y(Alignment::Center),
+            list,
+            text(
+                "Change them in the \"shortcuts\" table of settings.json, for example \
+                 \"save\": \"Ctrl+Shift+S\", or \"delete\": \"\" to turn one off. \
+                 Changes apply the next time faitout starts.",
+            )
+            .size(14),
+        ]
+        .spacing(16)
+        .width(Length::Fill);
+
+        if !self.problems.is_empty() {
+            let problems = self.problems.iter().fold(
+                column![text("Some entries in settings.json are ignored:").size(16)].spacing(6),
+                |problems, problem| problems.push(text(problem.as_str()).size(14)),
+            );
+            content = content.push(
+                container(problems)
+                    .width(Length::Fill)
+                    .padding([8, 16])
+                    .style(problem_style),
+            );
+        }
+
+        scrollable(container(content).padding(16)).into()
+    }
+}
+
+fn problem_style(theme: &Theme) -> container::Style {
+    let danger = theme.extended_palette().danger.weak;
+    container::Style::default()
+        .background(danger.color)
+        .color(danger.text)
+}
@@ -1,47 +1,92 @@
 #![windows_subsystem = "windows"]
+#![allow(clippy::module_inception)]
 
+use api::api::Message as ApiMessage;
+use capture::capture::{Capture, Event as CaptureEvent, Message as CaptureMessage};
 use editor::editor::{Editor, Event as EditorEvent, Message as EditorMessage};
-use iced::Element;
+use editor::tabs::{Event as TabsEvent, Message as TabsMessage, Tabs};
+use export::export::ExportFormat;
+use graph::graph::{Event as GraphEvent, Graph, Message as GraphMessage};
+use instance::instance::{Instance, Launch, Message as InstanceMessage};
+use keymap::keymap::{Action, Keymap, Message as KeymapMessage};
+use import::import::{Event as ImportEvent, ImportPreview, Message as ImportMessage, Plan};
+use iced::{Alignment, Element, Subscription};
+use iced::keyboard;
 use iced::window;
 use iced::window::icon;
 use iced::{Task, Length};
-use iced::widget::{column, container, scrollable, text};
-use iced::widget::markdown::{self, Settings as MdSettings, Style as MdStyle};
+use iced::widget::{button, column, container, horizontal_space, row, stack, text, text_input};
+use iced::widget::markdown;
 use iced::Theme;
-use notes::notes::{Event as NotesEvent, Message as NotesMessage, Note, Notes};
-use settings::settings::{SettingsState, Message as SettingsMessage};
+use notes::attachments;
+use notes::links::{self, WikiLink};
+use notes::notebook;
+use notes::notes::{Event as NotesEvent, Message as NotesMessage, Note, NoteId, Notes};
+use settings::settings::{SettingsState, Message as SettingsMessage, WindowGeometry, WindowSession};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use toast::toast::{Message as ToastMessage, Toasts};
 
+mod api;
+mod capture;
+mod cli;
 mod editor;
+mod export;
+mod graph;
+mod import;
+mod instance;
+mod keymap;
 mod notes;
+mod platform;
 mod settings;
+mod storage;
+mod toast;
 
 
 fn main() -> iced::Result {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let Some(launch) = Launch::parse(&args) else {
+        std::process::exit(cli::cli::run(&args).unwrap_or(0));
+    };
+    // Only one app may write the notebooks; later launches hand their request to it.
+    let instance = match Instance::claim(&launch) {
+        Ok(Some(instance)) => instance,
+        Ok(None) => return Ok(()),
+        Err(error) => {
+            eprintln!("faitout: {error}");
+            std::process::exit(1);
+        }
+    };
+
     iced::daemon(|app: &App, id: window::Id| {
             match app.state.windows.get(&id).copied() {
-                Some(WindowView::Note(index)) => {
-                    let title = app.state.notes.get(index).map(|n| n.title.as_str()).unwrap_or("Note");
-                    if title.trim().is_empty() {
-                        String::from("faitout - Untitled page")
-                    } else {
-                        format!("faitout - {}", title)
-                    }
+                Some(WindowView::Note(id)) => {
+                    let title = app.state.notes.find(id).map(Note::display_title).unwrap_or("Note");
+                    format!("faitout - {}", title)
                 }
+                Some(WindowView::Capture) => String::from("faitout - Quick capture"),
                 _ => String::from("faitout"),
             }
         }, App::update, App::view)
         .theme(|app: &App, _id: window::Id| app.state.settings.theme())
-        .subscription(|_app: &App| window::close_events().map(Message::WindowClosed))
-        .run_with(|| {
+        .subscription(App::subscription)
+        .run_with(move || {
             let mut app = App::default();
-            let settings = window::Settings {
-                icon: load_app_icon(),
-                ..Default::default()
+            app.state.instance = Some(instance);
+            // Files that could not be read are reported right away, not after the first
+            // message.
+            app.report_storage_errors();
+            let task = match launch {
+                // Capturing a thought should not bring up the whole app.
+                Launch::Capture => app.handle_launch(Launch::Capture),
+                Launch::Main => app.restore_windows(),
+                launch => {
+                    let restored = app.restore_windows();
+                    Task::batch([restored, app.handle_launch(launch)])
+                }
             };
-            let (id, task) = window::open(settings);
-            app.state.windows.insert(id, WindowView::Main);
-            (app, task.map(Message::WindowOpened))
+            (app, task)
         })
 }
 
@@ -50,13 +95,71 @@ struct App {
     state: State,
 }
 
-#[derive(Default)]
 struct State {
     screen: Screen,
-    editor: Editor,
+    /// The pages open in the main window.
+    tabs: Tabs,
+    graph: Graph,
+    import: ImportPreview,
+    capture: Capture,
+    /// The editor in each note window.
+    note_editors: HashMap<window::Id, Editor>,
     notes: Notes,
     settings: SettingsState,
+    keymap: Keymap,
     windows: HashMap<window::Id, WindowView>,
+    /// Where each window is and how big, as last reported.
+    geometry: HashMap<window::Id, WindowGeometry>,
+    toasts: Toasts,
+    /// Title of a wiki link that was clicked but leads to no page yet.
+    missing_link: Option<String>,
+    /// A clicked link waiting for the user to confirm it should be opened.
+    pending_open: Option<String>,
+    /// The main window was asked to close while changes are unsaved.
+    quitting: bool,
+    /// A page the delete shortcut was pressed on, waiting for confirmation.
+    deleting: Option<NoteId>,
+    /// This process's hold on the data directory; later launches are handed to it.
+    instance: Option<Instance>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        let settings = SettingsState::default();
+        let mut notes = match settings.last_notebook() {
+            Some(key) if notebook::discover().iter().any(|notebook| notebook.key == key) => {
+                Notes::open(key)
+            }
+            _ => Notes::load(),
+        };
+        notes.set_color_filter(settings.color_filter());
+        collect_attachments(&notes, []);
+        let keymap = Keymap::new(settings.shortcuts());
+        let mut toasts = Toasts::default();
+        if !keymap.problems().is_empty() {
+            toasts.error("Some keyboard shortcuts in settings.json are ignored. See Settings.");
+        }
+
+        Self {
+            screen: Screen::default(),
+            tabs: Tabs::default(),
+            graph: Graph::default(),
+            import: ImportPreview::default(),
+            capture: Capture::default(),
+            note_editors: HashMap::new(),
+            notes,
+            settings,
+            keymap,
+            windows: HashMap::new(),
+            geometry: HashMap::new(),
+            toasts,
+            missing_link: None,
+            pending_open: None,
+            quitting: false,
+            deleting: None,
+            instance: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
@@ -64,62 +167,111 @@ enum Screen {
     #[default]
     Notes,
     Editor,
-    Settings
+    Settings,
+    Graph,
+    Import,
+    Shortcuts,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     Editor(EditorMessage),
+    Tabs(TabsMessage),
+    Keymap(KeymapMessage),
+    /// A key was pressed in a window; `captured` when a widget there already used it.
+    KeyPressed {
+        window: window::Id,
+        key: keyboard::Key,
+        physical_key: keyboard::key::Physical,
+        modifiers: keyboard::Modifiers,
+        captured: bool,
+    },
     Notes(NotesMessage),
+    Graph(GraphMessage),
+    Import(ImportMessage),
+    Capture(CaptureMessage),
     Settings(SettingsMessage),
     WindowOpened(window::Id),
     WindowClosed(window::Id),
+    /// The user asked to close the main window, which waits for `QuitConfirmed` while
+    /// changes are unsaved.
+    CloseRequested(window::Id),
+    QuitConfirmed,
+    QuitCancelled,
+    DeleteConfirmed,
+    DeleteCancelled,
+    /// A window opened, moved or was resized.
+    WindowPlaced(window::Id, Option<iced::Point>, Option<iced::Size>),
+    Toast(ToastMessage),
+    Api(ApiMessage),
+    Instance(InstanceMessage),
+    RetrySave,
+    NoteWindow(window::Id, EditorMessage),
+    CreateLinkedPage,
+    DismissLinkPrompt,
+    ConfirmOpenLink,
+    CancelOpenLink,
+    ImagesPicked(Option<window::Id>, Result<Vec<PathBuf>, String>),
+    FilesPicked(Option<window::Id>, Result<Vec<PathBuf>, String>),
+    ExportNoteTo(NoteId, Result<Option<PathBuf>, String>),
+    ExportNotebookTo(ExportFormat, Result<Option<PathBuf>, String>),
+    ImportScanned(Result<Option<Plan>, String>),
+    ClipboardImage(Option<window::Id>, Option<Vec<u8>>),
+    ClipboardText(Option<window::Id>, Option<String>),
+    FileDropped(window::Id, PathBuf),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum WindowView {
     Main,
-    Note(usize),
+    Note(NoteId),
+    Capture,
 }
 
 impl App {
     fn update(&mut self, message: Message) -> Task<Message> {
-        match message {
-            Message::Editor(message) => {
-                if let Some(event) = self.state.editor.update(message) {
-                    match event {
-                        EditorEvent::Save {
-                            title,
-                            body,
-                            tags,
-                            editing,
-                        } => {
-                            let note = Note::new(title, body, tags);
-
-                            let saved_index = self.state.notes.upsert(note, editing);
-                            self.state.notes.select(Some(saved_index));
-                            self.state.editor.load_new();
-                            self.state.screen = Screen::Notes;
-                        }
-                        EditorEvent::Back => {
-                            self.state.editor.load_new();
+        let task = match message {
+            Message::Editor(message) => self.update_editor(None, message),
+            Message::NoteWindow(window, message) => self.update_editor(Some(window), message),
+            Message::Tabs(message) => {
+                match self.state.tabs.update(message) {
+                    Some(TabsEvent::Show) => self.show_current_tab(),
+                    Some(TabsEvent::ShowNotebook) => self.state.screen = Screen::Notes,
+                    Some(TabsEvent::Closed) => {
+                        self.collect_attachments();
+                        if self.state.tabs.is_empty() {
                             self.state.screen = Screen::Notes;
                         }
                     }
+                    None => {}
                 }
                 Task::none()
             }
+            Message::Keymap(KeymapMessage::Back) => {
+                self.state.screen = Screen::Settings;
+                Task::none()
+            }
+            Message::KeyPressed {
+                window,
+                key,
+                physical_key,
+                modifiers,
+                captured,
+            } => match self.state.keymap.action(&key, physical_key, modifiers, captured) {
+                Some(action) => self.perform(window, action),
+                None => Task::none(),
+            },
             Message::Notes(message) => {
                 if let Some(event) = self.state.notes.update(message) {
                     match event {
                         NotesEvent::Create => {
-                            self.state.editor.load_new();
+                            self.state.tabs.open_new(None);
                             self.state.screen = Screen::Editor;
                             Task::none()
                         }
                         NotesEvent::Edit(index) => {
                             if let Some(note) = self.state.notes.get(index) {
-                                self.state.editor.load_existing(index, note);
+                                self.state.tabs.open(index, note);
                                 self.state.notes.select(Some(index));
                                 self.state.screen = Screen::Editor;
                                 Task::none()
@@ -127,39 +279,128 @@ impl App {
                             else { Task::none() }
                         }
                         NotesEvent::Delete(index) => {
-                            self.state.editor.adjust_after_delete(index);
-
-                            if let Some(current) = self.state.editor.editing() {
-                                self.state.notes.select(Some(current));
-                            } else {
-                                self.state.notes.select(None);
-                                self.state.screen = Screen::Notes;
-                            }
-                            Task::none()
+                            self.after_note_removed(index);
+                            self.collect_attachments();
+                            self.sync_note_windows(&[])
                         }
                         NotesEvent::OpenSettings => {
                             self.state.screen = Screen::Settings;
                             Task::none()
                         }
-                        NotesEvent::OpenInNewWindow(index) => {
-                            let settings = window::Settings {
-                                icon: load_app_icon(),
-                                ..Default::default()
+                        NotesEvent::Export(format) => {
+                            let name = export::files::sanitize(&self.state.notes.name());
+                            let file_name = format!("{name}.zip");
+                            Task::perform(
+                                async move {
+                                    if format.is_folder() {
+                                        platform::platform::pick_folder("Export notebook")
+                                    } else {
+                                        platform::platform::save_file("Export notebook", &file_name)
+                                    }
+                                    .map_err(|error| error.to_string())
+                                },
+                                move |picked| Message::ExportNotebookTo(format, picked),
+                            )
+                        }
+                        NotesEvent::Import(source) => Task::perform(
+                            async move { import::import::pick_and_scan(source) },
+                            Message::ImportScanned,
+                        ),
+                        NotesEvent::OpenGraph => {
+                            self.state.screen = Screen::Graph;
+                            self.state
+                                .graph
+                                .open(&self.state.notes, self.state.settings.palette())
+                                .map(Message::Graph)
+                        }
+                        NotesEvent::OpenInNewWindow(note) => self.open_note_window(note, None),
+                        NotesEvent::QuickCapture => self.handle_launch(Launch::Capture),
+                        NotesEvent::SwitchNotebook(key) if self.can_leave_notebook() => {
+                            self.switch_notebook(Notes::open(&key))
+                        }
+                        NotesEvent::CreateNotebook(name) if self.can_leave_notebook() => {
+                            self.switch_notebook(Notes::create(&name))
+                        }
+                        NotesEvent::SwitchNotebook(_) | NotesEvent::CreateNotebook(_) => {
+                            Task::none()
+                        }
+                        NotesEvent::MoveToNotebook { index, notebook } => {
+                            self.move_note(index, &notebook)
+                        }
+                        NotesEvent::NotesRemoved(ids) => self.forget_notes(&ids),
+                        NotesEvent::FollowLink(link) => self.follow_link(&link, None),
+                        NotesEvent::OpenExternal(target) => self.open_external(&target),
+                        NotesEvent::ColorFilterChanged(colors) => {
+                            self.state.settings.set_color_filter(colors);
+                            Task::none()
+                        }
+                        NotesEvent::Reloaded { changed, removed } => {
+                            self.sync_tabs(&changed, &removed);
+                            let relayout = if self.state.screen == Screen::Graph {
+                                self.state
+                                    .graph
+                                    .rebuild(&self.state.notes, self.state.settings.palette())
+                                    .map(Message::Graph)
+                            } else {
+                                Task::none()
                             };
-                            let (id, task) = window::open(settings);
-                            self.state.windows.insert(id, WindowView::Note(index));
-                            task.map(Message::WindowOpened)
+                            Task::batch([relayout, self.sync_note_windows(&changed)])
                         }
                     }
                 } else { Task::none() }
             }
-
+            Message::Graph(message) => match self.state.graph.update(message) {
+                Some(GraphEvent::Open(id)) => {
+                    let title = self
+                        .state
+                        .notes
+                        .find(id)
+                        .map(|note| note.title.clone())
+                        .unwrap_or_default();
+                    self.follow_link(&WikiLink { title, id: Some(id) }, None)
+                }
+                Some(GraphEvent::Back) => {
+                    self.state.screen = Screen::Notes;
+                    Task::none()
+                }
+                Some(GraphEvent::TagsToggled) => self
+                    .state
+                    .graph
+                    .rebuild(&self.state.notes, self.state.settings.palette())
+                    .map(Message::Graph),
+                None => Task::none(),
+            },
+            Message::Import(message) => match self.state.import.update(message) {
+                Some(ImportEvent::Confirm) => {
+                    let plan = self.state.import.take_plan();
+                    let applied = import::import::apply(&plan, &mut self.state.notes);
+                    for error in &applied.errors {
+                        self.state.toasts.error(error.to_string());
+                    }
+                    let summary = applied.summary(&format!("from {}", plan.source));
+                    self.state.toasts.info(summary);
+                    self.state.screen = Screen::Notes;
+                    Task::none()
+                }
+                Some(ImportEvent::Cancel) => {
+                    self.state.screen = Screen::Notes;
+                    Task::none()
+                }
+                None => Task::none(),
+            },
             Message::Settings(message) => {
                 match message {
                     SettingsMessage::Back => {
                         self.state.screen = Screen::Notes;
                     }
-                    _ => self.state.settings.update(message),
+                    SettingsMessage::ShowShortcuts => {
+                        self.state.screen = Screen::Shortcuts;
+                    }
+                    _ => {
+                        self.state.settings.update(message);
+                        // Removing a custom color takes it out of the filter as well.
+                        self.state.notes.set_color_filter(self.state.settings.color_filter());
+                    }
                 }
                 Task::none()
             }
@@ -168,47 +409,1351 @@ impl App {
                 Task::none()
             }
             Message::WindowClosed(id) => {
-                self.state.windows.remove(&id);
-                if self.state.windows.is_empty() {
-                    iced::exit()
+                if self.state.windows.get(&id) == Some(&WindowView::Main) {
+                    // Closing the main window quits; its note windows come back next time.
+                    self.save_session(id);
+                    let others = self.state.windows.keys().copied().collect();
+                    Task::batch([self.close_window_ids(others), iced::exit()])
                 } else {
-                    Task::none()
+                    self.forget_window(id);
+                    if self.state.windows.is_empty() {
+                        iced::exit()
+                    } else {
+                        Task::none()
+                    }
+                }
+            }
+            Message::CloseRequested(id) => {
+                if self.has_unsaved_changes() {
+                    self.state.quitting = true;
+                    self.focus_main_window()
+                } else {
+                    window::close(id)
+                }
+            }
+            Message::QuitConfirmed => {
+                self.state.quitting = false;
+                match self.main_window() {
+                    Some(id) => window::close(id),
+                    None => iced::exit(),
+                }
+            }
+            Message::QuitCancelled => {
+                self.state.quitting = false;
+                Task::none()
+            }
+            Message::DeleteConfirmed => {
+                let index = self
+                    .state
+                    .deleting
+                    .take()
+                    .and_then(|id| self.state.notes.position(id));
+                match index {
+                    Some(index) => {
+                        self.update(Message::Notes(NotesMessage::DeleteRequested(index)))
+                    }
+                    None => Task::none(),
+                }
+            }
+            Message::DeleteCancelled => {
+                self.state.deleting = None;
+                Task::none()
+            }
+            Message::WindowPlaced(id, position, size) => {
+                let geometry = self.state.geometry.entry(id).or_insert(WindowGeometry {
+                    width: 0.0,
+                    height: 0.0,
+                    position: None,
+                });
+                if let Some(position) = position {
+                    geometry.position = Some((position.x, position.y));
+                }
+                if let Some(size) = size {
+                    geometry.width = size.width;
+                    geometry.height = size.height;
+                }
+                Task::none()
+            }
+            Message::Toast(message) => {
+                self.state.toasts.update(message);
+                Task::none()
+            }
+            Message::Api(ApiMessage::Called(call)) => {
+                match call.answer(&mut self.state.notes) {
+                    // Open editors follow the script's change, as after an outside edit.
+                    Some(id) => {
+                        self.sync_tabs(&[id], &[]);
+                        self.sync_note_windows(&[id])
+                    }
+                    None => Task::none(),
+                }
+            }
+            Message::Api(ApiMessage::Failed(error)) => {
+                self.state.toasts.error(error);
+                Task::none()
+            }
+            Message::Instance(InstanceMessage::Launched(launch)) => self.handle_launch(launch),
+            Message::Capture(message) => match self.state.capture.update(message) {
+                Some(CaptureEvent::Save { title, body, tags }) => {
+                    let index = self.state.notes.upsert(Note::new(title, body, tags), None);
+                    if let Some(note) = self.state.notes.get(index) {
+                        let title = note.display_title();
+                        self.state.toasts.info(format!("Added \"{title}\"."));
+                    }
+                    self.close_windows(|view| *view == WindowView::Capture)
+                }
+                Some(CaptureEvent::Cancel) => {
+                    self.close_windows(|view| *view == WindowView::Capture)
+                }
+                None => Task::none(),
+            },
+            Message::CreateLinkedPage => {
+                if let Some(title) = self.state.missing_link.take() {
+                    self.state.tabs.open_new(Some(&title));
+                    self.state.screen = Screen::Editor;
                 }
+                Task::none()
             }
+            Message::DismissLinkPrompt => {
+                self.state.missing_link = None;
+                Task::none()
+            }
+            Message::ConfirmOpenLink => {
+                if let Some(target) = self.state.pending_open.take() {
+                    self.launch(&target);
+                }
+                Task::none()
+            }
+            Message::CancelOpenLink => {
+                self.state.pending_open = None;
+                Task::none()
+            }
+            Message::ImagesPicked(target, Ok(paths)) => {
+                for path in paths {
+                    self.attach_image(target, &path);
+                }
+                Task::none()
+            }
+            Message::FilesPicked(target, Ok(paths)) => {
+                for path in paths {
+                    self.attach_file(target, &path);
+                }
+                Task::none()
+            }
+            Message::ExportNoteTo(id, Ok(Some(path))) => {
+                if let Some(note) = self.state.notes.find(id) {
+                    match export::html::export_note(note, self.export_look(), &path) {
+                        Ok(()) => self.state.toasts.info(format!("Exported to {}", path.display())),
+                        Err(error) => self.state.toasts.error(error.to_string()),
+                    }
+                }
+                Task::none()
+            }
+            Message::ExportNotebookTo(format, Ok(Some(path))) => {
+                let notes = &self.state.notes;
+                match export::export::export_notebook(format, notes, self.export_look(), &path) {
+                    Ok(count) => self
+                        .state
+                        .toasts
+                        .info(format!("Exported {count} pages to {}", path.display())),
+                    Err(error) => self.state.toasts.error(error.to_string()),
+                }
+                Task::none()
+            }
+            Message::ExportNoteTo(_, Ok(None)) | Message::ExportNotebookTo(_, Ok(None)) => {
+                Task::none()
+            }
+            Message::ImagesPicked(_, Err(error))
+            | Message::FilesPicked(_, Err(error))
+            | Message::ExportNoteTo(_, Err(error))
+            | Message::ExportNotebookTo(_, Err(error)) => {
+                self.state.toasts.error(format!("Could not show the file dialog: {error}"));
+                Task::none()
+            }
+            Message::ImportScanned(Ok(Some(plan))) => {
+                self.state.import.open(plan, &self.state.notes);
+                self.state.screen = Screen::Import;
+                Task::none()
+            }
+            Message::ImportScanned(Ok(None)) => Task::none(),
+            Message::ImportScanned(Err(error)) => {
+                self.state.toasts.error(error);
+                Task::none()
+            }
+            Message::ClipboardImage(target, Some(bytes)) => {
+                match attachments::store_bytes(&bytes, "Pasted image.png") {
+                    Ok(stored) => {
+                        if let Some(editor) = self.editor_mut(target) {
+                            editor.insert_block(&attachments::image_markdown(
+                                &stored.file,
+                                "Pasted image",
+                            ));
+                        }
+                    }
+                    Err(error) => self.state.toasts.error(error.to_string()),
+                }
+                Task::none()
+            }
+            // Without image data, the clipboard may still name an image file.
+            Message::ClipboardImage(target, None) => {
+                iced::clipboard::read().map(move |text| Message::ClipboardText(target, text))
+            }
+            Message::ClipboardText(target, text) => {
+                let paths = text
+                    .iter()
+                    .flat_map(|text| text.lines())
+                    .filter_map(|line| {
+                        let line = line.trim();
+                        match markdown::Url::parse(line) {
+                            Ok(url) if url.scheme() == "file" => url.to_file_path().ok(),
+                            _ => Some(PathBuf::from(line)),
+                        }
+                    })
+                    .filter(|path| attachments::is_image(path) && path.is_file())
+                    .collect::<Vec<_>>();
+
+                if paths.is_empty() {
+                    self.state.toasts.info("There is no image on the clipboard.");
+                }
+                for path in paths {
+                    self.attach_image(target, &path);
+                }
+                Task::none()
+            }
+            Message::FileDropped(window, path) => {
+                let target = match self.state.windows.get(&window) {
+                    Some(WindowView::Main) if self.state.screen == Screen::Editor => Some(None),
+                    Some(WindowView::Note(_)) => Some(Some(window)),
+                    _ => None,
+                };
+
+                match target {
+                    None => {
+                        self.state.toasts.info("Open a page in the editor to attach files to it.")
+                    }
+                    Some(target) if attachments::is_image(&path) => {
+                        self.attach_image(target, &path)
+                    }
+                    Some(target) => self.attach_file(target, &path),
+                }
+                Task::none()
+            }
+            Message::RetrySave => {
+                let notes_saved =
+                    self.state.notes.unsaved().is_none() || self.state.notes.retry_save();
+                let settings_saved =
+                    self.state.settings.unsaved().is_none() || self.state.settings.retry_save();
+                if notes_saved && settings_saved {
+                    self.state.toasts.info("All changes saved.");
+                }
+                Task::none()
+            }
+        };
+
+        self.report_storage_errors();
+        task
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let instance = match &self.state.instance {
+            Some(instance) => instance.subscription().map(Message::Instance),
+            None => Subscription::none(),
+        };
+        let api = if self.state.settings.scripting_api() {
+            api::api::subscription().map(Message::Api)
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch([
+            window::close_events().map(Message::WindowClosed),
+            window::close_requests().map(Message::CloseRequested),
+            iced::event::listen_with(|event, status, window| match event {
+                iced::Event::Window(window::Event::FileDropped(path)) => {
+                    Some(Message::FileDropped(window, path))
+                }
+                iced::Event::Window(window::Event::Opened { position, size }) => {
+                    Some(Message::WindowPlaced(window, position, Some(size)))
+                }
+                iced::Event::Window(window::Event::Moved(position)) => {
+                    Some(Message::WindowPlaced(window, Some(position), None))
+                }
+                iced::Event::Window(window::Event::Resized(size)) => {
+                    Some(Message::WindowPlaced(window, None, Some(size)))
+                }
+                iced::Event::Keyboard(keyboard::Event::KeyPressed {
+                    key,
+                    physical_key,
+                    modifiers,
+                    ..
+                }) => Some(Message::KeyPressed {
+                    window,
+                    key,
+                    physical_key,
+                    modifiers,
+                    captured: status == iced::event::Status::Captured,
+                }),
+                _ => None,
+            }),
+            self.state.notes.subscription().map(Message::Notes),
+            self.state.toasts.subscription().map(Message::Toast),
+            instance,
+            api,
+        ])
+    }
+
+    /// Moves load/save failures recorded by the stores into on-screen notifications.
+    fn report_storage_errors(&mut self) {
+        let errors = self
+            .state
+            .notes
+            .take_errors()
+            .into_iter()
+            .chain(self.state.settings.take_errors());
+
+        for error in errors {
+            self.state.toasts.error(error.to_string());
         }
     }
 
     fn view(&self, id: window::Id) -> Element<'_, Message> {
         match self.state.windows.get(&id).copied() {
-            Some(WindowView::Main) | None => match self.state.screen {
-                Screen::Editor => self.state.editor.view().map(Message::Editor),
-                Screen::Notes => self.state.notes.view().map(Message::Notes),
-                Screen::Settings => self.state.settings.view().map(Message::Settings),
-            },
-            Some(WindowView::Note(index)) => self.note_window_view(index),
+            Some(WindowView::Main) | None => {
+                let notes = || {
+                    self.state
+                        .notes
+                        .view(self.state.settings.palette())
+                        .map(Message::Notes)
+                };
+                let screen = match self.state.screen {
+                    Screen::Editor => match self.state.tabs.current() {
+                        Some(editor) => {
+                            let backlinks = editor
+                                .editing_id()
+                                .map(|id| self.state.notes.backlinks(id))
+                                .unwrap_or_default();
+                            let view = editor.view(backlinks).map(Message::Editor);
+                            self.with_shared_edit_warning(None, view)
+                        }
+                        None => notes(),
+                    },
+                    Screen::Notes => notes(),
+                    Screen::Settings => self.state.settings.view().map(Message::Settings),
+                    Screen::Graph => self.state.graph.view().map(Message::Graph),
+                    Screen::Import => self
+                        .state
+                        .import
+                        .view(&self.state.notes.name())
+                        .map(Message::Import),
+                    Screen::Shortcuts => self.state.keymap.view().map(Message::Keymap),
+                };
+                let tabs_shown = matches!(self.state.screen, Screen::Notes | Screen::Editor);
+                let screen = if tabs_shown && !self.state.tabs.is_empty() {
+                    let notebook_shown = self.state.screen == Screen::Notes;
+                    let tabs = self.state.tabs.view(notebook_shown).map(Message::Tabs);
+                    column![tabs, screen].into()
+                } else {
+                    screen
+                };
+                self.with_notifications(screen)
+            }
+            Some(WindowView::Note(_)) => self.note_window_view(id),
+            Some(WindowView::Capture) => self.state.capture.view().map(Message::Capture),
         }
     }
 
-    fn note_window_view(&self, index: usize) -> Element<'_, Message> {
+    /// Opens the target of a wiki link: in `window` if it came from a note window,
+    /// otherwise in the editor. Links to missing pages offer to create them.
+    fn follow_link(&mut self, link: &WikiLink, window: Option<window::Id>) -> Task<Message> {
+        let Some(target) = self.state.notes.resolve(link) else {
+            self.state.missing_link = Some(link.title.clone());
+            return self.focus_main_window();
+        };
+
+        if let Some(window) = window {
+            self.show_in_window(window, target);
+            return Task::none();
+        }
+
+        if let Some(index) = self.state.notes.position(target)
+            && let Some(note) = self.state.notes.get(index)
+        {
+            self.state.tabs.open(index, note);
+            self.state.notes.select(Some(index));
+            self.state.screen = Screen::Editor;
+        }
+        Task::none()
+    }
+
+    /// Hands a link that does not point into the notebook to the system, asking first
+    /// when the settings say so.
+    fn open_external(&mut self, target: &str) -> Task<Message> {
+        let (resolved, is_file) = match resolve_external(target, &storage::storage::data_dir()) {
+            Ok(resolved) => resolved,
+            Err(reason) => {
+                self.state.toasts.error(reason);
+                return Task::none();
+            }
+        };
+
+        // Files are always confirmed: opening one may run it.
+        if is_file || self.state.settings.confirm_external_links() {
+            self.state.pending_open = Some(resolved);
+            return self.focus_main_window();
+        }
+
+        self.launch(&resolved);
+        Task::none()
+    }
+
+    fn launch(&mut self, target: &str) {
+        if let Err(error) = platform::platform::open(target) {
+            self.state.toasts.error(format!("Could not open {target}: {error}"));
+        }
+    }
+
+    /// Copies the image at `path` into the attachments and embeds it in the page being
+    /// edited in `target`.
+    fn attach_image(&mut self, target: Option<window::Id>, path: &Path) {
+        match attachments::store(path) {
+            Ok(stored) => {
+                let alt = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or("image");
+                if let Some(editor) = self.editor_mut(target) {
+                    editor.insert_block(&attachments::image_markdown(&stored.file, alt));
+                }
+            }
+            Err(error) => self.state.toasts.error(error.to_string()),
+        }
+    }
+
+    /// Copies the file at `path` into the attachments and adds it to the attachment
+    /// list of the page being edited in `target`.
+    fn attach_file(&mut self, target: Option<window::Id>, path: &Path) {
+        match attachments::store(path) {
+            Ok(stored) => {
+                let name = stored.name.clone();
+                let added = self
+                    .editor_mut(target)
+                    .is_some_and(|editor| editor.add_attachment(stored));
+                if !added {
+                    self.state.toasts.info(format!("{name} is already attached to this page."));
+                }
+            }
+            Err(error) => self.state.toasts.error(error.to_string()),
+        }
+    }
+
+    /// Deletes attachments no page refers to any more, counting the unsaved pages in
+    /// the editors.
+    fn collect_attachments(&self) {
+        let unsaved = self
+            .state
+            .tabs
+            .iter()
+            .chain(self.state.note_editors.values())
+            .flat_map(|editor| {
+                std::iter::once(editor.body_text())
+                    .chain(editor.attachments().iter().map(|attachment| attachment.file.clone()))
+            })
+            .collect::<Vec<_>>();
+        collect_attachments(&self.state.notes, unsaved);
+    }
+
+    fn export_look(&self) -> export::html::Look<'_> {
+        export::html::Look {
+            theme: self.state.settings.theme().palette(),
+            notes: self.state.settings.palette(),
+        }
+    }
+
+    /// Carries out what a launch of the app asked for.
+    fn handle_launch(&mut self, launch: Launch) -> Task<Message> {
+        match launch {
+            Launch::Main => {
+                if self.state.windows.values().any(|view| *view == WindowView::Main) {
+                    self.focus_main_window()
+                } else {
+                    self.open_main_window(self.state.settings.windows().main)
+                }
+            }
+            Launch::Open(page) => {
+                let found = page
+                    .trim()
+                    .parse()
+                    .ok()
+                    .and_then(|raw| self.state.notes.find(NoteId::from_raw(raw)))
+                    .or_else(|| {
+                        let title = links::normalize(&page);
+                        self.state
+                            .notes
+                            .iter()
+                            .find(|note| links::normalize(&note.title) == title)
+                    })
+                    .map(|note| note.id);
+
+                match found {
+                    Some(note) => self.open_note_window(note, None),
+                    None => {
+                        self.state.toasts.error(format!("There is no page called \"{page}\"."));
+                        self.handle_launch(Launch::Main)
+                    }
+                }
+            }
+            Launch::Capture => {
+                let open = self
+                    .state
+                    .windows
+                    .iter()
+                    .find(|(_, view)| **view == WindowView::Capture)
+                    .map(|(id, _)| *id);
+                if let Some(id) = open {
+                    return window::gain_focus(id);
+                }
+
+                let settings = window::Settings {
+                    icon: load_app_icon(),
+                    ..Capture::window_settings()
+                };
+                let geometry = self.state.settings.windows().capture;
+                let (id, task) = window::open(placed(settings, geometry));
+                self.state.windows.insert(id, WindowView::Capture);
+                task.map(Message::WindowOpened)
+            }
+        }
+    }
+
+    /// Opens the main window and the note windows that were open when the app was last
+    /// closed, where they were.
+    fn restore_windows(&mut self) -> Task<Message> {
+        let session = self.state.settings.windows().clone();
+        for &id in &session.tabs {
+            if let Some(index) = self.state.notes.position(id)
+                && let Some(note) = self.state.notes.get(index)
+            {
+                self.state.tabs.open(index, note);
+            }
+        }
+        if let Some(tab) = session.active_tab.and_then(|id| self.state.tabs.find(id)) {
+            self.state.tabs.select(tab);
+            self.state.screen = Screen::Editor;
+        }
+
+        let main = self.open_main_window(session.main);
+        let notes = session
+            .notes
+            .into_iter()
+            .map(|(note, geometry)| self.open_note_window(note, Some(geometry)))
+            .collect::<Vec<_>>();
+        Task::batch(std::iter::once(main).chain(notes))
+    }
+
+    /// Remembers the open windows, to be restored by [`App::restore_windows`].
+    fn save_session(&mut self, main: window::Id) {
+        let geometry = &self.state.geometry;
+        let mut notes = self
+            .state
+            .windows
+            .iter()
+            .filter_map(|(window, view)| match view {
+                WindowView::Note(note) => Some((*window, *note)),
+                _ => None,
+            })
+            .filter_map(|(window, note)| Some((window, note, *geometry.get(&window)?)))
+            .collect::<Vec<_>>();
+        notes.sort_by_key(|(window, _, _)| *window);
+
+        let previous = self.state.settings.windows();
+        let capture = self
+            .state
+            .windows
+            .iter()
+            .find(|(_, view)| **view == WindowView::Capture)
+            .and_then(|(window, _)| geometry.get(window).copied())
+            .or(previous.capture);
+        let session = WindowSession {
+            main: geometry.get(&main).copied().or(previous.main),
+            notes: notes
+                .into_iter()
+                .map(|(_, note, geometry)| (note, geometry))
+                .collect(),
+            capture,
+            tabs: self.state.tabs.iter().filter_map(Editor::editing_id).collect(),
+            active_tab: self
+                .state
+                .tabs
+                .current()
+                .and_then(Editor::editing_id)
+                .filter(|_| self.state.screen == Screen::Editor),
+        };
+        self.state.settings.set_windows(session);
+    }
+
+    /// Drops what the app kept about a window that was closed.
+    fn forget_window(&mut self, id: window::Id) {
+        let view = self.state.windows.remove(&id);
+        self.state.note_editors.remove(&id);
+        let geometry = self.state.geometry.remove(&id);
+
+        if view == Some(WindowView::Capture) && geometry.is_some() {
+            let mut session = self.state.settings.windows().clone();
+            session.capture = geometry;
+            self.state.settings.set_windows(session);
+        }
+    }
+
+    fn open_main_window(&mut self, geometry: Option<WindowGeometry>) -> Task<Message> {
+        // Closing it quits, which waits for `CloseRequested` to check for unsaved changes.
+        let settings = window::Settings {
+            exit_on_close_request: false,
+            ..window_settings(geometry)
+        };
+        let (id, task) = window::open(settings);
+        self.state.windows.insert(id, WindowView::Main);
+        task.map(Message::WindowOpened)
+    }
+
+    fn open_note_window(
+        &mut self,
+        note: NoteId,
+        geometry: Option<WindowGeometry>,
+    ) -> Task<Message> {
+        let Some(index) = self.state.notes.position(note) else {
+            return Task::none();
+        };
+        let mut editor = Editor::detached();
+        if let Some(note) = self.state.notes.get(index) {
+            editor.load_existing(index, note);
+        }
+
+        let (id, task) = window::open(window_settings(geometry));
+        self.state.windows.insert(id, WindowView::Note(note));
+        self.state.note_editors.insert(id, editor);
+        task.map(Message::WindowOpened)
+    }
+
+    /// Loads the page `target` into the editor of the note window `window`, unless that
+    /// would throw away unsaved changes.
+    fn show_in_window(&mut self, window: window::Id, target: NoteId) {
+        let Some(index) = self.state.notes.position(target) else {
+            return;
+        };
+        let Some(editor) = self.state.note_editors.get_mut(&window) else {
+            return;
+        };
+        if editor.editing_id() != Some(target) && editor.is_modified() {
+            self.state.toasts.info("Save or close the page before following a link.");
+            return;
+        }
+
         if let Some(note) = self.state.notes.get(index) {
-            let title = if note.title.trim().is_empty() { "Untitled page" } else { &note.title };
+            editor.reload(index, note);
+            self.state.windows.insert(window, WindowView::Note(target));
+        }
+    }
+
+    /// Carries out the keyboard shortcut `action` pressed in `window`.
+    fn perform(&mut self, window: window::Id, action: Action) -> Task<Message> {
+        let view = self.state.windows.get(&window).copied();
+        let editing = self.state.screen == Screen::Editor;
+
+        match (action, view) {
+            (Action::NewNote, _) => {
+                self.state.tabs.open_new(None);
+                self.state.screen = Screen::Editor;
+                self.focus_main_window()
+            }
+            (Action::ShowShortcuts, _) => {
+                self.state.screen = Screen::Shortcuts;
+                self.focus_main_window()
+            }
+            (Action::Save, Some(WindowView::Main)) if editing => {
+                self.update_editor(None, EditorMessage::SavePressed)
+            }
+            (Action::Save, Some(WindowView::Note(_))) => {
+                self.update_editor(Some(window), EditorMessage::SavePressed)
+            }
+            (Action::Save, Some(WindowView::Capture)) => {
+                self.update(Message::Capture(CaptureMessage::SavePressed))
+            }
+            (Action::Back, Some(WindowView::Main)) => self.go_back(),
+            (Action::Back, Some(WindowView::Note(_))) => {
+                self.update_editor(Some(window), EditorMessage::BackPressed)
+            }
+            (Action::Back, Some(WindowView::Capture)) => {
+                self.update(Message::Capture(CaptureMessage::CancelPressed))
+            }
+            (Action::TogglePreview, Some(WindowView::Main)) if editing => {
+                self.update_editor(None, EditorMessage::ToggleViewMode)
+            }
+            (Action::TogglePreview, Some(WindowView::Note(_))) => {
+                self.update_editor(Some(window), EditorMessage::ToggleViewMode)
+            }
+            (Action::FocusSearch, Some(WindowView::Main)) => {
+                self.state.screen = Screen::Notes;
+                text_input::focus(notes::notes::search_input())
+            }
+            (Action::OpenInWindow, Some(WindowView::Main)) => {
+                let note = match self.state.screen {
+                    Screen::Editor => self.state.tabs.current().and_then(Editor::editing_id),
+                    Screen::Notes => self
+                        .state
+                        .notes
+                        .selected()
+                        .and_then(|index| self.state.notes.get(index))
+                        .map(|note| note.id),
+                    _ => None,
+                };
+                match note {
+                    Some(note) => self.open_note_window(note, None),
+                    None => Task::none(),
+                }
+            }
+            (Action::Delete, Some(WindowView::Main)) if self.state.screen == Screen::Notes => {
+                // A stray key press must not take a page and its files with it.
+                self.state.deleting = self
+                    .state
+                    .notes
+                    .selected()
+                    .and_then(|index| self.state.notes.get(index))
+                    .map(|note| note.id);
+                Task::none()
+            }
+            (Action::NextTab | Action::PreviousTab, Some(WindowView::Main)) => {
+                if matches!(self.state.screen, Screen::Notes | Screen::Editor) {
+                    let notebook_shown = self.state.screen == Screen::Notes;
+                    if self.state.tabs.cycle(notebook_shown, action == Action::PreviousTab) {
+                        self.show_current_tab();
+                    } else {
+                        self.state.screen = Screen::Notes;
+                    }
+                }
+                Task::none()
+            }
+            _ => Task::none(),
+        }
+    }
+
+    /// Closes the prompt shown in the main window, or else leaves the current screen.
+    fn go_back(&mut self) -> Task<Message> {
+        if self.state.pending_open.take().is_some()
+            || self.state.missing_link.take().is_some()
+            || self.state.deleting.take().is_some()
+        {
+            return Task::none();
+        }
+
+        match self.state.screen {
+            Screen::Editor => self.update_editor(None, EditorMessage::BackPressed),
+            Screen::Import => self.update(Message::Import(ImportMessage::Cancel)),
+            Screen::Settings | Screen::Graph => {
+                self.state.screen = Screen::Notes;
+                Task::none()
+            }
+            Screen::Shortcuts => {
+                self.state.screen = Screen::Settings;
+                Task::none()
+            }
+            Screen::Notes => Task::none(),
+        }
+    }
+
+    /// Shows the tab in front, with its page selected in the notebook.
+    fn show_current_tab(&mut self) {
+        if let Some(editor) = self.state.tabs.current() {
+            if let Some(index) = editor.editing() {
+                self.state.notes.select(Some(index));
+            }
+            self.state.screen = Screen::Editor;
+        }
+    }
+
+    /// The editor of the note window `target`, or of the main window's tab in front for
+    /// `None`.
+    fn editor_mut(&mut self, target: Option<window::Id>) -> Option<&mut Editor> {
+        match target {
+            None => self.state.tabs.current_mut(),
+            Some(window) => self.state.note_editors.get_mut(&window),
+        }
+    }
+
+    fn update_editor(
+        &mut self,
+        target: Option<window::Id>,
+        message: EditorMessage,
+    ) -> Task<Message> {
+        let Some(event) = self.editor_mut(target).and_then(|editor| editor.update(message)) else {
+            return Task::none();
+        };
+
+        match event {
+            EditorEvent::Save {
+                title,
+                body,
+                tags,
+                attachments,
+                editing,
+            } => {
+                let mut note = Note::new(title, body, tags);
+                note.attachments = attachments;
+                if editing.is_none() {
+                    note.folder = self.state.notes.current_folder();
+                }
+
+                let saved_index = self.state.notes.upsert(note, editing);
+                let saved = self.state.notes.get(saved_index).cloned();
+                if let (Some(editor), Some(note)) = (self.editor_mut(target), &saved) {
+                    editor.reload(saved_index, note);
+                }
+                match (target, &saved) {
+                    (None, _) => {
+                        self.state.notes.select(Some(saved_index));
+                        self.state.screen = Screen::Notes;
+                    }
+                    (Some(window), Some(note)) => {
+                        self.state.windows.insert(window, WindowView::Note(note.id));
+                    }
+                    (Some(_), None) => {}
+                }
+                self.collect_attachments();
+
+                // Other editors showing the page follow the saved version.
+                let changed = saved.map(|note| vec![note.id]).unwrap_or_default();
+                self.sync_tabs(&changed, &[]);
+                self.sync_note_windows(&changed)
+            }
+            EditorEvent::Back => match target {
+                None => {
+                    // A blank new page is not worth keeping a tab for.
+                    let blank = self
+                        .state
+                        .tabs
+                        .current()
+                        .is_some_and(|editor| editor.editing().is_none() && !editor.is_modified());
+                    if blank {
+                        self.state.tabs.close_current();
+                    }
+                    self.state.screen = Screen::Notes;
+                    Task::none()
+                }
+                Some(window) => self.close_window_ids(vec![window]),
+            },
+            EditorEvent::FollowLink(link) => self.follow_link(&link, target),
+            EditorEvent::OpenExternal(external) => self.open_external(&external),
+            EditorEvent::AttachImage => Task::perform(
+                async {
+                    platform::platform::pick_files("Attach images", Some(attachments::IMAGE_FILTER))
+                        .map_err(|error| error.to_string())
+                },
+                move |picked| Message::ImagesPicked(target, picked),
+            ),
+            EditorEvent::AddAttachment => Task::perform(
+                async {
+                    platform::platform::pick_files("Attach files", None)
+                        .map_err(|error| error.to_string())
+                },
+                move |picked| Message::FilesPicked(target, picked),
+            ),
+            EditorEvent::ExportHtml(id) => {
+                let file_name = self
+                    .state
+                    .notes
+                    .find(id)
+                    .map(|note| format!("{}.html", export::files::slug(&note.title)))
+                    .unwrap_or_default();
+                Task::perform(
+                    async move {
+                        platform::platform::save_file("Export page", &file_name)
+                            .map_err(|error| error.to_string())
+                    },
+                    move |path| Message::ExportNoteTo(id, path),
+                )
+            }
+            EditorEvent::PasteImage => Task::perform(
+                async { platform::platform::clipboard_image().ok().flatten() },
+                move |image| Message::ClipboardImage(target, image),
+            ),
+        }
+    }
+
+    fn focus_main_window(&self) -> Task<Message> {
+        self.main_window()
+            .map(window::gain_focus)
+            .unwrap_or_else(Task::none)
+    }
+
+    fn main_window(&self) -> Option<window::Id> {
+        self.state
+            .windows
+            .iter()
+            .find(|(_, view)| **view == WindowView::Main)
+            .map(|(id, _)| *id)
+    }
 
-            let md_style = MdStyle::from_palette(Theme::KanagawaDragon.palette());
-            let preview = markdown::view(note.parsed(), MdSettings::default(), md_style)
-                .map(|_| Message::Notes(NotesMessage::LinkClicked));
+    /// Whether an editor holds changes, or the notebook failed to save some.
+    fn has_unsaved_changes(&self) -> bool {
+        self.state
+            .tabs
+            .iter()
+            .chain(self.state.note_editors.values())
+            .any(Editor::is_modified)
+            || self.state.notes.unsaved().is_some()
+    }
+
+    fn after_note_removed(&mut self, index: usize) {
+        self.state.tabs.adjust_after_delete(index);
+
+        let current = self.state.tabs.current().and_then(Editor::editing);
+        match current {
+            Some(current) if self.state.screen == Screen::Editor => {
+                self.state.notes.select(Some(current));
+            }
+            _ => {
+                self.state.notes.select(None);
+                if self.state.tabs.is_empty() {
+                    self.state.screen = Screen::Notes;
+                }
+            }
+        }
+    }
 
-            let content = column![
-                text(title).size(26),
-                scrollable(preview).height(Length::Fill),
-            ]
-            .spacing(12)
-            .padding(16);
+    /// Whether the open notebook can be replaced without losing work; if not, tells the
+    /// user what is in the way.
+    fn can_leave_notebook(&mut self) -> bool {
+        let modified = self
+            .state
+            .tabs
+            .iter()
+            .chain(self.state.note_editors.values())
+            .any(Editor::is_modified);
 
-            container(content).width(Length::Fill).height(Length::Fill).into()
+        if modified {
+            self.state
+                .toasts
+                .info("Save or close the pages with unsaved changes before switching notebooks.");
+            false
+        } else if self.state.notes.unsaved().is_some() {
+            self.state
+                .toasts
+                .error("This notebook could not be saved. Retry saving before switching.");
+            false
         } else {
-            container(text("Note not found").size(16)).padding(24).into()
+            true
+        }
+    }
+
+    /// Replaces the open notebook, closing windows that showed notes from the previous one.
+    fn switch_notebook(&mut self, mut notes: Notes) -> Task<Message> {
+        self.report_storage_errors();
+        notes.set_color_filter(self.state.settings.color_filter());
+        self.state.settings.set_last_notebook(notes.key());
+        self.state.notes = notes;
+        self.state.tabs = Tabs::default();
+        self.state.screen = Screen::Notes;
+
+        self.close_windows(|view| matches!(view, WindowView::Note(_)))
+    }
+
+    /// Copies the note at `index` into another notebook and removes it here once the
+    /// copy is safely on disk.
+    fn move_note(&mut self, index: usize, key: &str) -> Task<Message> {
+        let Some(note) = self.state.notes.get(index).cloned() else {
+            return Task::none();
+        };
+
+        let mut target = Notes::open(key);
+        let mut errors = target.take_errors();
+        if errors.is_empty() {
+            // Folders belong to a notebook; the page lands at the top of the other one.
+            let mut copy = note.clone();
+            copy.folder = None;
+            target.upsert(copy, None);
+            errors = target.take_errors();
+        }
+
+        if !errors.is_empty() {
+            for error in errors {
+                self.state.toasts.error(error.to_string());
+            }
+            return Task::none();
+        }
+
+        self.state.notes.remove(index);
+        self.after_note_removed(index);
+        self.state.toasts.info(format!("Moved to {}.", target.name()));
+
+        let moved = self.close_windows(|view| *view == WindowView::Note(note.id));
+        Task::batch([moved, self.sync_note_windows(&[])])
+    }
+
+    /// Drops tabs and windows that belonged to notes deleted in bulk.
+    fn forget_notes(&mut self, ids: &[NoteId]) -> Task<Message> {
+        let notes = &self.state.notes;
+        self.state.tabs.close_where(|editor| {
+            editor
+                .editing_id()
+                .is_some_and(|id| ids.contains(&id) || notes.position(id).is_none())
+        });
+        for editor in self.state.tabs.iter_mut() {
+            if let Some(index) = editor.editing_id().and_then(|id| notes.position(id)) {
+                editor.set_editing_index(index);
+            }
+        }
+        if self.state.tabs.is_empty() {
+            self.state.screen = Screen::Notes;
         }
+
+        let removed =
+            self.close_windows(|view| matches!(view, WindowView::Note(id) if ids.contains(id)));
+        Task::batch([removed, self.sync_note_windows(&[])])
+    }
+
+    fn close_windows(&mut self, matching: impl Fn(&WindowView) -> bool) -> Task<Message> {
+        let ids = self
+            .state
+            .windows
+            .iter()
+            .filter(|(_, view)| matching(view))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        self.close_window_ids(ids)
+    }
+
+    fn close_window_ids(&mut self, ids: Vec<window::Id>) -> Task<Message> {
+        Task::batch(ids.into_iter().map(|id| {
+            self.forget_window(id);
+            window::close(id)
+        }))
     }
+
+    /// Brings the note windows back in line with the notes after pages were saved,
+    /// deleted or rewritten elsewhere. Windows of deleted pages close, unless they hold
+    /// unsaved changes; saving those creates the page again.
+    fn sync_note_windows(&mut self, changed: &[NoteId]) -> Task<Message> {
+        let mut gone = Vec::new();
+        for (window, editor) in &mut self.state.note_editors {
+            let Some(id) = editor.editing_id() else {
+                continue;
+            };
+
+            match self.state.notes.position(id) {
+                Some(index) => {
+                    editor.set_editing_index(index);
+                    if changed.contains(&id)
+                        && let Some(note) = self.state.notes.get(index)
+                    {
+                        editor.note_changed_elsewhere(index, note);
+                    }
+                }
+                None if editor.is_modified() => {
+                    editor.note_removed_on_disk();
+                    self.state.toasts.info(
+                        "A page open in another window was deleted. Saving it will recreate it.",
+                    );
+                }
+                None => gone.push(*window),
+            }
+        }
+
+        self.close_window_ids(gone)
+    }
+
+    /// Brings the main window's tabs back in line with the notes after a page was saved
+    /// elsewhere or the file was changed by another program.
+    fn sync_tabs(&mut self, changed: &[NoteId], removed: &[NoteId]) {
+        for editor in self.state.tabs.iter_mut() {
+            let Some(id) = editor.editing_id() else {
+                continue;
+            };
+
+            match self.state.notes.position(id) {
+                Some(index) => {
+                    editor.set_editing_index(index);
+                    if changed.contains(&id)
+                        && let Some(note) = self.state.notes.get(index)
+                    {
+                        editor.note_changed_elsewhere(index, note);
+                    }
+                }
+                None if removed.contains(&id) => {
+                    editor.note_removed_on_disk();
+                    self.state.toasts.info(
+                        "A page open in a tab was deleted outside faitout. Saving it will \
+                         recreate it.",
+                    );
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Wraps a screen with the unsaved-changes banner and the toast overlay.
+    fn with_notifications<'a>(&'a self, screen: Element<'a, Message>) -> Element<'a, Message> {
+        let unsaved = self
+            .state
+            .notes
+            .unsaved()
+            .or(self.state.settings.unsaved());
+
+        let mut content = column![];
+        if let Some(title) = &self.state.missing_link {
+            let prompt = container(
+                row![
+                    text(format!("There is no page called \"{title}\" yet.")).size(14),
+                    horizontal_space(),
+                    button(text("Create it").size(14)).on_press(Message::CreateLinkedPage),
+                    button(text("Dismiss").size(14)).on_press(Message::DismissLinkPrompt),
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center),
+            )
+            .width(Length::Fill)
+            .padding([8, 16])
+            .style(container::rounded_box);
+            content = content.push(prompt);
+        }
+        if let Some(target) = &self.state.pending_open {
+            let prompt = container(
+                row![
+                    text(format!("Open {target}?")).size(14),
+                    horizontal_space(),
+                    button(text("Open").size(14)).on_press(Message::ConfirmOpenLink),
+                    button(text("Cancel").size(14)).on_press(Message::CancelOpenLink),
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center),
+            )
+            .width(Length::Fill)
+            .padding([8, 16])
+            .style(container::rounded_box);
+            content = content.push(prompt);
+        }
+        if let Some(note) = self.state.deleting.and_then(|id| self.state.notes.find(id)) {
+            let title = note.display_title();
+            let prompt = container(
+                row![
+                    text(format!("Delete \"{title}\" and its attached files?")).size(14),
+                    horizontal_space(),
+                    button(text("Delete").size(14))
+                        .style(button::danger)
+                        .on_press(Message::DeleteConfirmed),
+                    button(text("Keep it").size(14)).on_press(Message::DeleteCancelled),
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center),
+            )
+            .width(Length::Fill)
+            .padding([8, 16])
+            .style(container::rounded_box);
+            content = content.push(prompt);
+        }
+        if self.state.quitting {
+            let prompt = container(
+                row![
+                    text("Some changes are not saved yet.").size(14),
+                    horizontal_space(),
+                    button(text("Quit without saving").size(14))
+                        .style(button::danger)
+                        .on_press(Message::QuitConfirmed),
+                    button(text("Keep editing").size(14)).on_press(Message::QuitCancelled),
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center),
+            )
+            .width(Length::Fill)
+            .padding([8, 16])
+            .style(container::rounded_box);
+            content = content.push(prompt);
+        }
+        if let Some(error) = unsaved {
+            let banner = container(
+                row![
+                    text(format!("Unsaved changes. {error}")).size(14),
+                    horizontal_space(),
+                    button(text("Retry").size(14)).on_press(Message::RetrySave),
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center),
+            )
+            .width(Length::Fill)
+            .padding([8, 16])
+            .style(unsaved_banner_style);
+            content = content.push(banner);
+        }
+        content = content.push(screen);
+
+        stack![content, self.state.toasts.view().map(Message::Toast)].into()
+    }
+
+    fn note_window_view(&self, window: window::Id) -> Element<'_, Message> {
+        let Some(editor) = self.state.note_editors.get(&window) else {
+            return container(text("Note not found").size(16)).padding(24).into();
+        };
+
+        let backlinks = editor
+            .editing_id()
+            .map(|id| self.state.notes.backlinks(id))
+            .unwrap_or_default();
+        let view = editor
+            .view(backlinks)
+            .map(move |message| Message::NoteWindow(window, message));
+        self.with_shared_edit_warning(Some(window), view)
+    }
+
+    /// Warns above the editor of `target` when another window has unsaved changes to
+    /// the same page, since only one of the two versions can be kept.
+    fn with_shared_edit_warning<'a>(
+        &'a self,
+        target: Option<window::Id>,
+        editor: Element<'a, Message>,
+    ) -> Element<'a, Message> {
+        let current = match target {
+            None => self.state.tabs.current(),
+            Some(window) => self.state.note_editors.get(&window),
+        };
+        let Some(current) = current else {
+            return editor;
+        };
+        let editing = current.editing_id();
+        let shared = editing.is_some()
+            && self
+                .state
+                .tabs
+                .iter()
+                .chain(self.state.note_editors.values())
+                .any(|other| {
+                    !std::ptr::eq(other, current)
+                        && other.editing_id() == editing
+                        && other.is_modified()
+                });
+
+        if !shared {
+            return editor;
+        }
+
+        let warning = container(
+            text(
+                "This page has unsaved changes in another window. Whichever is saved second \
+                 will ask which version to keep.",
+            )
+            .size(14),
+        )
+        .width(Length::Fill)
+        .padding([8, 16])
+        .style(container::rounded_box);
+        column![warning, editor].into()
+    }
+}
+
+/// Deletes stored attachments that no note in any notebook, nor the `unsaved` texts,
+/// refer to. Nothing is deleted unless every notebook could be read.
+fn collect_attachments(notes: &Notes, unsaved: impl IntoIterator<Item = String>) {
+    let mut texts = Vec::new();
+    for notebook in notebook::discover() {
+        match notebook::contents(&notebook.key) {
+            Ok(found) => texts.extend(found),
+            Err(_) => return,
+        }
+    }
+    for note in notes.iter() {
+        texts.push(note.body.clone());
+        texts.extend(note.attachments.iter().map(|attachment| attachment.file.clone()));
+    }
+    texts.extend(unsaved);
+
+    attachments::collect_garbage(texts.iter().map(String::as_str));
+}
+
+/// What to hand to the system for a link found in a note: web and mail links as they
+/// are, `file://` URLs and relative paths as files that must exist under `data_dir`.
+/// The flag tells whether it is a file, which is opened only once the user confirms.
+fn resolve_external(target: &str, data_dir: &Path) -> Result<(String, bool), String> {
+    let target = target.trim();
+
+    // Single-letter schemes are Windows drive letters, not URLs.
+    let path = match markdown::Url::parse(target) {
+        Ok(url) if url.scheme().len() > 1 => match url.scheme() {
+            "http" | "https" | "mailto" => return Ok((url.to_string(), false)),
+            "file" => url
+                .to_file_path()
+                .map_err(|_| format!("{target} is not a valid file link."))?,
+            scheme => return Err(format!("Links of type \"{scheme}:\" cannot be opened.")),
+        },
+        _ => {
+            let relative = target.split(['#', '?']).next().unwrap_or_default();
+            data_dir.join(links::percent_decode(relative))
+        }
+    };
+
+    let path = path
+        .canonicalize()
+        .map_err(|_| format!("{} does not exist.", path.display()))?;
+    // Links may come from imported files or scripts; they must not reach the rest of
+    // the disk, where opening a file can run it.
+    let root = data_dir
+        .canonicalize()
+        .map_err(|error| format!("Could not find the data directory: {error}"))?;
+    if !path.starts_with(&root) {
+        return Err(format!("{} is outside the notebook's folder.", path.display()));
+    }
+    Ok((path.display().to_string(), true))
+}
+
+fn unsaved_banner_style(theme: &Theme) -> container::Style {
+    let danger = theme.extended_palette().danger.weak;
+    container::Style::default()
+        .background(danger.color)
+        .color(danger.text)
+}
+
+fn window_settings(geometry: Option<WindowGeometry>) -> window::Settings {
+    let settings = window::Settings {
+        icon: load_app_icon(),
+        ..Default::default()
+    };
+    placed(settings, geometry)
+}
+
+/// `settings` with the size and position of `geometry`, as far as they are sane.
+/// Saved geometry of windows that are being opened at a saved position, until iced asks
+/// [`on_monitor`] where to put them.
+static PENDING_PLACEMENTS: Mutex<Vec<WindowGeometry>> = Mutex::new(Vec::new());
+
+fn placed(mut settings: window::Settings, geometry: Option<WindowGeometry>) -> window::Settings {
+    if let Some(geometry) = geometry.and_then(|geometry| geometry.sanitized()) {
+        settings.size = geometry.size();
+        if geometry.position().is_some() {
+            PENDING_PLACEMENTS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(geometry);
+            settings.position = window::Position::SpecificWith(on_monitor);
+        }
+    }
+    settings
+}
+
+/// Places a window opened by [`placed`] on the monitor it opens on. iced only passes the
+/// window and monitor sizes, so the saved geometry is found by the window's size.
+fn on_monitor(size: iced::Size, monitor: iced::Size) -> iced::Point {
+    let mut pending = PENDING_PLACEMENTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let geometry = pending
+        .iter()
+        .position(|geometry| geometry.size() == size)
+        .map(|index| pending.remove(index));
+
+    geometry
+        .and_then(|geometry| geometry.position_on(monitor))
+        .unwrap_or_else(|| {
+            iced::Point::new(
+                ((monitor.width - size.width) / 2.0).max(0.0),
+                ((monitor.height - size.height) / 2.0).max(0.0),
+            )
+        })
 }
 
 fn load_app_icon() -> Option<window::Icon> {
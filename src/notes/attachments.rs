@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::platform::platform::FileFilter;
+use crate::storage::storage::{self, StorageError};
+
+/// Directory, next to the data files, that attached files are copied into. Notes refer
+/// to them by this relative path.
+pub const DIR: &str = "attachments";
+
+/// Files stored more recently than this are never collected: an import running in
+/// another process stores its files before it writes the pages that refer to them.
+const GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "bmp", "webp"];
+
+pub const IMAGE_FILTER: FileFilter = FileFilter {
+    name: "Images",
+    extensions: &IMAGE_EXTENSIONS,
+};
+
+/// A file attached to a note.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Where the copy lives, relative to the data directory.
+    pub file: String,
+    /// The file name it was attached under.
+    pub name: String,
+    pub size: u64,
+    /// SHA-1 of the contents, which is also the stored file's name.
+    pub hash: String,
+}
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Copies `source` into the attachments directory.
+pub fn store(source: &Path) -> Result<Attachment, StorageError> {
+    let bytes = fs::read(source).map_err(|error| StorageError::Read {
+        path: source.to_path_buf(),
+        reason: error.to_string(),
+    })?;
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    store_bytes(&bytes, &name)
+}
+
+/// Writes `bytes`, attached under the file name `name`, into the attachments directory.
+/// Stored files are named after their content, so the same file is only stored once.
+pub fn store_bytes(bytes: &[u8], name: &str) -> Result<Attachment, StorageError> {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_lowercase();
+    let hash = content_hash(bytes);
+    let file = match extension.as_str() {
+        "" => format!("{DIR}/{hash}"),
+        extension => format!("{DIR}/{hash}.{extension}"),
+    };
+    let attachment = Attachment {
+        file,
+        name: name.to_string(),
+        size: bytes.len() as u64,
+        hash,
+    };
+
+    let target = storage::data_dir().join(&attachment.file);
+    if target.exists() {
+        // Stored again, so it starts a new grace period before it can be collected.
+        let _ = fs::File::options()
+            .append(true)
+            .open(&target)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        return Ok(attachment);
+    }
+
+    let write_error = |error: std::io::Error| StorageError::Write {
+        path: target.clone(),
+        reason: error.to_string(),
+    };
+    fs::create_dir_all(storage::data_dir().join(DIR)).map_err(write_error)?;
+    fs::write(&target, bytes).map_err(write_error)?;
+
+    Ok(attachment)
+}
+
+/// The markdown that embeds the stored image at `relative`.
+pub fn image_markdown(relative: &str, alt: &str) -> String {
+    let alt = alt.replace(['[', ']'], "");
+    format!("![{alt}]({relative})")
+}
+
+/// File names inside the attachments directory that `body` refers to.
+pub fn references(body: &str) -> impl Iterator<Item = &str> {
+    let prefix = format!("{DIR}/");
+    body.match_indices(DIR).filter_map(move |(start, _)| {
+        let rest = body[start..].strip_prefix(prefix.as_str())?;
+        let end = rest
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '-' || ch == '_'))
+            .unwrap_or(rest.len());
+        (end > 0).then(|| &rest[..end])
+    })
+}
+
+/// Deletes stored files that none of `bodies` refers to, unless they were stored within
+/// the last [`GRACE_PERIOD`]. Returns the deleted paths.
+pub fn collect_garbage<'a>(bodies: impl IntoIterator<Item = &'a str>) -> Vec<PathBuf> {
+    let referenced = bodies
+        .into_iter()
+        .flat_map(references)
+        .collect::<HashSet<_>>();
+
+    let Ok(entries) = fs::read_dir(storage::data_dir().join(DIR)) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| {
+                    modified
+                        .elapsed()
+                        .is_ok_and(|elapsed| elapsed >= GRACE_PERIOD)
+                })
+        })
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| !referenced.contains(name))
+        })
+        .filter(|path| fs::remove_file(path).is_ok())
+        .collect()
+}
+
+/// `size` in the largest unit that keeps it above one, e.g. `1.4 MB`.
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if size < 1024 {
+        return format!("{size} B");
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
+
+fn content_hash(bytes: &[u8]) -> String {
+    Sha1::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recently_stored_files_are_not_collected() {
+        let _data = storage::use_test_data_dir();
+        let fresh = store_bytes(b"stored by an import still running", "fresh.png").unwrap();
+        let old = store_bytes(b"left over from a deleted page", "old.png").unwrap();
+        let path = |attachment: &Attachment| storage::data_dir().join(&attachment.file);
+
+        let long_ago = SystemTime::now() - GRACE_PERIOD * 2;
+        let file = fs::File::options().append(true).open(path(&old)).unwrap();
+        file.set_modified(long_ago).unwrap();
+
+        let deleted = collect_garbage([]);
+        assert!(deleted.contains(&path(&old)));
+        assert!(!deleted.contains(&path(&fresh)));
+        assert!(path(&fresh).is_file() && !path(&old).exists());
+
+        // Storing a file again starts its grace period over.
+        let again = store_bytes(b"stored by an import still running", "again.png").unwrap();
+        let file = fs::File::options().append(true).open(path(&again)).unwrap();
+        file.set_modified(long_ago).unwrap();
+        store_bytes(b"stored by an import still running", "again.png").unwrap();
+        collect_garbage([]);
+        assert!(path(&again).is_file());
+    }
+}
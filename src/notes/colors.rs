@@ -0,0 +1,123 @@
+use iced::Color;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_NAME: &str = "Default";
+
+const BUILT_IN: [(&str, [u8; 3]); 5] = [
+    ("Cherry", [0xf5, 0x6a, 0x6a]),
+    ("Emerald", [0x5b, 0xc0, 0x7a]),
+    ("Ocean", [0x4a, 0x90, 0xe2]),
+    ("Amber", [0xf1, 0xc4, 0x0f]),
+    ("Violet", [0xb4, 0x79, 0xe6]),
+];
+
+/// The color label of a note, stored by name.
+///
+/// Names are the built-in presets (which is how older files spelled them) or colors
+/// the user defined in settings. A name that resolves to nothing renders as Default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NoteColor(String);
+
+impl NoteColor {
+    pub fn named(name: impl Into<String>) -> Self {
+        NoteColor(name.into())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for NoteColor {
+    fn default() -> Self {
+        NoteColor::named(DEFAULT_NAME)
+    }
+}
+
+/// A color defined by the user in settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomColor {
+    pub name: String,
+    /// `#rrggbb`
+    pub hex: String,
+}
+
+impl CustomColor {
+    pub fn rgb(&self) -> Option<[u8; 3]> {
+        parse_hex(&self.hex)
+    }
+}
+
+/// Parses `#rrggbb` or `rrggbb`.
+pub fn parse_hex(hex: &str) -> Option<[u8; 3]> {
+    let digits = hex.trim().trim_start_matches('#');
+    if digits.len() != 6 || !digits.is_ascii() {
+        return None;
+    }
+
+    let channel = |range: std::ops::Range<usize>| u8::from_str_radix(&digits[range], 16).ok();
+    Some([channel(0..2)?, channel(2..4)?, channel(4..6)?])
+}
+
+pub fn to_hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Every color a note can be labelled with: Default, the presets, then custom colors.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    entries: Vec<(NoteColor, Option<Color>)>,
+}
+
+impl Palette {
+    pub fn new(custom: &[CustomColor]) -> Self {
+        let mut entries = vec![(NoteColor::default(), None)];
+        entries.extend(BUILT_IN.iter().map(|(name, [r, g, b])| {
+            (NoteColor::named(*name), Some(Color::from_rgb8(*r, *g, *b)))
+        }));
+
+        for color in custom {
+            let name = NoteColor::named(color.name.as_str());
+            if entries.iter().any(|(existing, _)| *existing == name) {
+                continue;
+            }
+            if let Some([r, g, b]) = color.rgb() {
+                entries.push((name, Some(Color::from_rgb8(r, g, b))));
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// The color to paint for `color`, or `None` for Default and unknown names.
+    pub fn swatch(&self, color: &NoteColor) -> Option<Color> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name == color)
+            .and_then(|(_, swatch)| *swatch)
+    }
+
+    /// `color` itself if the palette knows it, Default otherwise.
+    pub fn resolve(&self, color: &NoteColor) -> NoteColor {
+        if self.entries.iter().any(|(name, _)| name == color) {
+            color.clone()
+        } else {
+            NoteColor::default()
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&NoteColor, Option<Color>)> {
+        self.entries.iter().map(|(name, swatch)| (name, *swatch))
+    }
+
+    pub fn is_built_in(name: &str) -> bool {
+        name == DEFAULT_NAME || BUILT_IN.iter().any(|(built_in, _)| *built_in == name)
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+use super::notes::unique_id;
+
+/// Stable identity of a folder inside a notebook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FolderId(u64);
+
+impl FolderId {
+    pub fn generate() -> Self {
+        FolderId(unique_id())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Folder {
+    pub id: FolderId,
+    pub name: String,
+    #[serde(default)]
+    pub parent: Option<FolderId>,
+}
+
+/// A place a folder can be moved to; `None` is the top level of the notebook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderChoice {
+    pub id: Option<FolderId>,
+    pub label: String,
+}
+
+impl fmt::Display for FolderChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+pub fn children(folders: &[Folder], parent: Option<FolderId>) -> impl Iterator<Item = &Folder> {
+    let mut children = folders
+        .iter()
+        .filter(|folder| folder.parent == parent)
+        .collect::<Vec<_>>();
+    children.sort_by_key(|folder| folder.name.to_lowercase());
+    children.into_iter()
+}
+
+/// `root` and every folder nested below it.
+pub fn subtree(folders: &[Folder], root: FolderId) -> HashSet<FolderId> {
+    let mut found = HashSet::from([root]);
+    let mut pending = vec![root];
+
+    while let Some(parent) = pending.pop() {
+        for child in folders
+            .iter()
+            .filter(|folder| folder.parent == Some(parent))
+        {
+            if found.insert(child.id) {
+                pending.push(child.id);
+            }
+        }
+    }
+
+    found
+}
+
+/// Folders in display order, each with its nesting depth, skipping the contents of
+/// folders for which `expanded` is false.
+pub fn flatten(folders: &[Folder], expanded: impl Fn(FolderId) -> bool) -> Vec<(usize, &Folder)> {
+    fn visit<'a>(
+        folders: &'a [Folder],
+        parent: Option<FolderId>,
+        depth: usize,
+        expanded: &dyn Fn(FolderId) -> bool,
+        out: &mut Vec<(usize, &'a Folder)>,
+    ) {
+        for folder in children(folders, parent) {
+            out.push((depth, folder));
+            if expanded(folder.id) {
+                visit(folders, Some(folder.id), depth + 1, expanded, out);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    visit(folders, None, 0, &expanded, &mut out);
+    out
+}
+
+/// The folder with `id` and its ancestors, outermost first.
+pub fn lineage(folders: &[Folder], id: FolderId) -> Vec<&Folder> {
+    let mut lineage = Vec::new();
+    let mut current = Some(id);
+
+    while let Some(folder) = current.and_then(|id| folders.iter().find(|folder| folder.id == id)) {
+        lineage.push(folder);
+        current = folder.parent;
+        if lineage.len() > folders.len() {
+            break;
+        }
+    }
+
+    lineage.reverse();
+    lineage
+}
+
+/// Slash-separated path of a folder, e.g. `Work / Meetings`.
+pub fn path(folders: &[Folder], id: FolderId) -> String {
+    lineage(folders, id)
+        .iter()
+        .map(|folder| folder.name.as_str())
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// Where `moving` may be placed: the top level or any folder outside its own subtree.
+pub fn move_targets(folders: &[Folder], moving: FolderId) -> Vec<FolderChoice> {
+    let excluded = subtree(folders, moving);
+
+    let mut choices = vec![FolderChoice {
+        id: None,
+        label: String::from("Top level"),
+    }];
+    choices.extend(
+        flatten(folders, |_| true)
+            .into_iter()
+            .filter(|(_, folder)| !excluded.contains(&folder.id))
+            .map(|(_, folder)| FolderChoice {
+                id: Some(folder.id),
+                label: path(folders, folder.id),
+            }),
+    );
+    choices
+}
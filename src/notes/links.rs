@@ -0,0 +1,353 @@
+use iced::widget::markdown::Url;
+use iced::widget::{button, column, text};
+use iced::{Element, Length};
+use pulldown_cmark::{Event as MdEvent, LinkType, Parser, Tag};
+use std::collections::BTreeMap;
+
+use super::notes::{self, NoteId};
+
+/// The markdown preview only makes http(s) links clickable, so wiki links are encoded
+/// under a reserved host that can never resolve on the network.
+const HOST: &str = "faitout.invalid";
+
+/// Where a clicked `[[wiki link]]` points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    /// The title as written between the brackets.
+    pub title: String,
+    /// The note the link was resolved to when its source was last saved.
+    pub id: Option<NoteId>,
+}
+
+/// A note that links to the one being viewed.
+#[derive(Debug, Clone)]
+pub struct Backlink {
+    pub source: NoteId,
+    pub title: String,
+    /// The line of the source note that holds the link.
+    pub snippet: String,
+}
+
+const SNIPPET_LENGTH: usize = 140;
+
+/// The first line of `body` whose wiki links lead to `target` according to `resolved`.
+pub fn snippet(body: &str, resolved: &BTreeMap<String, NoteId>, target: NoteId) -> String {
+    let line = body
+        .lines()
+        .find(|line| {
+            targets(line)
+                .iter()
+                .any(|title| resolved.get(&normalize(title)) == Some(&target))
+        })
+        .unwrap_or_default()
+        .trim();
+
+    match line.char_indices().nth(SNIPPET_LENGTH) {
+        Some((cut, _)) => format!("{}...", &line[..cut]),
+        None => line.to_string(),
+    }
+}
+
+/// The "Linked from" list shown under a note's preview.
+pub fn backlinks_view<'a, Message: Clone + 'a>(
+    backlinks: &'a [Backlink],
+    on_open: impl Fn(WikiLink) -> Message,
+) -> Element<'a, Message> {
+    let heading = text(format!("Linked from ({})", backlinks.len())).size(18);
+
+    if backlinks.is_empty() {
+        return column![heading, text("No other page links here yet.").size(14)]
+            .spacing(8)
+            .into();
+    }
+
+    backlinks
+        .iter()
+        .fold(column![heading].spacing(8), |list, backlink| {
+            let title = notes::display_title(&backlink.title);
+
+            let entry = column![
+                text(title).size(16),
+                text(backlink.snippet.as_str()).size(13),
+            ]
+            .spacing(2);
+
+            list.push(
+                button(entry)
+                    .width(Length::Fill)
+                    .style(button::text)
+                    .on_press(on_open(WikiLink {
+                        title: backlink.title.clone(),
+                        id: Some(backlink.source),
+                    })),
+            )
+        })
+        .into()
+}
+
+/// Titles of every `[[wiki link]]` in `body`, in order of appearance.
+pub fn targets(body: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    scan(body, |link| {
+        found.push(link.target.to_string());
+        None
+    });
+    found
+}
+
+/// Rewrites `[[Title]]` and `[[Title|label]]` into markdown links the preview can
+/// render. Links resolved in `resolved` carry the target id so they keep working
+/// after the target is renamed. Other links the preview would not make clickable,
+/// such as `mailto:` or relative paths, are routed through [`HOST`] as well.
+pub fn to_markdown(body: &str, resolved: &BTreeMap<String, NoteId>) -> String {
+    let body = scan(body, |link| {
+        let label = escape_label(link.label.unwrap_or(link.target));
+        let url = url_for(link.target, resolved.get(&normalize(link.target)).copied());
+        Some(format!("[{label}](<{url}>)"))
+    });
+    route_links(&body)
+}
+
+/// Rewrites wiki links into markdown links to wherever `href` says the target lives.
+/// Links `href` has no destination for are reduced to their label.
+pub fn to_markdown_with(
+    body: &str,
+    resolved: &BTreeMap<String, NoteId>,
+    href: impl Fn(Option<NoteId>) -> Option<String>,
+) -> String {
+    scan(body, |link| {
+        let label = escape_label(link.label.unwrap_or(link.target));
+        match href(resolved.get(&normalize(link.target)).copied()) {
+            Some(url) => Some(format!("[{label}](<{url}>)")),
+            None => Some(label),
+        }
+    })
+}
+
+/// Replaces links to `old` with links to `new`, keeping custom labels.
+pub fn retitle(body: &str, old: &str, new: &str) -> String {
+    let old = normalize(old);
+    scan(body, |link| {
+        (normalize(link.target) == old).then(|| match link.label {
+            Some(label) => format!("[[{new}|{label}]]"),
+            None => format!("[[{new}]]"),
+        })
+    })
+}
+
+/// The key link targets are compared with: trimmed and case-insensitive.
+pub fn normalize(title: &str) -> String {
+    title.trim().to_lowercase()
+}
+
+/// Recognises URLs produced by [`to_markdown`].
+pub fn parse(url: &Url) -> Option<WikiLink> {
+    if url.scheme() != "https" || url.host_str() != Some(HOST) {
+        return None;
+    }
+
+    let mut segments = url.path_segments()?;
+    if segments.next()? != "link" {
+        return None;
+    }
+    let title = segments.next()?;
+    let title = percent_decode(title);
+    let id = url
+        .query_pairs()
+        .find(|(key, _)| key == "id")
+        .and_then(|(_, value)| value.parse::<u64>().ok())
+        .map(NoteId::from_raw);
+
+    Some(WikiLink { title, id })
+}
+
+/// The original destination of a clicked link that is not a wiki link.
+pub fn external(url: &Url) -> Option<String> {
+    if url.host_str() != Some(HOST) {
+        return Some(url.to_string());
+    }
+    if url.path() != "/open" {
+        return None;
+    }
+
+    url.query_pairs()
+        .find(|(key, _)| key == "target")
+        .map(|(_, value)| value.into_owned())
+}
+
+fn url_for(title: &str, id: Option<NoteId>) -> String {
+    let mut url = Url::parse(&format!("https://{HOST}/")).expect("static URL is valid");
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.clear().push("link").push(title.trim());
+    }
+    if let Some(id) = id {
+        url.query_pairs_mut()
+            .append_pair("id", &id.raw().to_string());
+    }
+    url.to_string()
+}
+
+fn open_url_for(target: &str) -> String {
+    let mut url = Url::parse(&format!("https://{HOST}/open")).expect("static URL is valid");
+    url.query_pairs_mut().append_pair("target", target);
+    url.to_string()
+}
+
+fn is_web(destination: &str) -> bool {
+    Url::parse(destination).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Points inline links and autolinks that are not http(s) at [`open_url_for`].
+fn route_links(body: &str) -> String {
+    let mut replacements = Vec::new();
+
+    for (event, range) in Parser::new(body).into_offset_iter() {
+        let MdEvent::Start(Tag::Link {
+            link_type,
+            dest_url,
+            ..
+        }) = event
+        else {
+            continue;
+        };
+        if dest_url.is_empty() || is_web(&dest_url) {
+            continue;
+        }
+
+        let source = &body[range.clone()];
+        match link_type {
+            LinkType::Inline => {
+                let Some(open) = source.rfind("](") else {
+                    continue;
+                };
+                let start = range.start + open + 2;
+                let rest = &body[start..range.end];
+                let skipped = rest.len() - rest.trim_start().len();
+                let rest = rest.trim_start();
+                let length = if rest.starts_with('<') {
+                    rest.find('>').map(|end| end + 1)
+                } else {
+                    rest.find(|ch: char| ch.is_whitespace() || ch == ')')
+                };
+                if let Some(length) = length {
+                    let start = start + skipped;
+                    let url = open_url_for(&dest_url);
+                    replacements.push((start..start + length, format!("<{url}>")));
+                }
+            }
+            LinkType::Autolink | LinkType::Email => {
+                let target = if link_type == LinkType::Email {
+                    format!("mailto:{dest_url}")
+                } else {
+                    dest_url.to_string()
+                };
+                let label = escape_label(&dest_url);
+                let url = open_url_for(&target);
+                replacements.push((range, format!("[{label}](<{url}>)")));
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = body.to_string();
+    for (range, replacement) in replacements.into_iter().rev() {
+        out.replace_range(range, &replacement);
+    }
+    out
+}
+
+fn escape_label(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+pub fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let escaped = (byte == b'%')
+            .then(|| segment.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(value) => {
+                decoded.push(value);
+                index += 3;
+            }
+            None => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+struct RawLink<'a> {
+    target: &'a str,
+    label: Option<&'a str>,
+}
+
+/// Walks `body` outside of code, handing each `[[link]]` to `replace`. Links for which
+/// `replace` returns `Some` are substituted in the returned text.
+fn scan(body: &str, mut replace: impl FnMut(RawLink<'_>) -> Option<String>) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut in_fence = false;
+
+    for line in body.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            out.push_str(line);
+            continue;
+        }
+        if in_fence {
+            out.push_str(line);
+            continue;
+        }
+
+        let mut rest = line;
+        let mut in_code = false;
+        while !rest.is_empty() {
+            if rest.starts_with('`') {
+                in_code = !in_code;
+                out.push('`');
+                rest = &rest[1..];
+                continue;
+            }
+
+            if !in_code
+                && rest.starts_with("[[")
+                && let Some(end) = rest[2..].find("]]")
+            {
+                let inner = &rest[2..2 + end];
+                let (target, label) = match inner.split_once('|') {
+                    Some((target, label)) => (target, Some(label)),
+                    None => (inner, None),
+                };
+
+                if !target.trim().is_empty() && !inner.contains('\n') {
+                    let original = &rest[..end + 4];
+                    match replace(RawLink { target, label }) {
+                        Some(replacement) => out.push_str(&replacement),
+                        None => out.push_str(original),
+                    }
+                    rest = &rest[end + 4..];
+                    continue;
+                }
+            }
+
+            let next = rest.chars().next().map_or(1, char::len_utf8);
+            out.push_str(&rest[..next]);
+            rest = &rest[next..];
+        }
+    }
+
+    out
+}
@@ -1 +1,7 @@
+pub mod attachments;
+pub mod colors;
+pub mod folders;
+pub mod links;
+pub mod notebook;
 pub mod notes;
+pub mod preview;
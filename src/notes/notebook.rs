@@ -0,0 +1,154 @@
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+use super::attachments::Attachment;
+use crate::storage::storage::{self, StorageError};
+
+/// Key of the notebook stored in `notes.json`, which predates multiple notebooks.
+pub const DEFAULT_KEY: &str = "default";
+const DEFAULT_NAME: &str = "Notebook";
+const NOTEBOOKS_DIR: &str = "notebooks";
+
+/// A notebook known on disk: `key` names its file, `name` is what the user sees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotebookRef {
+    pub key: String,
+    pub name: String,
+}
+
+impl fmt::Display for NotebookRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Only the header of a notebook file, so discovery does not rebuild every note.
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default)]
+    name: String,
+}
+
+/// Only what the notes of a notebook file refer to: their bodies and attachments.
+#[derive(Deserialize)]
+struct Contents {
+    #[serde(default)]
+    entries: Vec<Content>,
+}
+
+#[derive(Deserialize)]
+struct Content {
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+}
+
+pub fn path_for(key: &str) -> PathBuf {
+    if key == DEFAULT_KEY {
+        storage::data_dir().join("notes.json")
+    } else {
+        storage::data_dir()
+            .join(NOTEBOOKS_DIR)
+            .join(format!("{key}.json"))
+    }
+}
+
+pub fn display_name(key: &str, name: &str) -> String {
+    match name.trim() {
+        "" if key == DEFAULT_KEY => DEFAULT_NAME.to_string(),
+        "" => key.to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// Lists the default notebook followed by every notebook file, sorted by name.
+pub fn discover() -> Vec<NotebookRef> {
+    let mut found = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(storage::data_dir().join(NOTEBOOKS_DIR)) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(key) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if key == DEFAULT_KEY {
+                continue;
+            }
+
+            let name = storage::load_json::<Header>(&path)
+                .ok()
+                .flatten()
+                .map(|header| header.name)
+                .unwrap_or_default();
+
+            found.push(NotebookRef {
+                key: key.to_string(),
+                name: display_name(key, &name),
+            });
+        }
+    }
+
+    found.sort_by_key(|notebook| notebook.name.to_lowercase());
+    found.insert(
+        0,
+        NotebookRef {
+            key: DEFAULT_KEY.to_string(),
+            name: DEFAULT_NAME.to_string(),
+        },
+    );
+    found
+}
+
+/// Turns a notebook name into a file key that is not used by any existing notebook.
+pub fn unused_key(name: &str, existing: &[NotebookRef]) -> String {
+    let mut slug = String::new();
+    for ch in name.trim().chars() {
+        if ch.is_alphanumeric() {
+            slug.extend(ch.to_lowercase());
+        } else if !slug.ends_with('-') && !slug.is_empty() {
+            slug.push('-');
+        }
+    }
+    let slug = match slug.trim_end_matches('-') {
+        "" => "notebook".to_string(),
+        slug => slug.to_string(),
+    };
+
+    let taken =
+        |key: &str| key == DEFAULT_KEY || existing.iter().any(|notebook| notebook.key == key);
+    if !taken(&slug) {
+        return slug;
+    }
+
+    (2..)
+        .map(|suffix| format!("{slug}-{suffix}"))
+        .find(|key| !taken(key))
+        .expect("an unused suffix always exists")
+}
+
+/// The body of every note in the notebook stored under `key`, followed by the stored
+/// path of each of its attachments.
+pub fn contents(key: &str) -> Result<Vec<String>, StorageError> {
+    let contents = storage::load_json::<Contents>(&path_for(key))?;
+    Ok(contents
+        .map(|contents| {
+            contents
+                .entries
+                .into_iter()
+                .flat_map(|entry| {
+                    let files = entry
+                        .attachments
+                        .into_iter()
+                        .map(|attachment| attachment.file);
+                    std::iter::once(entry.body).chain(files)
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
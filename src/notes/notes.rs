@@ -1,85 +1,158 @@
 use iced::widget::button::Status as ButtonStatus;
-use iced::widget::markdown::{self, Item, Settings, Style};
+use iced::widget::markdown;
 use iced::widget::{
-    button, column, container, horizontal_space, mouse_area, row, scrollable, text, text_input,
-    vertical_space,
+    button, checkbox, column, container, horizontal_space, mouse_area, pick_list, row, scrollable,
+    text, text_input, vertical_space,
 };
 use iced::{Color, Element, Length, Theme, alignment::Alignment};
 use iced::{Shadow, border};
+use iced::{Subscription, time};
 use serde::{Serialize, Deserialize};
-use std::fs::{self, File};
-use std::io::{self, BufReader, BufWriter};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
 use std::path::PathBuf;
-use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::attachments::Attachment;
+use super::colors::{NoteColor, Palette};
+use super::folders::{self, Folder, FolderChoice, FolderId};
+use super::links::{self, Backlink, WikiLink};
+use super::notebook::{self, NotebookRef};
+use super::preview::{self, Block};
+use crate::export::export::ExportFormat;
+use crate::import::import::ImportSource;
+use crate::storage::storage::{self, StorageError};
 
 const DOUBLE_CLICK_WINDOW: Duration = Duration::from_millis(300);
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stable identity of a note, independent of its position in the list.
+///
+/// Ids are microsecond timestamps so they stay exact when the file is handled by
+/// tools that read JSON numbers as doubles. Zero means "not assigned yet".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NoteId(u64);
+
+impl NoteId {
+    pub fn generate() -> Self {
+        NoteId(unique_id())
+    }
+
+    pub fn from_raw(raw: u64) -> Self {
+        NoteId(raw)
+    }
+
+    pub fn raw(self) -> u64 {
+        self.0
+    }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub enum NoteColor {
-    Default,
-    Cherry,
-    Emerald,
-    Ocean,
-    Amber,
-    Violet,
+    fn is_assigned(self) -> bool {
+        self.0 != 0
+    }
 }
 
-impl NoteColor {
-    const ALL: [NoteColor; 6] = [
-        NoteColor::Default,
-        NoteColor::Cherry,
-        NoteColor::Emerald,
-        NoteColor::Ocean,
-        NoteColor::Amber,
-        NoteColor::Violet,
-    ];
-
-    fn swatch(self) -> Option<Color> {
-        match self {
-            NoteColor::Default => None,
-            NoteColor::Cherry => Some(Color::from_rgb8(0xf5, 0x6a, 0x6a)),
-            NoteColor::Emerald => Some(Color::from_rgb8(0x5b, 0xc0, 0x7a)),
-            NoteColor::Ocean => Some(Color::from_rgb8(0x4a, 0x90, 0xe2)),
-            NoteColor::Amber => Some(Color::from_rgb8(0xf1, 0xc4, 0x0f)),
-            NoteColor::Violet => Some(Color::from_rgb8(0xb4, 0x79, 0xe6)),
-        }
-    }
-
-    fn label(self) -> &'static str {
-        match self {
-            NoteColor::Default => "Default",
-            NoteColor::Cherry => "Cherry",
-            NoteColor::Emerald => "Emerald",
-            NoteColor::Ocean => "Ocean",
-            NoteColor::Amber => "Amber",
-            NoteColor::Violet => "Violet",
-        }
+/// The notebook's search field, which can be focused with [`text_input::focus`].
+pub fn search_input() -> text_input::Id {
+    text_input::Id::new("notes-search")
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// `title` as shown on screen: "Untitled page" when it is blank.
+pub fn display_title(title: &str) -> &str {
+    if title.trim().is_empty() {
+        "Untitled page"
+    } else {
+        title
     }
 }
 
+/// A microsecond timestamp that is strictly greater than any previously returned one.
+pub(crate) fn unique_id() -> u64 {
+    static LAST: AtomicU64 = AtomicU64::new(0);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_micros() as u64)
+        .unwrap_or_default();
+    let previous = LAST
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+            Some(now.max(last + 1))
+        })
+        .unwrap_or_default();
+
+    now.max(previous + 1)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Note {
+    #[serde(default)]
+    pub id: NoteId,
     pub title: String,
     pub body: String,
     pub tags: Vec<String>,
     pub color: NoteColor,
+    #[serde(default)]
+    pub folder: Option<FolderId>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// Seconds since the Unix epoch.
+    #[serde(default)]
+    pub created: u64,
+    /// Seconds since the Unix epoch at the last change to the content.
+    #[serde(default)]
+    pub updated: u64,
+    /// Targets of the `[[wiki links]]` in the body, keyed by normalized title.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    links: BTreeMap<String, NoteId>,
     #[serde(skip, default)]
-    parsed: Vec<Item>, // not persisted; rebuilt from body
+    parsed: Vec<Block>, // not persisted; rebuilt from body
 }
 
 impl Note {
     pub fn new(title: String, body: String, tags: Vec<String>) -> Self {
-        let parsed = markdown::parse(body.as_str()).collect();
-        Self {
+        let mut note = Self {
+            id: NoteId::generate(),
             title,
             body,
             tags,
-            color: NoteColor::Default,
-            parsed,
-        }
+            color: NoteColor::default(),
+            folder: None,
+            pinned: false,
+            archived: false,
+            attachments: Vec::new(),
+            created: now(),
+            updated: now(),
+            links: BTreeMap::new(),
+            parsed: Vec::new(),
+        };
+        note.refresh_parsed();
+        note
     }
 
-    pub fn parsed(&self) -> &[Item] {
+    /// The title to show for the page, which stands in for a missing one.
+    pub fn display_title(&self) -> &str {
+        display_title(&self.title)
+    }
+
+    /// Where each `[[wiki link]]` in the body currently points.
+    pub fn links(&self) -> &BTreeMap<String, NoteId> {
+        &self.links
+    }
+
+    pub fn parsed(&self) -> &[Block] {
         &self.parsed
     }
 
@@ -87,6 +160,17 @@ impl Note {
         self.color = color;
     }
 
+    /// Whether both notes hold the same user-visible content, ignoring identity.
+    pub fn same_content(&self, other: &Note) -> bool {
+        self.title == other.title
+            && self.body == other.body
+            && self.tags == other.tags
+            && self.color == other.color
+            && self.pinned == other.pinned
+            && self.archived == other.archived
+            && self.attachments == other.attachments
+    }
+
     fn matches(&self, query: &str) -> bool {
         if query.is_empty() {
             true
@@ -104,8 +188,14 @@ impl Default for Note {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Notes {
+    #[serde(default)]
+    name: String,
+    #[serde(skip, default)]
+    key: String,
     #[serde(skip, default)]
     selected: Option<usize>,
+    #[serde(default)]
+    folders: Vec<Folder>,
     entries: Vec<Note>,
     #[serde(skip, default)]
     search: String,
@@ -113,19 +203,82 @@ pub struct Notes {
     color_menu: Option<usize>,
     #[serde(skip, default)]
     last_click: Option<(usize, Instant)>,
+    #[serde(skip, default)]
+    unsaved: Option<StorageError>,
+    #[serde(skip, default)]
+    errors: Vec<StorageError>,
+    #[serde(skip, default)]
+    dirty: HashSet<NoteId>,
+    #[serde(skip, default)]
+    removed: HashSet<NoteId>,
+    #[serde(skip, default)]
+    disk_stamp: Option<(SystemTime, u64)>,
+    /// Pages another program changed or removed, found while saving and not yet
+    /// reported by `CheckDisk`.
+    #[serde(skip, default)]
+    merged: (Vec<NoteId>, Vec<NoteId>),
+    #[serde(skip, default)]
+    notebooks: Vec<NotebookRef>,
+    #[serde(skip, default)]
+    new_notebook: String,
+    #[serde(skip, default)]
+    move_menu: Option<usize>,
+    #[serde(skip, default)]
+    current_folder: Option<FolderId>,
+    #[serde(skip, default)]
+    expanded: HashSet<FolderId>,
+    #[serde(skip, default)]
+    new_folder: String,
+    #[serde(skip, default)]
+    folder_name: String,
+    #[serde(skip, default)]
+    confirm_folder_delete: bool,
+    #[serde(skip, default)]
+    show_archived: bool,
+    #[serde(skip, default)]
+    color_filter: HashSet<NoteColor>,
+    /// For each note, the notes whose wiki links point at it. Rebuilt by `relink`.
+    #[serde(skip, default)]
+    backlinks: HashMap<NoteId, Vec<Backlink>>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     NoteClicked(usize),
     CreateNew,
-    LinkClicked,
+    LinkClicked(markdown::Url),
     ToggleColorMenu(usize),
     ColorPicked { index: usize, color: NoteColor },
     DeleteRequested(usize),
     SearchChanged(String),
     OpenSettings,
+    OpenGraph,
+    QuickCapture,
+    ExportRequested(ExportFormat),
+    ImportRequested(ImportSource),
     OpenInNewWindow(usize),
+    CheckDisk,
+    NotebookSelected(NotebookRef),
+    NewNotebookChanged(String),
+    CreateNotebook,
+    ToggleMoveMenu(usize),
+    MoveRequested { index: usize, notebook: String },
+    MoveToFolder { index: usize, folder: Option<FolderId> },
+    FolderSelected(Option<FolderId>),
+    ToggleFolder(FolderId),
+    NewFolderChanged(String),
+    CreateFolder,
+    FolderNameChanged(String),
+    RenameFolder,
+    FolderMoved(FolderChoice),
+    DeleteFolderRequested,
+    DeleteFolderConfirmed,
+    DeleteFolderCancelled,
+    TogglePinned(usize),
+    ToggleArchived(usize),
+    ShowArchived(bool),
+    ColorFilterToggled(NoteColor),
+    ColorFilterCleared,
 }
 
 #[derive(Debug, Clone)]
@@ -134,72 +287,292 @@ pub enum Event {
     Create,
     Delete(usize),
     OpenSettings,
-    OpenInNewWindow(usize),
+    OpenGraph,
+    QuickCapture,
+    Export(ExportFormat),
+    Import(ImportSource),
+    OpenInNewWindow(NoteId),
+    SwitchNotebook(String),
+    CreateNotebook(String),
+    MoveToNotebook { index: usize, notebook: String },
+    FollowLink(WikiLink),
+    /// A link outside the notebook, as written in the note.
+    OpenExternal(String),
+    /// The set of colors the list is filtered by changed.
+    ColorFilterChanged(Vec<NoteColor>),
+    /// Several notes were deleted at once, e.g. together with their folder.
+    NotesRemoved(Vec<NoteId>),
+    /// The file was modified by another program and has been merged into memory.
+    Reloaded {
+        changed: Vec<NoteId>,
+        removed: Vec<NoteId>,
+    },
 }
 
 impl Notes {
     pub fn load() -> Self {
-        match Self::load_from_disk() {
-            Ok(mut notes) => {
+        Self::open(notebook::DEFAULT_KEY)
+    }
+
+    /// Loads the notebook stored under `key`, or an empty one if its file does not exist yet.
+    pub fn open(key: &str) -> Self {
+        Self::open_with(key, true)
+    }
+
+    /// Like [`Notes::open`], but never writes the notes file: ids and timestamps missing
+    /// from it are only filled in memory.
+    pub fn open_read_only(key: &str) -> Self {
+        Self::open_with(key, false)
+    }
+
+    fn open_with(key: &str, writable: bool) -> Self {
+        let path = notebook::path_for(key);
+        let disk_stamp = fs::metadata(&path)
+            .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+            .ok();
+
+        let mut notes = match storage::load_json::<Self>(&path) {
+            Ok(Some(mut notes)) => {
+                notes.key = key.to_string();
                 for note in &mut notes.entries {
                     note.refresh_parsed();
                 }
+                notes.relink();
+                if notes.assign_missing_metadata() && writable {
+                    notes.persist();
+                }
                 notes
             }
+            Ok(None) => Self::default_values(key),
             Err(error) => {
-                eprintln!("Failed to load notes from disk: {error}");
-                Self::default_values()
+                let mut notes = Self::default_values(key);
+                notes.errors.push(error);
+                notes
             }
+        };
+
+        if notes.disk_stamp.is_none() {
+            notes.disk_stamp = disk_stamp;
         }
+        notes.notebooks = notebook::discover();
+        notes
+    }
+
+    /// Creates and saves a new, empty notebook called `name`.
+    pub fn create(name: &str) -> Self {
+        let key = notebook::unused_key(name, &notebook::discover());
+        let mut notes = Self::default_values(&key);
+        notes.name = name.trim().to_string();
+        notes.persist();
+        notes.notebooks = notebook::discover();
+        notes
     }
 
-    fn default_values() -> Self {
+    fn default_values(key: &str) -> Self {
         Self {
+            name: String::new(),
+            key: key.to_string(),
             selected: None,
+            folders: Vec::new(),
             entries: Vec::new(),
             search: String::new(),
             color_menu: None,
             last_click: None,
+            unsaved: None,
+            errors: Vec::new(),
+            dirty: HashSet::new(),
+            removed: HashSet::new(),
+            disk_stamp: None,
+            merged: (Vec::new(), Vec::new()),
+            notebooks: Vec::new(),
+            new_notebook: String::new(),
+            move_menu: None,
+            current_folder: None,
+            expanded: HashSet::new(),
+            new_folder: String::new(),
+            folder_name: String::new(),
+            confirm_folder_delete: false,
+            show_archived: false,
+            color_filter: HashSet::new(),
+            backlinks: HashMap::new(),
         }
     }
 
-    fn persist(&self) {
-        if let Err(error) = self.save_to_disk() {
-            eprintln!("Failed to save notes: {error}");
+    fn persist(&mut self) {
+        // Another program, like the command line, wrote the file since it was read:
+        // take its changes in first rather than writing over them.
+        if let Some(Event::Reloaded { changed, removed }) = self.merge_from_disk(true).0 {
+            self.merged.0.extend(changed);
+            self.merged.1.extend(removed);
         }
+
+        match storage::save_json(&self.storage_path(), self) {
+            Ok(()) => {
+                self.unsaved = None;
+                self.dirty.clear();
+                self.removed.clear();
+                self.disk_stamp = self.read_disk_stamp();
+            }
+            Err(error) => {
+                self.unsaved = Some(error.clone());
+                self.errors.push(error);
+            }
+        }
+    }
+
+    fn storage_path(&self) -> PathBuf {
+        notebook::path_for(&self.key)
+    }
+
+    /// Modification time and length of the notes file, used to spot writes by other programs.
+    fn read_disk_stamp(&self) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(self.storage_path()).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
     }
 
-    fn save_to_disk(&self) -> io::Result<()> {
-        let path = Self::storage_path();
-        if let Some(parent) = path.parent() {
-            if !parent.as_os_str().is_empty() {
-                fs::create_dir_all(parent)?;
+    /// Gives an id and timestamps to every note that came from a file written before
+    /// they existed (or by a tool that left them out). Returns whether anything changed.
+    fn assign_missing_metadata(&mut self) -> bool {
+        let mut assigned = false;
+        for note in &mut self.entries {
+            if !note.id.is_assigned() {
+                note.id = NoteId::generate();
+                assigned = true;
+            }
+            if note.created == 0 {
+                // Ids are creation times, at least for notes created since they exist.
+                note.created = note.id.raw() / 1_000_000;
+                assigned = true;
+            }
+            if note.updated == 0 {
+                note.updated = note.created;
+                assigned = true;
             }
         }
+        assigned
+    }
 
-        let file = File::create(path)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self)
-            .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+    /// Polls the notes file for changes made outside the app.
+    pub fn subscription(&self) -> Subscription<Message> {
+        time::every(DISK_CHECK_INTERVAL).map(|_| Message::CheckDisk)
     }
 
-    fn load_from_disk() -> io::Result<Self> {
-        let path = Self::storage_path();
-        if !path.exists() {
-            return Ok(Self::default_values());
+    /// Reloads the notes file if another program wrote to it, keeping local edits
+    /// that have not reached disk yet on top of the new contents.
+    fn reload_if_modified(&mut self) -> Option<Event> {
+        let (event, needs_write) = self.merge_from_disk(false);
+        if needs_write {
+            self.persist();
+        }
+
+        let (mut changed, mut removed) = std::mem::take(&mut self.merged);
+        if let Some(Event::Reloaded {
+            changed: more_changed,
+            removed: more_removed,
+        }) = event
+        {
+            changed.extend(more_changed);
+            removed.extend(more_removed);
+        }
+        (!changed.is_empty() || !removed.is_empty())
+            .then_some(Event::Reloaded { changed, removed })
+    }
+
+    /// Takes in the notes file if another program wrote to it since it was last read
+    /// or written, and returns what changed for the app and whether local edits still
+    /// need writing. Local folders win over the file's when `keep_folders`.
+    fn merge_from_disk(&mut self, keep_folders: bool) -> (Option<Event>, bool) {
+        let stamp = self.read_disk_stamp();
+        if stamp.is_none() || stamp == self.disk_stamp {
+            return (None, false);
+        }
+        self.disk_stamp = stamp;
+
+        let mut disk = match storage::load_json::<Self>(&self.storage_path()) {
+            Ok(Some(disk)) => disk,
+            Ok(None) => return (None, false),
+            Err(error) => {
+                self.errors.push(error);
+                return (None, false);
+            }
+        };
+        let needs_write =
+            disk.assign_missing_metadata() || !self.dirty.is_empty() || !self.removed.is_empty();
+
+        let selected = self
+            .selected
+            .and_then(|index| self.entries.get(index))
+            .map(|note| note.id);
+        let mut changed = Vec::new();
+
+        for note in &mut disk.entries {
+            note.refresh_parsed();
+            let unchanged = self
+                .find(note.id)
+                .is_some_and(|local| local.same_content(note));
+            if !unchanged && !self.dirty.contains(&note.id) {
+                changed.push(note.id);
+            }
+        }
+
+        let removed = self
+            .entries
+            .iter()
+            .map(|note| note.id)
+            .filter(|id| {
+                !self.dirty.contains(id) && !disk.entries.iter().any(|note| note.id == *id)
+            })
+            .collect::<Vec<_>>();
+
+        for local in self.entries.iter().filter(|note| self.dirty.contains(&note.id)) {
+            match disk.entries.iter_mut().find(|note| note.id == local.id) {
+                Some(slot) => *slot = local.clone(),
+                None => disk.entries.push(local.clone()),
+            }
+        }
+        disk.entries.retain(|note| !self.removed.contains(&note.id));
+
+        if !keep_folders {
+            self.name = disk.name;
         }
+        if self.unsaved.is_none() && !keep_folders {
+            self.folders = disk.folders;
+        }
+        if let Some(current) = self.current_folder
+            && !self.folders.iter().any(|folder| folder.id == current)
+        {
+            self.current_folder = None;
+        }
+        self.entries = disk.entries;
+        self.relink();
+        self.selected = selected.and_then(|id| self.position(id));
+        self.color_menu = None;
+        self.move_menu = None;
+        self.last_click = None;
+
+        let event = (!changed.is_empty() || !removed.is_empty())
+            .then_some(Event::Reloaded { changed, removed });
+        (event, needs_write)
+    }
+
+    /// Storage failures that happened since the last call, oldest first.
+    pub fn take_errors(&mut self) -> Vec<StorageError> {
+        std::mem::take(&mut self.errors)
+    }
 
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        serde_json::from_reader(reader)
-            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    /// The last failed write, if the notes on screen have not reached disk yet.
+    pub fn unsaved(&self) -> Option<&StorageError> {
+        self.unsaved.as_ref()
     }
 
-    fn storage_path() -> PathBuf {
-        PathBuf::from("notes.json")
+    /// Attempts to write the notes again after a failed save.
+    pub fn retry_save(&mut self) -> bool {
+        self.persist();
+        self.unsaved.is_none()
     }
 
-    fn changed(&mut self) {
+    fn changed(&mut self, id: NoteId) {
+        self.dirty.insert(id);
         self.persist();
     }
 
@@ -217,6 +590,7 @@ impl Notes {
 
                 self.last_click = Some((index, now));
                 self.color_menu = None;
+                self.move_menu = None;
                 self.selected = Some(index);
 
                 if double_click {
@@ -234,8 +608,15 @@ impl Notes {
             Message::OpenSettings => {
                 Some(Event::OpenSettings)
             }
-            Message::LinkClicked => None,
+            Message::OpenGraph => Some(Event::OpenGraph),
+            Message::QuickCapture => Some(Event::QuickCapture),
+            Message::ExportRequested(format) => Some(Event::Export(format)),
+            Message::ImportRequested(source) => Some(Event::Import(source)),
+            Message::LinkClicked(url) => links::parse(&url)
+                .map(Event::FollowLink)
+                .or_else(|| links::external(&url).map(Event::OpenExternal)),
             Message::ToggleColorMenu(index) => {
+                self.move_menu = None;
                 self.color_menu = if self.color_menu == Some(index) {
                     None
                 } else {
@@ -244,41 +625,191 @@ impl Notes {
                 None
             }
             Message::ColorPicked { index, color } => {
+                self.color_menu = None;
                 if let Some(note) = self.entries.get_mut(index) {
                     note.set_color(color);
+                    let id = note.id;
+                    self.changed(id);
                 }
-                self.color_menu = None;
-                self.changed();
                 None
             }
             Message::DeleteRequested(index) => {
-                if index < self.entries.len() {
-                    self.entries.remove(index);
-                    self.adjust_after_remove(index);
-                    self.color_menu = None;
-                    self.last_click = None;
-                    self.changed();
-                    Some(Event::Delete(index))
-                } else {
-                    None
-                }
+                self.remove(index).map(|_| Event::Delete(index))
             }
             Message::SearchChanged(query) => {
                 self.search = query;
                 self.color_menu = None;
+                self.move_menu = None;
                 self.last_click = None;
                 None
             }
-            Message::OpenInNewWindow(index) => Some(Event::OpenInNewWindow(index)),
+            Message::OpenInNewWindow(index) => self
+                .entries
+                .get(index)
+                .map(|note| Event::OpenInNewWindow(note.id)),
+            Message::CheckDisk => self.reload_if_modified(),
+            Message::NotebookSelected(notebook) => {
+                (notebook.key != self.key).then_some(Event::SwitchNotebook(notebook.key))
+            }
+            Message::NewNotebookChanged(name) => {
+                self.new_notebook = name;
+                None
+            }
+            Message::CreateNotebook => {
+                let name = std::mem::take(&mut self.new_notebook);
+                (!name.trim().is_empty()).then_some(Event::CreateNotebook(name))
+            }
+            Message::ToggleMoveMenu(index) => {
+                self.color_menu = None;
+                self.move_menu = if self.move_menu == Some(index) {
+                    None
+                } else {
+                    Some(index)
+                };
+                None
+            }
+            Message::MoveRequested { index, notebook } => {
+                self.move_menu = None;
+                Some(Event::MoveToNotebook { index, notebook })
+            }
+            Message::MoveToFolder { index, folder } => {
+                self.move_menu = None;
+                if let Some(note) = self.entries.get_mut(index)
+                    && note.folder != folder
+                {
+                    note.folder = folder;
+                    let id = note.id;
+                    self.changed(id);
+                }
+                None
+            }
+            Message::FolderSelected(folder) => {
+                self.current_folder = folder;
+                self.folder_name = folder
+                    .and_then(|id| self.folder(id))
+                    .map(|folder| folder.name.clone())
+                    .unwrap_or_default();
+                self.confirm_folder_delete = false;
+                self.color_menu = None;
+                self.move_menu = None;
+                None
+            }
+            Message::ToggleFolder(id) => {
+                if !self.expanded.remove(&id) {
+                    self.expanded.insert(id);
+                }
+                None
+            }
+            Message::NewFolderChanged(name) => {
+                self.new_folder = name;
+                None
+            }
+            Message::CreateFolder => {
+                let name = std::mem::take(&mut self.new_folder);
+                if !name.trim().is_empty() {
+                    let parent = self.current_folder;
+                    self.folders.push(Folder {
+                        id: FolderId::generate(),
+                        name: name.trim().to_string(),
+                        parent,
+                    });
+                    if let Some(parent) = parent {
+                        self.expanded.insert(parent);
+                    }
+                    self.persist();
+                }
+                None
+            }
+            Message::FolderNameChanged(name) => {
+                self.folder_name = name;
+                None
+            }
+            Message::RenameFolder => {
+                let name = self.folder_name.trim().to_string();
+                if let Some(current) = self.current_folder
+                    && !name.is_empty()
+                    && let Some(folder) = self.folders.iter_mut().find(|f| f.id == current)
+                {
+                    folder.name = name;
+                    self.persist();
+                }
+                None
+            }
+            Message::FolderMoved(target) => {
+                if let Some(current) = self.current_folder
+                    && !target
+                        .id
+                        .is_some_and(|id| folders::subtree(&self.folders, current).contains(&id))
+                    && let Some(folder) = self.folders.iter_mut().find(|f| f.id == current)
+                {
+                    folder.parent = target.id;
+                    if let Some(parent) = target.id {
+                        self.expanded.insert(parent);
+                    }
+                    self.persist();
+                }
+                None
+            }
+            Message::DeleteFolderRequested => {
+                self.confirm_folder_delete = self.current_folder.is_some();
+                None
+            }
+            Message::DeleteFolderCancelled => {
+                self.confirm_folder_delete = false;
+                None
+            }
+            Message::TogglePinned(index) => {
+                if let Some(note) = self.entries.get_mut(index) {
+                    note.pinned = !note.pinned;
+                    let id = note.id;
+                    self.changed(id);
+                }
+                None
+            }
+            Message::ToggleArchived(index) => {
+                self.color_menu = None;
+                self.move_menu = None;
+                if let Some(note) = self.entries.get_mut(index) {
+                    note.archived = !note.archived;
+                    let id = note.id;
+                    self.changed(id);
+                }
+                None
+            }
+            Message::ShowArchived(show) => {
+                self.show_archived = show;
+                self.color_menu = None;
+                self.move_menu = None;
+                None
+            }
+            Message::ColorFilterToggled(color) => {
+                if !self.color_filter.remove(&color) {
+                    self.color_filter.insert(color);
+                }
+                self.last_click = None;
+                Some(Event::ColorFilterChanged(self.color_filter()))
+            }
+            Message::ColorFilterCleared => {
+                self.color_filter.clear();
+                Some(Event::ColorFilterChanged(Vec::new()))
+            }
+            Message::DeleteFolderConfirmed => {
+                self.confirm_folder_delete = false;
+                let current = self.current_folder?;
+                Some(Event::NotesRemoved(self.delete_folder(current)))
+            }
         }
     }
 
-    pub fn view(&self) -> Element<'_, Message> {
-        let markdown_style = Style::from_palette(Theme::KanagawaDragon.palette());
-        let query = self.search.to_lowercase();
+    pub fn view<'a>(&'a self, palette: &'a Palette) -> Element<'a, Message> {
+        let placeholder = match self.current_folder {
+            Some(id) => format!("Search titles in {}...", folders::path(&self.folders, id)),
+            None => String::from("Search titles..."),
+        };
 
         let mut search_row = row![
-            text_input("Search titles...", &self.search)
+            text_input(&placeholder, &self.search)
+                .id(search_input())
                 .on_input(Message::SearchChanged)
                 .padding(10)
                 .size(16)
@@ -292,12 +823,43 @@ impl Notes {
                 .push(button(text("Clear")).on_press(Message::SearchChanged(String::new())));
         }
 
+        search_row = search_row.push(
+            checkbox("Archived", self.show_archived)
+                .on_toggle(Message::ShowArchived)
+                .size(16),
+        );
+
         let mut content = column![search_row, vertical_space().height(Length::Fixed(12.0))];
 
+        let current = self
+            .notebooks
+            .iter()
+            .find(|notebook| notebook.key == self.key)
+            .cloned();
+
+        let mut create_notebook = button(text("Create notebook"));
+        if !self.new_notebook.trim().is_empty() {
+            create_notebook = create_notebook.on_press(Message::CreateNotebook);
+        }
+
         let header = row![
-            text("Notebook").size(32),
+            pick_list(self.notebooks.as_slice(), current, Message::NotebookSelected)
+                .text_size(24)
+                .padding([4, 12]),
             horizontal_space().width(Length::Fill),
+            text_input("New notebook name", &self.new_notebook)
+                .on_input(Message::NewNotebookChanged)
+                .on_submit(Message::CreateNotebook)
+                .padding(8)
+                .width(Length::Fixed(200.0)),
+            create_notebook,
             button(text("New page")).on_press(Message::CreateNew),
+            button(text("Quick capture")).on_press(Message::QuickCapture),
+            button(text("Graph")).on_press(Message::OpenGraph),
+            pick_list(ExportFormat::ALL, None::<ExportFormat>, Message::ExportRequested)
+                .placeholder("Export…"),
+            pick_list(ImportSource::ALL, None::<ImportSource>, Message::ImportRequested)
+                .placeholder("Import…"),
             button(text("Settings")).on_press(Message::OpenSettings),
         ]
         .align_y(Alignment::Center)
@@ -306,17 +868,18 @@ impl Notes {
         content = content.push(header);
         content = content.push(vertical_space().height(Length::Fixed(16.0)));
 
+        let candidates = self.candidates();
+        content = content.push(self.color_chips(palette, &candidates));
+
         let mut any_visible = false;
 
-        for (index, note) in self
-            .entries
-            .iter()
-            .enumerate()
-            .filter(|(_, note)| note.matches(&query))
+        for (index, note) in candidates
+            .into_iter()
+            .filter(|(_, note)| self.passes_color_filter(palette, note))
         {
             any_visible = true;
 
-            let bar_color = note.color.swatch();
+            let bar_color = palette.swatch(&note.color);
             let selected = self.selected == Some(index);
 
             let drag_icon = container(text("=").size(18))
@@ -325,19 +888,23 @@ impl Notes {
 
             let mut note_column = column![
                 row![
-                    text(if note.title.trim().is_empty() {
-                        "Untitled page"
-                    } else {
-                        note.title.as_str()
-                    })
-                    .size(26),
+                    text(note.display_title()).size(26),
                     horizontal_space().width(Length::Fill),
                     button(text("Open in new window").size(18))
                         .on_press(Message::OpenInNewWindow(index))
                         .padding([6, 10]),
+                    button(text("move").size(18))
+                        .on_press(Message::ToggleMoveMenu(index))
+                        .padding([6, 10]),
                     button(text("colors").size(18))
                         .on_press(Message::ToggleColorMenu(index))
                         .padding([6, 10]),
+                    button(text(if note.pinned { "unpin" } else { "pin" }).size(18))
+                        .on_press(Message::TogglePinned(index))
+                        .padding([6, 10]),
+                    button(text(if note.archived { "unarchive" } else { "archive" }).size(18))
+                        .on_press(Message::ToggleArchived(index))
+                        .padding([6, 10]),
                     button(text("trash").size(18))
                         .on_press(Message::DeleteRequested(index))
                         .padding([6, 10]),
@@ -347,27 +914,26 @@ impl Notes {
             ]
             .spacing(8);
 
-            if !note.tags.is_empty() {
-                let tags = note
-                    .tags
-                    .iter()
-                    .map(|tag| format!("#{tag}"))
-                    .collect::<Vec<_>>()
-                    .join(" ");
+            let status = [(note.pinned, "Pinned"), (note.archived, "Archived")]
+                .into_iter()
+                .filter(|(on, _)| *on)
+                .map(|(_, label)| label.to_string());
+            let labels = status
+                .chain(note.tags.iter().map(|tag| format!("#{tag}")))
+                .collect::<Vec<_>>();
 
-                note_column = note_column.push(text(tags).size(14));
+            if !labels.is_empty() {
+                note_column = note_column.push(text(labels.join(" ")).size(14));
             }
 
-            let preview = markdown::view(note.parsed(), Settings::default(), markdown_style)
-                .map(|_| Message::LinkClicked);
+            let preview = preview::view(note.parsed(), Message::LinkClicked);
 
             note_column = note_column.push(preview);
 
             if self.color_menu == Some(index) {
-                let palette = NoteColor::ALL.iter().fold(row![], |row, color| {
-                    let swatch_color = color.swatch();
+                let picker = palette.iter().fold(row![], |row, (color, swatch_color)| {
                     let selected_color = note.color == *color;
-                    let label = text(color.label()).size(14);
+                    let label = text(color.name()).size(14);
 
                     let swatch = container(vertical_space().height(Length::Fixed(20.0)))
                         .width(Length::Fixed(32.0))
@@ -378,13 +944,55 @@ impl Notes {
                         .style(move |_, status| color_button_style(selected_color, status))
                         .on_press(Message::ColorPicked {
                             index,
-                            color: *color,
+                            color: color.clone(),
                         });
 
                     row.push(button)
                 });
 
-                note_column = note_column.push(palette.spacing(8).align_y(Alignment::Center));
+                note_column = note_column.push(picker.spacing(8).align_y(Alignment::Center).wrap());
+            }
+
+            if self.move_menu == Some(index) {
+                let folder_targets = std::iter::once(FolderChoice {
+                    id: None,
+                    label: String::from("No folder"),
+                })
+                .chain(self.folders.iter().map(|folder| FolderChoice {
+                    id: Some(folder.id),
+                    label: folders::path(&self.folders, folder.id),
+                }))
+                .filter(|choice| choice.id != note.folder)
+                .fold(row![text("Folder").size(14)], |row, choice| {
+                    row.push(
+                        button(text(choice.label).size(14))
+                            .padding([4, 10])
+                            .on_press(Message::MoveToFolder {
+                                index,
+                                folder: choice.id,
+                            }),
+                    )
+                });
+
+                note_column =
+                    note_column.push(folder_targets.spacing(8).align_y(Alignment::Center));
+
+                let targets = self
+                    .notebooks
+                    .iter()
+                    .filter(|notebook| notebook.key != self.key)
+                    .fold(row![text("Move to").size(14)], |row, notebook| {
+                        row.push(
+                            button(text(notebook.name.as_str()).size(14))
+                                .padding([4, 10])
+                                .on_press(Message::MoveRequested {
+                                    index,
+                                    notebook: notebook.key.clone(),
+                                }),
+                        )
+                    });
+
+                note_column = note_column.push(targets.spacing(8).align_y(Alignment::Center));
             }
 
             let card = container(note_column.spacing(10))
@@ -417,41 +1025,475 @@ impl Notes {
             .height(Length::Fill)
             .width(Length::Fill);
 
-        container(scroll)
+        container(row![self.folder_sidebar(), scroll].spacing(24))
             .width(Length::Fill)
             .padding([24, 32])
             .into()
     }
 
+    /// Notes matching every filter except color, in display order: pinned notes first,
+    /// then the rest, each group keeping the notebook order. Archived notes only appear
+    /// when asked for.
+    fn candidates(&self) -> Vec<(usize, &Note)> {
+        let query = self.search.to_lowercase();
+        let scope = self
+            .current_folder
+            .map(|id| folders::subtree(&self.folders, id));
+
+        let mut visible = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, note)| self.show_archived || !note.archived)
+            .filter(|(_, note)| note.matches(&query))
+            .filter(|(_, note)| {
+                scope.as_ref().is_none_or(|scope| {
+                    note.folder.is_some_and(|folder| scope.contains(&folder))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        visible.sort_by_key(|(_, note)| !note.pinned);
+        visible
+    }
+
+    fn passes_color_filter(&self, palette: &Palette, note: &Note) -> bool {
+        self.color_filter.is_empty() || self.color_filter.contains(&palette.resolve(&note.color))
+    }
+
+    pub fn color_filter(&self) -> Vec<NoteColor> {
+        self.color_filter.iter().cloned().collect()
+    }
+
+    pub fn set_color_filter(&mut self, colors: &[NoteColor]) {
+        self.color_filter = colors.iter().cloned().collect();
+    }
+
+    /// One toggle per palette color, with how many of `candidates` carry it.
+    fn color_chips<'a>(
+        &'a self,
+        palette: &'a Palette,
+        candidates: &[(usize, &Note)],
+    ) -> Element<'a, Message> {
+        let chips = palette.iter().fold(row![].spacing(8), |chips, (color, swatch)| {
+            let count = candidates
+                .iter()
+                .filter(|(_, note)| palette.resolve(&note.color) == *color)
+                .count();
+            let active = self.color_filter.contains(color);
+
+            let dot = container(vertical_space().height(Length::Fixed(12.0)))
+                .width(Length::Fixed(12.0))
+                .style(move |_| swatch_style(swatch, false));
+
+            chips.push(
+                button(
+                    row![dot, text(format!("{} {count}", color.name())).size(14)]
+                        .spacing(6)
+                        .align_y(Alignment::Center),
+                )
+                .padding([4, 10])
+                .style(move |_, status| color_button_style(active, status))
+                .on_press(Message::ColorFilterToggled(color.clone())),
+            )
+        });
+
+        let mut chips = chips.align_y(Alignment::Center);
+        if !self.color_filter.is_empty() {
+            chips = chips.push(
+                button(text("Clear colors").size(14))
+                    .padding([4, 10])
+                    .on_press(Message::ColorFilterCleared),
+            );
+        }
+
+        chips.wrap().into()
+    }
+
+    fn folder_sidebar(&self) -> Element<'_, Message> {
+        let showing_all = self.current_folder.is_none();
+        let all_notes = button(text("All pages").size(16))
+            .width(Length::Fill)
+            .style(move |theme, status| folder_button_style(theme, status, showing_all))
+            .on_press(Message::FolderSelected(None));
+
+        let tree = folders::flatten(&self.folders, |id| self.expanded.contains(&id))
+            .into_iter()
+            .fold(column![all_notes].spacing(4), |tree, (depth, folder)| {
+                let has_children = folders::children(&self.folders, Some(folder.id))
+                    .next()
+                    .is_some();
+                let toggle: Element<'_, Message> = if has_children {
+                    let marker = if self.expanded.contains(&folder.id) { "v" } else { ">" };
+                    button(text(marker).size(14))
+                        .padding([2, 6])
+                        .on_press(Message::ToggleFolder(folder.id))
+                        .into()
+                } else {
+                    horizontal_space().width(Length::Fixed(22.0)).into()
+                };
+
+                let active = self.current_folder == Some(folder.id);
+                let label = button(text(folder.name.as_str()).size(16))
+                    .width(Length::Fill)
+                    .style(move |theme, status| folder_button_style(theme, status, active))
+                    .on_press(Message::FolderSelected(Some(folder.id)));
+
+                tree.push(
+                    row![
+                        horizontal_space().width(Length::Fixed(16.0 * depth as f32)),
+                        toggle,
+                        label,
+                    ]
+                    .spacing(4)
+                    .align_y(Alignment::Center),
+                )
+            });
+
+        let mut create_folder = button(text("Add").size(14));
+        if !self.new_folder.trim().is_empty() {
+            create_folder = create_folder.on_press(Message::CreateFolder);
+        }
+
+        let mut sidebar = column![
+            text("Folders").size(20),
+            scrollable(tree).height(Length::Fill),
+            row![
+                text_input("New folder", &self.new_folder)
+                    .on_input(Message::NewFolderChanged)
+                    .on_submit(Message::CreateFolder)
+                    .padding(6)
+                    .size(14),
+                create_folder,
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(10);
+
+        if let Some(current) = self.current_folder {
+            let targets = folders::move_targets(&self.folders, current);
+            let parent = self.folder(current).and_then(|folder| folder.parent);
+            let selected_target = targets.iter().find(|choice| choice.id == parent).cloned();
+
+            sidebar = sidebar.push(
+                row![
+                    text_input("Folder name", &self.folder_name)
+                        .on_input(Message::FolderNameChanged)
+                        .on_submit(Message::RenameFolder)
+                        .padding(6)
+                        .size(14),
+                    button(text("Rename").size(14)).on_press(Message::RenameFolder),
+                ]
+                .spacing(6)
+                .align_y(Alignment::Center),
+            );
+            sidebar = sidebar.push(
+                row![
+                    text("Inside").size(14),
+                    pick_list(targets, selected_target, Message::FolderMoved).text_size(14),
+                ]
+                .spacing(6)
+                .align_y(Alignment::Center),
+            );
+
+            sidebar = if self.confirm_folder_delete {
+                let doomed = folders::subtree(&self.folders, current);
+                let pages = self
+                    .entries
+                    .iter()
+                    .filter(|note| note.folder.is_some_and(|folder| doomed.contains(&folder)))
+                    .count();
+
+                sidebar.push(
+                    column![
+                        text(format!("Delete this folder, its subfolders and {pages} page(s)?"))
+                            .size(14),
+                        row![
+                            button(text("Delete").size(14))
+                                .on_press(Message::DeleteFolderConfirmed),
+                            button(text("Cancel").size(14))
+                                .on_press(Message::DeleteFolderCancelled),
+                        ]
+                        .spacing(6),
+                    ]
+                    .spacing(6),
+                )
+            } else {
+                sidebar.push(
+                    button(text("Delete folder").size(14)).on_press(Message::DeleteFolderRequested),
+                )
+            };
+        }
+
+        container(sidebar)
+            .width(Length::Fixed(240.0))
+            .height(Length::Fill)
+            .into()
+    }
+
+    /// The page highlighted in the list.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
     pub fn select(&mut self, selection: Option<usize>) {
         self.selected = selection.and_then(|index| self.entries.get(index).map(|_| index));
     }
 
+    /// Saves `note` over the page at `editing`, which keeps its id, folder, colour, pin
+    /// and archive state, or adds it as a new page in the folder `note` names.
     pub fn upsert(&mut self, mut note: Note, editing: Option<usize>) -> usize {
-        if let Some(index) = editing {
-            if let Some(slot) = self.entries.get_mut(index) {
-                note.color = slot.color;
+        let (index, renamed) = match editing
+            .and_then(|index| self.entries.get_mut(index).map(|slot| (index, slot)))
+        {
+            Some((index, slot)) => {
+                note.id = slot.id;
+                note.created = slot.created;
+                note.color = slot.color.clone();
+                note.folder = slot.folder;
+                note.pinned = slot.pinned;
+                note.archived = slot.archived;
+                if note.same_content(slot) {
+                    note.updated = slot.updated;
+                }
+                note.links = std::mem::take(&mut slot.links);
+                let renamed = (links::normalize(&slot.title) != links::normalize(&note.title))
+                    .then(|| slot.title.clone());
                 *slot = note;
-                self.changed();
-                index
-            } else {
-                let index = self.entries.len();
+                (index, renamed)
+            }
+            None => {
                 self.entries.push(note);
-                self.changed();
-                index
+                (self.entries.len() - 1, None)
             }
-        } else {
-            let index = self.entries.len();
+        };
+
+        let id = self.entries[index].id;
+        self.dirty.insert(id);
+        if let Some(old_title) = renamed {
+            let new_title = self.entries[index].title.clone();
+            self.retitle_links(id, &old_title, &new_title);
+        }
+        self.relink();
+        self.persist();
+        index
+    }
+
+    /// Adds `notes` as they are, keeping their folders, and saves once.
+    pub fn insert_all(&mut self, notes: Vec<Note>) {
+        if notes.is_empty() {
+            return;
+        }
+
+        for note in notes {
+            self.dirty.insert(note.id);
             self.entries.push(note);
-            self.changed();
-            index
         }
+        self.relink();
+        self.persist();
+    }
+
+    /// The folder reached by following `path` of folder names from the top level,
+    /// creating the ones that do not exist yet. Names match case-insensitively.
+    pub fn ensure_folder(&mut self, path: &[String]) -> Option<FolderId> {
+        let mut parent = None;
+        for name in path {
+            let name = name.trim();
+            let existing = folders::children(&self.folders, parent)
+                .find(|folder| folder.name.to_lowercase() == name.to_lowercase())
+                .map(|folder| folder.id);
+            parent = Some(existing.unwrap_or_else(|| {
+                let id = FolderId::generate();
+                self.folders.push(Folder {
+                    id,
+                    name: name.to_string(),
+                    parent,
+                });
+                id
+            }));
+        }
+        parent
+    }
+
+    /// Rewrites `[[old title]]` in every note that links to `id`, after it was renamed.
+    fn retitle_links(&mut self, id: NoteId, old_title: &str, new_title: &str) {
+        if old_title.trim().is_empty() || new_title.trim().is_empty() {
+            return;
+        }
+
+        for note in &mut self.entries {
+            if !note.links.values().any(|target| *target == id) {
+                continue;
+            }
+
+            let body = links::retitle(&note.body, old_title, new_title);
+            if body != note.body {
+                note.body = body;
+                self.dirty.insert(note.id);
+            }
+        }
+    }
+
+    /// Points every wiki link at a note. Links keep the note they were resolved to
+    /// while it exists; otherwise they are matched by title.
+    fn relink(&mut self) {
+        let ids = self.entries.iter().map(|note| note.id).collect::<HashSet<_>>();
+        let mut titles = HashMap::new();
+        for note in &self.entries {
+            let key = links::normalize(&note.title);
+            if !key.is_empty() {
+                titles.entry(key).or_insert(note.id);
+            }
+        }
+
+        for note in &mut self.entries {
+            let mut resolved = BTreeMap::new();
+            for target in links::targets(&note.body) {
+                let key = links::normalize(&target);
+                let id = note
+                    .links
+                    .get(&key)
+                    .copied()
+                    .filter(|id| ids.contains(id))
+                    .or_else(|| titles.get(&key).copied());
+                if let Some(id) = id {
+                    resolved.insert(key, id);
+                }
+            }
+
+            // Resolved links are derived from the notes, so a change here alone does not
+            // make a note dirty: marking it would let a reload overwrite edits on disk.
+            if resolved != note.links {
+                note.links = resolved;
+                note.refresh_parsed();
+            } else if self.dirty.contains(&note.id) {
+                note.refresh_parsed();
+            }
+        }
+
+        self.backlinks.clear();
+        for note in &self.entries {
+            let targets = note.links.values().copied().collect::<HashSet<_>>();
+            for target in targets.into_iter().filter(|target| *target != note.id) {
+                self.backlinks.entry(target).or_default().push(Backlink {
+                    source: note.id,
+                    title: note.title.clone(),
+                    snippet: links::snippet(&note.body, &note.links, target),
+                });
+            }
+        }
+    }
+
+    /// Notes that link to `id`, in notebook order.
+    pub fn backlinks(&self, id: NoteId) -> &[Backlink] {
+        self.backlinks.get(&id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The note a clicked wiki link leads to, if it still exists.
+    pub fn resolve(&self, link: &WikiLink) -> Option<NoteId> {
+        link.id
+            .filter(|id| self.find(*id).is_some())
+            .or_else(|| {
+                let key = links::normalize(&link.title);
+                self.entries
+                    .iter()
+                    .find(|note| links::normalize(&note.title) == key)
+                    .map(|note| note.id)
+            })
+    }
+
+    /// The folder shown in the list, where new pages from the editor go.
+    pub fn current_folder(&self) -> Option<FolderId> {
+        self.current_folder
+    }
+
+    pub fn folders(&self) -> &[Folder] {
+        &self.folders
+    }
+
+    fn folder(&self, id: FolderId) -> Option<&Folder> {
+        self.folders.iter().find(|folder| folder.id == id)
+    }
+
+    /// Deletes a folder together with its subfolders and every note inside them.
+    /// Returns the ids of the deleted notes.
+    fn delete_folder(&mut self, id: FolderId) -> Vec<NoteId> {
+        let doomed = folders::subtree(&self.folders, id);
+        let parent = self.folder(id).and_then(|folder| folder.parent);
+        let selected = self
+            .selected
+            .and_then(|index| self.entries.get(index))
+            .map(|note| note.id);
+
+        let mut removed = Vec::new();
+        self.entries.retain(|note| {
+            let keep = !note.folder.is_some_and(|folder| doomed.contains(&folder));
+            if !keep {
+                removed.push(note.id);
+            }
+            keep
+        });
+        self.folders.retain(|folder| !doomed.contains(&folder.id));
+
+        self.removed.extend(removed.iter().copied());
+        self.selected = selected.and_then(|id| self.position(id));
+        self.current_folder = parent;
+        self.folder_name = parent
+            .and_then(|id| self.folder(id))
+            .map(|folder| folder.name.clone())
+            .unwrap_or_default();
+        self.color_menu = None;
+        self.move_menu = None;
+        self.last_click = None;
+        self.relink();
+        self.persist();
+
+        removed
+    }
+
+    /// Deletes the note at `index` and saves, returning it.
+    pub fn remove(&mut self, index: usize) -> Option<Note> {
+        if index >= self.entries.len() {
+            return None;
+        }
+
+        let note = self.entries.remove(index);
+        self.adjust_after_remove(index);
+        self.color_menu = None;
+        self.move_menu = None;
+        self.last_click = None;
+        self.removed.insert(note.id);
+        self.relink();
+        self.persist();
+        Some(note)
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Display name of the notebook, as shown in the switcher.
+    pub fn name(&self) -> String {
+        notebook::display_name(&self.key, &self.name)
     }
 
     pub fn get(&self, index: usize) -> Option<&Note> {
         self.entries.get(index)
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &Note> {
+        self.entries.iter()
+    }
+
+    pub fn find(&self, id: NoteId) -> Option<&Note> {
+        self.entries.iter().find(|note| note.id == id)
+    }
+
+    pub fn position(&self, id: NoteId) -> Option<usize> {
+        self.entries.iter().position(|note| note.id == id)
+    }
+
     fn adjust_after_remove(&mut self, index: usize) {
         if let Some(selected) = self.selected {
             if selected == index {
@@ -479,10 +1521,12 @@ impl Default for Notes {
 
 impl Note {
     fn refresh_parsed(&mut self) {
-        self.parsed = markdown::parse(self.body.as_str()).collect();
+        let body = links::to_markdown(&self.body, &self.links);
+        self.parsed = preview::parse(&body);
     }
 }
 
+#[allow(clippy::field_reassign_with_default)]
 fn note_card_style(color: Option<Color>, selected: bool) -> container::Style {
     let mut style = container::Style::default();
 
@@ -510,6 +1554,29 @@ fn note_card_style(color: Option<Color>, selected: bool) -> container::Style {
     style
 }
 
+fn folder_button_style(theme: &Theme, status: ButtonStatus, active: bool) -> button::Style {
+    let palette = theme.extended_palette();
+    let background = if active {
+        Some(palette.primary.weak.color.into())
+    } else if matches!(status, ButtonStatus::Hovered) {
+        Some(palette.background.weak.color.into())
+    } else {
+        None
+    };
+
+    button::Style {
+        background,
+        text_color: if active {
+            palette.primary.weak.text
+        } else {
+            palette.background.base.text
+        },
+        border: border::rounded(6),
+        ..button::Style::default()
+    }
+}
+
+#[allow(clippy::unwrap_or_default)]
 fn color_bar_style(color: Option<Color>) -> container::Style {
     color
         .map(container::Style::from)
@@ -532,6 +1599,7 @@ fn swatch_style(color: Option<Color>, selected: bool) -> container::Style {
     style
 }
 
+#[allow(clippy::field_reassign_with_default)]
 fn color_button_style(selected: bool, status: ButtonStatus) -> button::Style {
     let mut style = button::Style::default();
     style.text_color = Color::from_rgb8(0xee, 0xee, 0xee);
@@ -546,3 +1614,38 @@ fn color_button_style(selected: bool, status: ButtonStatus) -> button::Style {
     };
     style
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(notes: &Notes) -> Vec<&str> {
+        notes.iter().map(|note| note.title.as_str()).collect()
+    }
+
+    #[test]
+    fn saving_keeps_pages_another_program_wrote() {
+        let _data = storage::use_test_data_dir();
+        let mut app = Notes::open("merge-tests");
+        app.upsert(Note::new("First".into(), String::new(), Vec::new()), None);
+
+        // The command line adds a page while the app is open...
+        let mut cli = Notes::open("merge-tests");
+        cli.upsert(Note::new("From the CLI".into(), String::new(), Vec::new()), None);
+
+        // ...and the app saves before its next look at the disk.
+        app.upsert(Note::new("Second".into(), String::new(), Vec::new()), None);
+        assert!(app.take_errors().is_empty());
+        assert_eq!(titles(&app), ["First", "From the CLI", "Second"]);
+
+        let disk = Notes::open("merge-tests");
+        assert_eq!(titles(&disk), ["First", "From the CLI", "Second"]);
+
+        // The app reports the page it picked up as changed.
+        let Some(Event::Reloaded { changed, removed }) = app.update(Message::CheckDisk) else {
+            panic!("expected the merged page to be reported");
+        };
+        assert_eq!(changed, [disk.iter().nth(1).unwrap().id]);
+        assert!(removed.is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use iced::widget::markdown::{self, Item, Settings, Style, Url};
+use iced::widget::{column, image};
+use iced::{Element, Theme};
+use std::path::PathBuf;
+
+use super::links;
+use crate::storage::storage;
+
+/// A rendered piece of a note. The markdown widget cannot draw images, so images on a
+/// line of their own are split out and shown with the image widget.
+#[derive(Debug, Clone)]
+pub enum Block {
+    Markdown(Vec<Item>),
+    Image(image::Handle),
+}
+
+/// Parses `markdown` into blocks, turning every `![alt](path)` line that points at a
+/// file on this machine into an image.
+pub fn parse(markdown: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut pending = String::new();
+    let mut in_fence = false;
+
+    for line in markdown.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+
+        match (!in_fence).then(|| local_image(line)).flatten() {
+            Some(path) => {
+                flush(&mut pending, &mut blocks);
+                blocks.push(Block::Image(image::Handle::from_path(path)));
+            }
+            None => pending.push_str(line),
+        }
+    }
+
+    flush(&mut pending, &mut blocks);
+    blocks
+}
+
+pub fn view<'a, Message: 'a>(
+    blocks: &'a [Block],
+    on_link: impl Fn(Url) -> Message + Copy + 'a,
+) -> Element<'a, Message> {
+    let style = Style::from_palette(Theme::KanagawaDragon.palette());
+
+    blocks
+        .iter()
+        .fold(column![].spacing(12), |column, block| match block {
+            Block::Markdown(items) => {
+                column.push(markdown::view(items, Settings::default(), style).map(on_link))
+            }
+            Block::Image(handle) => column.push(image(handle.clone())),
+        })
+        .into()
+}
+
+fn flush(pending: &mut String, blocks: &mut Vec<Block>) {
+    if !pending.trim().is_empty() {
+        blocks.push(Block::Markdown(markdown::parse(pending).collect()));
+    }
+    pending.clear();
+}
+
+/// The file a line consisting only of `![alt](destination)` shows, if it exists here.
+fn local_image(line: &str) -> Option<PathBuf> {
+    let inner = line.trim().strip_prefix("![")?.strip_suffix(')')?;
+    let (_, destination) = inner.split_once("](")?;
+
+    let destination = match destination.strip_prefix('<') {
+        Some(rest) => rest.split_once('>')?.0,
+        None => destination.split_whitespace().next()?,
+    };
+
+    let path = match Url::parse(destination) {
+        Ok(url) if url.scheme() == "file" => url.to_file_path().ok()?,
+        // Single-letter schemes are Windows drive letters.
+        Ok(url) if url.scheme().len() > 1 => return None,
+        _ => storage::data_dir().join(links::percent_decode(destination)),
+    };
+
+    path.is_file().then_some(path)
+}
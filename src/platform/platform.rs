@@ -0,0 +1,276 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use crate::storage::storage;
+
+/// Restricts a file dialog to files with one of the given extensions.
+#[derive(Debug, Clone, Copy)]
+pub struct FileFilter {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+}
+
+/// Hands a URL or file path to the operating system's default handler.
+pub fn open(target: impl AsRef<OsStr>) -> io::Result<()> {
+    let target = target.as_ref();
+
+    let mut command = if cfg!(target_os = "windows") {
+        // Not `cmd /C start`: cmd would act on `&`, `|`, `^` and `%` in the target.
+        let mut command = Command::new("rundll32");
+        command.args([OsStr::new("url.dll,FileProtocolHandler"), target]);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg(target);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(target);
+        command
+    };
+
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Connects standard output and error to the console of the terminal that started the
+/// app. On Windows the app is built without a console of its own, so the command line
+/// commands would otherwise print nowhere.
+pub fn attach_console() {
+    #[cfg(target_os = "windows")]
+    {
+        const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+        #[link(name = "kernel32")]
+        unsafe extern "system" {
+            fn AttachConsole(process_id: u32) -> i32;
+        }
+
+        // Fails when there is no parent console, e.g. when started from Explorer, and
+        // then there is nowhere to print anyway.
+        // SAFETY: AttachConsole takes a process id by value and touches no memory of ours.
+        unsafe {
+            AttachConsole(ATTACH_PARENT_PROCESS);
+        }
+    }
+}
+
+/// Asks the user for files with the system's file dialog. Blocks until the dialog is
+/// closed; cancelling it yields no files.
+pub fn pick_files(title: &str, filter: Option<FileFilter>) -> io::Result<Vec<PathBuf>> {
+    run_dialog(title, Dialog::Open(filter))
+}
+
+/// Asks the user where to save a file, suggesting `file_name`.
+pub fn save_file(title: &str, file_name: &str) -> io::Result<Option<PathBuf>> {
+    Ok(run_dialog(title, Dialog::Save(file_name))?
+        .into_iter()
+        .next())
+}
+
+/// Asks the user for a directory.
+pub fn pick_folder(title: &str) -> io::Result<Option<PathBuf>> {
+    Ok(run_dialog(title, Dialog::Folder)?.into_iter().next())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Dialog<'a> {
+    Open(Option<FileFilter>),
+    Save(&'a str),
+    Folder,
+}
+
+fn run_dialog(title: &str, dialog: Dialog<'_>) -> io::Result<Vec<PathBuf>> {
+    let output = if cfg!(target_os = "windows") {
+        let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+        let script = match dialog {
+            Dialog::Open(filter) => {
+                let filter = filter
+                    .map(|filter| {
+                        let patterns = filter
+                            .extensions
+                            .iter()
+                            .map(|ext| format!("*.{ext}"))
+                            .collect::<Vec<_>>()
+                            .join(";");
+                        format!(
+                            "$d.Filter = {};",
+                            quote(&format!("{}|{patterns}", filter.name))
+                        )
+                    })
+                    .unwrap_or_default();
+                format!(
+                    "$d = New-Object System.Windows.Forms.OpenFileDialog; \
+                     $d.Title = {}; $d.Multiselect = $true; {filter} \
+                     if ($d.ShowDialog() -eq 'OK') {{ $d.FileNames }}",
+                    quote(title)
+                )
+            }
+            Dialog::Save(file_name) => format!(
+                "$d = New-Object System.Windows.Forms.SaveFileDialog; \
+                 $d.Title = {}; $d.FileName = {}; \
+                 if ($d.ShowDialog() -eq 'OK') {{ $d.FileName }}",
+                quote(title),
+                quote(file_name)
+            ),
+            Dialog::Folder => format!(
+                "$d = New-Object System.Windows.Forms.FolderBrowserDialog; \
+                 $d.Description = {}; \
+                 if ($d.ShowDialog() -eq 'OK') {{ $d.SelectedPath }}",
+                quote(title)
+            ),
+        };
+        let script = format!("Add-Type -AssemblyName System.Windows.Forms; {script}");
+        run(Command::new("powershell").args(["-NoProfile", "-STA", "-Command", &script]))?
+    } else if cfg!(target_os = "macos") {
+        let quote = |value: &str| format!("\"{}\"", value.replace(['"', '\\'], "'"));
+        let script = match dialog {
+            Dialog::Open(_) => vec![
+                format!(
+                    "set picked to choose file with prompt {} with multiple selections allowed",
+                    quote(title)
+                ),
+                String::from("set out to \"\""),
+                String::from("repeat with f in picked"),
+                String::from("set out to out & POSIX path of f & linefeed"),
+                String::from("end repeat"),
+                String::from("return out"),
+            ],
+            Dialog::Save(file_name) => vec![format!(
+                "POSIX path of (choose file name with prompt {} default name {})",
+                quote(title),
+                quote(file_name)
+            )],
+            Dialog::Folder => vec![format!(
+                "POSIX path of (choose folder with prompt {})",
+                quote(title)
+            )],
+        };
+        let mut command = Command::new("osascript");
+        for line in &script {
+            command.args(["-e", line]);
+        }
+        run(&mut command)?
+    } else {
+        let mut zenity = Command::new("zenity");
+        zenity.args(["--file-selection", "--title", title]);
+        match dialog {
+            Dialog::Open(filter) => {
+                zenity.args(["--multiple", "--separator=\n"]);
+                if let Some(filter) = filter {
+                    let patterns = filter
+                        .extensions
+                        .iter()
+                        .map(|ext| format!("*.{ext}"))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    zenity.arg(format!("--file-filter={} | {patterns}", filter.name));
+                }
+            }
+            Dialog::Save(file_name) => {
+                zenity.args(["--save", "--confirm-overwrite", "--filename", file_name]);
+            }
+            Dialog::Folder => {
+                zenity.arg("--directory");
+            }
+        }
+
+        match run(&mut zenity) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let mut kdialog = Command::new("kdialog");
+                kdialog.args(["--title", title]);
+                match dialog {
+                    Dialog::Open(_) => {
+                        kdialog.args(["--getopenfilename", ".", "--multiple", "--separate-output"]);
+                    }
+                    Dialog::Save(file_name) => {
+                        kdialog.args(["--getsavefilename", file_name]);
+                    }
+                    Dialog::Folder => {
+                        kdialog.args(["--getexistingdirectory", "."]);
+                    }
+                }
+                run(&mut kdialog)?
+            }
+            result => result?,
+        }
+    };
+
+    // Every dialog exits unsuccessfully when cancelled.
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// The image on the clipboard as PNG data, if there is one.
+pub fn clipboard_image() -> io::Result<Option<Vec<u8>>> {
+    if cfg!(target_os = "windows") {
+        // A new file of our own, so nothing another user placed there is read or
+        // written through; PowerShell saves over it when there is an image.
+        let target = storage::unique_temp_path("clipboard.png");
+        fs::File::create_new(&target)?;
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $image = [System.Windows.Forms.Clipboard]::GetImage(); \
+             if ($image) {{ $image.Save('{}', [System.Drawing.Imaging.ImageFormat]::Png) }}",
+            target.display().to_string().replace('\'', "''")
+        );
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-STA", "-Command", &script]);
+        let saved = run(&mut command).and_then(|_| fs::read(&target));
+        let _ = fs::remove_file(&target);
+
+        return saved.map(|bytes| (!bytes.is_empty()).then_some(bytes));
+    }
+
+    if cfg!(target_os = "macos") {
+        // Prints the data as «data PNGf89504E47...».
+        let mut command = Command::new("osascript");
+        command.args(["-e", "the clipboard as «class PNGf»"]);
+        let output = run(&mut command)?;
+        let text = String::from_utf8_lossy(&output.stdout);
+        let hex = text
+            .trim()
+            .trim_start_matches("«data PNGf")
+            .trim_end_matches('»');
+        return Ok(output.status.success().then(|| decode_hex(hex)).flatten());
+    }
+
+    let output = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        run(Command::new("wl-paste").args(["--no-newline", "--type", "image/png"]))?
+    } else {
+        run(Command::new("xclip").args(["-selection", "clipboard", "-target", "image/png", "-o"]))?
+    };
+
+    let is_png = output.stdout.starts_with(b"\x89PNG");
+    Ok((output.status.success() && is_png).then_some(output.stdout))
+}
+
+fn run(command: &mut Command) -> io::Result<Output> {
+    command.stdin(Stdio::null()).stderr(Stdio::null()).output()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect()
+}
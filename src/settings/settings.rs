@@ -1,11 +1,24 @@
-use iced::widget::{button, column, container, horizontal_space, pick_list, row, slider, text, vertical_space};
-use iced::{Alignment, Element, Font, Length, Theme};
+use iced::widget::{
+    button, checkbox, column, container, horizontal_space, pick_list, row, scrollable, slider,
+    text, text_input, vertical_space,
+};
+use iced::{Alignment, Color, Element, Font, Length, Point, Size, Theme, border};
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io::{self, BufReader, BufWriter};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+use crate::notes::colors::{self, CustomColor, NoteColor, Palette};
+use crate::notes::notes::NoteId;
+use crate::storage::storage::{self, StorageError};
+
 const STORAGE_FILE: &str = "settings.json";
+/// Smallest size a window is restored at.
+const MIN_WINDOW_SIZE: Size = Size::new(320.0, 240.0);
+/// The largest size and position a restored window may have, in logical pixels.
+const MAX_DESKTOP_EXTENT: f32 = 16384.0;
+/// How much of a restored window, measured from its top-left corner, must stay right of
+/// and below the origin.
+const GRAB_MARGIN: f32 = 64.0;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ThemeOption {
@@ -81,19 +94,134 @@ impl std::fmt::Display for FontOption {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Message {
     ThemeChanged(ThemeOption),
     FontChanged(FontOption),
     FontSizeChanged(u16),
+    ConfirmLinksToggled(bool),
+    ScriptingApiToggled(bool),
+    ColorNameChanged(String),
+    ColorHexChanged(String),
+    ColorChannelChanged { channel: usize, value: u8 },
+    AddColor,
+    RemoveColor(usize),
+    ShowShortcuts,
     Back,
 }
 
+/// The "new color" form in the settings screen.
+#[derive(Debug, Clone, Default)]
+struct ColorForm {
+    name: String,
+    hex: String,
+    rgb: [u8; 3],
+}
+
+/// Where a window was and how big it was, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    /// Unknown on platforms that do not tell apps where their windows are, like Wayland.
+    #[serde(default)]
+    pub position: Option<(f32, f32)>,
+}
+
+impl WindowGeometry {
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// The geometry to restore a window with: sizes are kept within reason, and
+    /// positions that cannot be right, like the -32000 Windows reports for minimized
+    /// windows, are dropped. Kept positions are pulled onto the monitor the window
+    /// opens on with [`Self::position_on`].
+    pub fn sanitized(&self) -> Option<Self> {
+        if !self.width.is_finite() || !self.height.is_finite() {
+            return None;
+        }
+        let width = self.width.clamp(MIN_WINDOW_SIZE.width, MAX_DESKTOP_EXTENT);
+        let height = self.height.clamp(MIN_WINDOW_SIZE.height, MAX_DESKTOP_EXTENT);
+
+        let position = self.position.filter(|&(x, y)| {
+            (GRAB_MARGIN - width..=MAX_DESKTOP_EXTENT - GRAB_MARGIN).contains(&x)
+                && (-GRAB_MARGIN / 2.0..=MAX_DESKTOP_EXTENT - GRAB_MARGIN).contains(&y)
+        });
+
+        Some(Self {
+            width,
+            height,
+            position,
+        })
+    }
+
+    pub fn position(&self) -> Option<Point> {
+        self.position.map(|(x, y)| Point::new(x, y))
+    }
+
+    /// Where to open the window on a monitor of the given size, relative to its top-left
+    /// corner: the saved position, moved in just enough for the whole window to be on
+    /// the monitor, or its top-left part when it is larger. A window saved on a screen
+    /// that is gone opens at the edge of this one.
+    pub fn position_on(&self, monitor: Size) -> Option<Point> {
+        let (x, y) = self.position?;
+        let right = (monitor.width - self.width).max(0.0);
+        let bottom = (monitor.height - self.height).max(0.0);
+
+        Some(Point::new(x.clamp(0.0, right), y.clamp(0.0, bottom)))
+    }
+}
+
+/// The windows that were open when the app was last closed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindowSession {
+    #[serde(default)]
+    pub main: Option<WindowGeometry>,
+    /// Note windows of the notebook that was open, in the order they were opened.
+    #[serde(default)]
+    pub notes: Vec<(NoteId, WindowGeometry)>,
+    #[serde(default)]
+    pub capture: Option<WindowGeometry>,
+    /// Pages open in tabs in the main window, from left to right.
+    #[serde(default)]
+    pub tabs: Vec<NoteId>,
+    /// The page whose tab was in front, or `None` when the notebook was.
+    #[serde(default)]
+    pub active_tab: Option<NoteId>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SettingsState {
     selected_theme: ThemeOption,
     selected_font: FontOption,
     font_size: u16,
+    #[serde(default)]
+    last_notebook: Option<String>,
+    #[serde(default)]
+    custom_colors: Vec<CustomColor>,
+    #[serde(default)]
+    color_filter: Vec<NoteColor>,
+    /// Whether to ask before handing a clicked web or mail link to another program.
+    /// Links to files always ask.
+    #[serde(default = "confirm_external_links_default")]
+    confirm_external_links: bool,
+    /// Whether scripts on this computer may reach the notebook over the local API.
+    #[serde(default)]
+    scripting_api: bool,
+    #[serde(default)]
+    windows: WindowSession,
+    /// Keyboard shortcuts that differ from the defaults, by action name.
+    #[serde(default)]
+    shortcuts: BTreeMap<String, String>,
+    #[serde(skip, default)]
+    palette: Palette,
+    #[serde(skip, default)]
+    color_form: ColorForm,
+    #[serde(skip, default)]
+    unsaved: Option<StorageError>,
+    #[serde(skip, default)]
+    errors: Vec<StorageError>,
 }
 
 impl Default for SettingsState {
@@ -108,15 +236,37 @@ impl SettingsState {
             selected_theme: ThemeOption::KanagawaDragon,
             selected_font: FontOption::Sans,
             font_size: 16,
+            last_notebook: None,
+            custom_colors: Vec::new(),
+            color_filter: Vec::new(),
+            confirm_external_links: true,
+            scripting_api: false,
+            windows: WindowSession::default(),
+            shortcuts: BTreeMap::new(),
+            palette: Palette::default(),
+            color_form: ColorForm {
+                hex: colors::to_hex([0x80, 0x80, 0x80]),
+                rgb: [0x80, 0x80, 0x80],
+                ..ColorForm::default()
+            },
+            unsaved: None,
+            errors: Vec::new(),
         }
     }
 
     fn load() -> Self {
-        match Self::load_from_disk() {
-            Ok(state) => state,
+        match storage::load_json::<Self>(&Self::storage_path()) {
+            Ok(Some(mut state)) => {
+                state.palette = Palette::new(&state.custom_colors);
+                state.prune_color_filter();
+                state.color_form = Self::default_values().color_form;
+                state
+            }
+            Ok(None) => Self::default_values(),
             Err(error) => {
-                eprintln!("Failed to load settings from disk: {error}");
-                Self::default_values()
+                let mut state = Self::default_values();
+                state.errors.push(error);
+                state
             }
         }
     }
@@ -133,6 +283,68 @@ impl SettingsState {
         self.font_size
     }
 
+    /// Colors notes can be labelled with, including the user's own.
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Key of the notebook that was open when the app was last used.
+    pub fn last_notebook(&self) -> Option<&str> {
+        self.last_notebook.as_deref()
+    }
+
+    pub fn set_last_notebook(&mut self, key: &str) {
+        if self.last_notebook.as_deref() != Some(key) {
+            self.last_notebook = Some(key.to_string());
+            self.persist();
+        }
+    }
+
+    /// The `shortcuts` table, which is only ever edited in settings.json.
+    pub fn shortcuts(&self) -> &BTreeMap<String, String> {
+        &self.shortcuts
+    }
+
+    /// The windows to bring back when the app starts.
+    pub fn windows(&self) -> &WindowSession {
+        &self.windows
+    }
+
+    pub fn set_windows(&mut self, windows: WindowSession) {
+        if self.windows != windows {
+            self.windows = windows;
+            self.persist();
+        }
+    }
+
+    /// Colors the notebook list was filtered by when the app was last used.
+    pub fn color_filter(&self) -> &[NoteColor] {
+        &self.color_filter
+    }
+
+    pub fn set_color_filter(&mut self, mut colors: Vec<NoteColor>) {
+        colors.sort_by(|a, b| a.name().cmp(b.name()));
+        if self.color_filter != colors {
+            self.color_filter = colors;
+            self.persist();
+        }
+    }
+
+    /// Drops colors the palette no longer has from the filter, so notes are not
+    /// filtered by a color that cannot be picked or cleared one by one.
+    fn prune_color_filter(&mut self) {
+        let palette = &self.palette;
+        self.color_filter.retain(|color| palette.resolve(color) == *color);
+    }
+
+    pub fn confirm_external_links(&self) -> bool {
+        self.confirm_external_links
+    }
+
+    pub fn scripting_api(&self) -> bool {
+        self.scripting_api
+    }
+
     pub fn update(&mut self, message: Message) {
         let mut changed = false;
 
@@ -156,9 +368,53 @@ impl SettingsState {
                     changed = true;
                 }
             }
-            Message::Back => {
-                
+            Message::ConfirmLinksToggled(confirm) => {
+                if self.confirm_external_links != confirm {
+                    self.confirm_external_links = confirm;
+                    changed = true;
+                }
+            }
+            Message::ScriptingApiToggled(enabled) => {
+                if self.scripting_api != enabled {
+                    self.scripting_api = enabled;
+                    changed = true;
+                }
+            }
+            Message::ColorNameChanged(name) => {
+                self.color_form.name = name;
+            }
+            Message::ColorHexChanged(hex) => {
+                if let Some(rgb) = colors::parse_hex(&hex) {
+                    self.color_form.rgb = rgb;
+                }
+                self.color_form.hex = hex;
+            }
+            Message::ColorChannelChanged { channel, value } => {
+                if let Some(slot) = self.color_form.rgb.get_mut(channel) {
+                    *slot = value;
+                    self.color_form.hex = colors::to_hex(self.color_form.rgb);
+                }
+            }
+            Message::AddColor => {
+                if let Some(name) = self.new_color_name() {
+                    self.custom_colors.push(CustomColor {
+                        name,
+                        hex: colors::to_hex(self.color_form.rgb),
+                    });
+                    self.color_form.name.clear();
+                    self.palette = Palette::new(&self.custom_colors);
+                    changed = true;
+                }
+            }
+            Message::RemoveColor(index) => {
+                if index < self.custom_colors.len() {
+                    self.custom_colors.remove(index);
+                    self.palette = Palette::new(&self.custom_colors);
+                    self.prune_color_filter();
+                    changed = true;
+                }
             }
+            Message::ShowShortcuts | Message::Back => {}
         }
 
         if changed {
@@ -211,46 +467,188 @@ impl SettingsState {
             .align_y(Alignment::Center),
             vertical_space().height(Length::Fixed(16.0)),
             preview,
+            vertical_space().height(Length::Fixed(16.0)),
+            text("Links").size(24),
+            checkbox(
+                "Ask before opening web links in other programs (files always ask)",
+                self.confirm_external_links,
+            )
+            .on_toggle(Message::ConfirmLinksToggled),
+            vertical_space().height(Length::Fixed(16.0)),
+            text("Scripting").size(24),
+            checkbox(
+                "Let scripts on this computer read and add pages",
+                self.scripting_api,
+            )
+            .on_toggle(Message::ScriptingApiToggled),
+            text("The address and access token are written to api.json while this is on.")
+                .size(14),
+            vertical_space().height(Length::Fixed(16.0)),
+            row![
+                text("Keyboard shortcuts").size(24),
+                horizontal_space().width(Length::Fill),
+                button(text("Show all")).on_press(Message::ShowShortcuts),
+            ]
+            .align_y(Alignment::Center),
+            vertical_space().height(Length::Fixed(16.0)),
+            self.colors_view(),
         ]
         .spacing(16)
         .width(Length::Fill);
 
-        container(content).padding(16).into()
+        scrollable(container(content).padding(16)).into()
+    }
+
+    /// The form's name, trimmed, if it can be added as a new color.
+    fn new_color_name(&self) -> Option<String> {
+        let name = self.color_form.name.trim();
+        let taken = Palette::is_built_in(name)
+            || self.custom_colors.iter().any(|color| color.name == name);
+
+        (!name.is_empty() && !taken).then(|| name.to_string())
     }
 
-    fn persist(&self) {
-        if let Err(error) = self.save_to_disk() {
-            eprintln!("Failed to save settings: {error}");
+    fn colors_view(&self) -> Element<'_, Message> {
+        let mut list = column![text("Note colors").size(24)].spacing(10);
+
+        if self.custom_colors.is_empty() {
+            list = list.push(text("Add your own colors to label notes with.").size(14));
+        }
+
+        for (index, color) in self.custom_colors.iter().enumerate() {
+            let swatch = color.rgb().map(|[r, g, b]| Color::from_rgb8(r, g, b));
+            list = list.push(
+                row![
+                    swatch_box(swatch),
+                    text(color.name.as_str()).width(Length::Fixed(160.0)),
+                    text(color.hex.as_str()).size(14),
+                    horizontal_space().width(Length::Fill),
+                    button(text("Remove").size(14)).on_press(Message::RemoveColor(index)),
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        let [r, g, b] = self.color_form.rgb;
+        let channels = ["R", "G", "B"].into_iter().enumerate().fold(
+            column![].spacing(6),
+            |sliders, (channel, label)| {
+                let value = self.color_form.rgb[channel];
+                sliders.push(
+                    row![
+                        text(label).width(Length::Fixed(16.0)),
+                        slider(0.0..=255.0, f32::from(value), move |value| {
+                            Message::ColorChannelChanged {
+                                channel,
+                                value: value.round() as u8,
+                            }
+                        }),
+                        text(value.to_string()).width(Length::Fixed(32.0)),
+                    ]
+                    .spacing(12)
+                    .align_y(Alignment::Center),
+                )
+            },
+        );
+
+        let mut add = button(text("Add color"));
+        if self.new_color_name().is_some() {
+            add = add.on_press(Message::AddColor);
         }
+
+        list.push(
+            column![
+                row![
+                    swatch_box(Some(Color::from_rgb8(r, g, b))),
+                    text_input("Color name", &self.color_form.name)
+                        .on_input(Message::ColorNameChanged)
+                        .on_submit(Message::AddColor)
+                        .padding(8),
+                    text_input("#rrggbb", &self.color_form.hex)
+                        .on_input(Message::ColorHexChanged)
+                        .padding(8)
+                        .width(Length::Fixed(110.0)),
+                    add,
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center),
+                channels,
+            ]
+            .spacing(10),
+        )
+        .into()
     }
 
-    fn save_to_disk(&self) -> io::Result<()> {
-        let path = Self::storage_path();
-        if let Some(parent) = path.parent() {
-            if !parent.as_os_str().is_empty() {
-                fs::create_dir_all(parent)?;
+    fn persist(&mut self) {
+        match storage::save_json(&Self::storage_path(), self) {
+            Ok(()) => self.unsaved = None,
+            Err(error) => {
+                self.unsaved = Some(error.clone());
+                self.errors.push(error);
             }
         }
+    }
 
-        let file = File::create(path)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self)
-            .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+    fn storage_path() -> PathBuf {
+        storage::data_dir().join(STORAGE_FILE)
     }
 
-    fn load_from_disk() -> io::Result<Self> {
-        let path = Self::storage_path();
-        if !path.exists() {
-            return Ok(Self::default_values());
-        }
+    /// Storage failures that happened since the last call, oldest first.
+    pub fn take_errors(&mut self) -> Vec<StorageError> {
+        std::mem::take(&mut self.errors)
+    }
 
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        serde_json::from_reader(reader)
-            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    /// The last failed write, if the settings on screen have not reached disk yet.
+    pub fn unsaved(&self) -> Option<&StorageError> {
+        self.unsaved.as_ref()
     }
 
-    fn storage_path() -> PathBuf {
-        PathBuf::from(STORAGE_FILE)
+    /// Attempts to write the settings again after a failed save.
+    pub fn retry_save(&mut self) -> bool {
+        self.persist();
+        self.unsaved.is_none()
+    }
+}
+
+fn swatch_box<'a>(color: Option<Color>) -> Element<'a, Message> {
+    container(vertical_space().height(Length::Fixed(20.0)))
+        .width(Length::Fixed(32.0))
+        .style(move |_| {
+            container::Style::default()
+                .background(color.unwrap_or(Color::from_rgb8(0x44, 0x44, 0x44)))
+                .border(border::rounded(6))
+        })
+        .into()
+}
+
+fn confirm_external_links_default() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_positions_are_pulled_onto_the_monitor() {
+        let monitor = Size::new(1920.0, 1080.0);
+        let at = |x, y| WindowGeometry {
+            width: 800.0,
+            height: 600.0,
+            position: Some((x, y)),
+        };
+
+        assert_eq!(at(100.0, 50.0).position_on(monitor), Some(Point::new(100.0, 50.0)));
+        // Saved on a second screen to the right that is no longer there.
+        assert_eq!(at(2500.0, 200.0).position_on(monitor), Some(Point::new(1120.0, 200.0)));
+        assert_eq!(at(-300.0, -20.0).position_on(monitor), Some(Point::new(0.0, 0.0)));
+        // Larger than the monitor: its top-left corner stays on it.
+        let large = WindowGeometry {
+            width: 2400.0,
+            height: 1400.0,
+            position: Some((300.0, 100.0)),
+        };
+        assert_eq!(large.position_on(monitor), Some(Point::new(0.0, 0.0)));
     }
 }
@@ -0,0 +1,185 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(test)]
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A failure while reading or writing one of the JSON files the app keeps on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageError {
+    /// The file exists but could not be opened or read.
+    Read { path: PathBuf, reason: String },
+    /// The file was read but its contents are not valid for the expected format.
+    Corrupt { path: PathBuf, reason: String },
+    /// The file (or its parent directory) could not be written.
+    Write { path: PathBuf, reason: String },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Read { path, reason } => {
+                write!(f, "Could not read {}: {reason}", path.display())
+            }
+            StorageError::Corrupt { path, reason } => {
+                write!(
+                    f,
+                    "{} is damaged and was not loaded: {reason}",
+                    path.display()
+                )
+            }
+            StorageError::Write { path, reason } => {
+                write!(f, "Could not save {}: {reason}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// Reads `path` as JSON. A missing file is not an error and yields `Ok(None)`.
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, StorageError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let file = File::open(path).map_err(|error| StorageError::Read {
+        path: path.to_path_buf(),
+        reason: error.to_string(),
+    })?;
+
+    serde_json::from_reader(BufReader::new(file))
+        .map(Some)
+        .map_err(|error| StorageError::Corrupt {
+            path: path.to_path_buf(),
+            reason: error.to_string(),
+        })
+}
+
+/// Writes `value` to `path` as pretty-printed JSON, creating parent directories as needed.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), StorageError> {
+    write_json(
+        path,
+        value,
+        OpenOptions::new().write(true).create(true).truncate(true),
+    )
+}
+
+/// Like [`save_json`], for files holding secrets: the file is created afresh, readable
+/// only by the current user on Unix, so it is never visible to others even briefly.
+pub fn save_private_json<T: Serialize>(path: &Path, value: &T) -> Result<(), StorageError> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != ErrorKind::NotFound => {
+            return Err(StorageError::Write {
+                path: path.to_path_buf(),
+                reason: error.to_string(),
+            });
+        }
+        _ => {}
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    write_json(path, value, &options)
+}
+
+fn write_json<T: Serialize>(
+    path: &Path,
+    value: &T,
+    options: &OpenOptions,
+) -> Result<(), StorageError> {
+    let write_error = |reason: String| StorageError::Write {
+        path: path.to_path_buf(),
+        reason,
+    };
+
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).map_err(|error| write_error(error.to_string()))?;
+    }
+
+    let file = options
+        .open(path)
+        .map_err(|error| write_error(error.to_string()))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, value)
+        .map_err(|error| write_error(error.to_string()))?;
+    writer
+        .flush()
+        .map_err(|error| write_error(error.to_string()))
+}
+
+/// Directory the app keeps its files in. Data paths are relative to it, and so are
+/// relative links inside notes.
+pub fn data_dir() -> PathBuf {
+    #[cfg(test)]
+    {
+        let shared = TEST_DATA_DIR.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(dir) = &shared.0 {
+            return dir.clone();
+        }
+    }
+
+    std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
+}
+
+/// A path in the system's temporary directory that no other call, in this process or
+/// any other, has handed out, for a file or directory named after `name`. Create it
+/// with `create_new` or `fs::create_dir`, which fail rather than reuse what is there.
+pub fn unique_temp_path(name: &str) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or_default();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!(
+        "faitout-{}-{nanos}-{count}-{name}",
+        std::process::id()
+    ))
+}
+
+/// The data directory of the tests holding a [`TestDataDir`], and how many do.
+#[cfg(test)]
+static TEST_DATA_DIR: Mutex<(Option<PathBuf>, usize)> = Mutex::new((None, 0));
+
+/// Moves the tests into an empty data directory in the system's temporary directory,
+/// so tests that save never touch the files next to the sources. Tests running at the
+/// same time share it, and it is removed when the last of them drops its guard.
+#[cfg(test)]
+#[must_use = "the data directory is removed when the guard is dropped"]
+pub fn use_test_data_dir() -> TestDataDir {
+    let mut shared = TEST_DATA_DIR.lock().unwrap_or_else(PoisonError::into_inner);
+    if shared.0.is_none() {
+        let dir = unique_temp_path("tests");
+        fs::create_dir(&dir).expect("test data directory can be created");
+        shared.0 = Some(dir);
+    }
+    shared.1 += 1;
+    TestDataDir(())
+}
+
+/// Keeps the tests' data directory from [`use_test_data_dir`] in use.
+#[cfg(test)]
+pub struct TestDataDir(());
+
+#[cfg(test)]
+impl Drop for TestDataDir {
+    fn drop(&mut self) {
+        let mut shared = TEST_DATA_DIR.lock().unwrap_or_else(PoisonError::into_inner);
+        shared.1 -= 1;
+        if shared.1 == 0
+            && let Some(dir) = shared.0.take()
+        {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use iced::widget::{button, column, container, horizontal_space, row, text};
+use iced::{Alignment, Element, Length, Subscription, Theme, border, time};
+use std::time::{Duration, Instant};
+
+const INFO_TIMEOUT: Duration = Duration::from_secs(4);
+const ERROR_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+impl Level {
+    fn timeout(self) -> Duration {
+        match self {
+            Level::Info => INFO_TIMEOUT,
+            Level::Error => ERROR_TIMEOUT,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Toast {
+    id: u64,
+    level: Level,
+    text: String,
+    shown_at: Instant,
+}
+
+#[derive(Debug, Default)]
+pub struct Toasts {
+    items: Vec<Toast>,
+    next_id: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Dismiss(u64),
+    Tick(Instant),
+}
+
+impl Toasts {
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(Level::Info, text.into());
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(Level::Error, text.into());
+    }
+
+    fn push(&mut self, level: Level, text: String) {
+        let now = Instant::now();
+
+        // A repeated failure refreshes the toast already on screen instead of stacking copies.
+        if let Some(existing) = self
+            .items
+            .iter_mut()
+            .find(|toast| toast.level == level && toast.text == text)
+        {
+            existing.shown_at = now;
+            return;
+        }
+
+        self.items.push(Toast {
+            id: self.next_id,
+            level,
+            text,
+            shown_at: now,
+        });
+        self.next_id += 1;
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::Dismiss(id) => self.items.retain(|toast| toast.id != id),
+            Message::Tick(now) => self
+                .items
+                .retain(|toast| now.duration_since(toast.shown_at) < toast.level.timeout()),
+        }
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        if self.items.is_empty() {
+            Subscription::none()
+        } else {
+            time::every(Duration::from_secs(1)).map(Message::Tick)
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let list = self.items.iter().fold(column![].spacing(8), |list, toast| {
+            let level = toast.level;
+            let card = container(
+                row![
+                    text(toast.text.as_str()).size(14).width(Length::Fill),
+                    button(text("x").size(14))
+                        .on_press(Message::Dismiss(toast.id))
+                        .padding([2, 8]),
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center),
+            )
+            .width(Length::Fixed(360.0))
+            .padding(12)
+            .style(move |theme| toast_style(theme, level));
+
+            list.push(card)
+        });
+
+        container(row![horizontal_space(), list])
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_bottom(Length::Fill)
+            .padding(16)
+            .into()
+    }
+}
+
+fn toast_style(theme: &Theme, level: Level) -> container::Style {
+    let palette = theme.extended_palette();
+    let pair = match level {
+        Level::Info => palette.background.strong,
+        Level::Error => palette.danger.base,
+    };
+
+    container::Style::default()
+        .background(pair.color)
+        .color(pair.text)
+        .border(border::rounded(8))
+}